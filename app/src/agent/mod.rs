@@ -0,0 +1,88 @@
+//! Tool-calling loop on top of `LlmBackend::chat_with_tools`.
+
+mod tools;
+
+pub use tools::{ReadFileTool, SearchCodeTool, Tool};
+
+use crate::backend::LlmBackend;
+use crate::error::{Error, Result};
+use crate::ollama_client::structured::validate;
+use crate::ollama_client::{ChatMessage, GenerateOptions, ToolCall};
+
+/// model turns `Agent::run` allows before giving up
+pub const DEFAULT_MAX_STEPS: usize = 8;
+
+/// Runs a conversation, executing the tools the model asks for and feeding
+/// their output back until it answers without calling any.
+pub struct Agent<'a> {
+    client: &'a dyn LlmBackend,
+    model: String,
+    tools: Vec<Box<dyn Tool>>,
+    options: GenerateOptions,
+    max_steps: usize,
+}
+
+impl<'a> Agent<'a> {
+    pub fn new(client: &'a dyn LlmBackend, model: &str) -> Self {
+        Self {
+            client,
+            model: model.to_string(),
+            tools: Vec::new(),
+            options: GenerateOptions::default(),
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+
+    pub fn with_tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.tools.push(Box::new(tool));
+        self
+    }
+
+    pub fn with_options(mut self, options: GenerateOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Maximum model turns per `run`, counting the one that gives the answer.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Continue `messages` until the model answers, appending every assistant
+    /// turn and tool result to it, and return the final answer.
+    pub async fn run(&self, messages: &mut Vec<ChatMessage>) -> Result<ChatMessage> {
+        let specs: Vec<_> = self.tools.iter().map(|tool| tool.spec()).collect();
+
+        for _ in 0..self.max_steps {
+            let reply = self.client.chat_with_tools(&self.model, messages, &specs, &self.options).await?;
+            let calls = reply.tool_calls.clone();
+            messages.push(reply.clone());
+
+            if calls.is_empty() {
+                return Ok(reply);
+            }
+            for call in &calls {
+                let output = self.call_tool(call).await;
+                messages.push(ChatMessage::tool_result(call, output));
+            }
+        }
+
+        Err(Error::StepLimit(self.max_steps))
+    }
+
+    /// failures go back to the model as the tool's output so it can recover
+    async fn call_tool(&self, call: &ToolCall) -> String {
+        let Some(tool) = self.tools.iter().find(|tool| tool.name() == call.function.name) else {
+            return format!("error: unknown tool '{}'", call.function.name);
+        };
+        if let Err(e) = validate(&call.function.arguments, &tool.parameters()) {
+            return format!("error: invalid arguments: {}", e);
+        }
+
+        match tool.invoke(call.function.arguments.clone()).await {
+            Ok(output) => output,
+            Err(e) => format!("error: {}", e),
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::embedder::Embedder;
+use crate::embeddings_controller::relative_path;
+use crate::error::{Error, Result};
+use crate::lancedb::{LanceDbClient, QueryOptions};
+use crate::ollama_client::ToolSpec;
+
+/// Something the model can call during `Agent::run`.
+pub trait Tool: Send + Sync {
+    /// unique name the model calls the tool by
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// JSON schema of the arguments object; calls are validated against it
+    fn parameters(&self) -> Value;
+
+    /// Run the tool; the returned text is sent back to the model.
+    fn invoke(&self, arguments: Value) -> BoxFuture<'_, Result<String>>;
+
+    fn spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: self.name().to_string(),
+            description: self.description().to_string(),
+            parameters: self.parameters(),
+        }
+    }
+}
+
+/// shared with the blocking pool, where inference runs
+type EmbedFn = Arc<dyn Fn(&str) -> Result<Vec<f32>> + Send + Sync>;
+
+/// `search_code`: semantic search over the indexed code.
+/// Returned files are marked as accessed, since they go into the prompt.
+pub struct SearchCodeTool {
+    store: Arc<LanceDbClient>,
+    embed: EmbedFn,
+    options: QueryOptions,
+    /// results returned when the model does not ask for a number
+    default_limit: usize,
+}
+
+#[derive(Deserialize)]
+struct SearchArgs {
+    query: String,
+    limit: Option<usize>,
+}
+
+impl SearchCodeTool {
+    pub fn new(store: Arc<LanceDbClient>, embedder: Embedder) -> Self {
+        let embedder = Mutex::new(embedder);
+        Self::with_embed_fn(store, move |query| {
+            embedder.lock().map_err(|_| Error::WorkerPanicked)?.embed(query)
+        })
+    }
+
+    /// Search with query vectors from `embed` instead of the UniXcoder embedder.
+    pub fn with_embed_fn<F>(store: Arc<LanceDbClient>, embed: F) -> Self
+    where
+        F: Fn(&str) -> Result<Vec<f32>> + Send + Sync + 'static,
+    {
+        Self {
+            store,
+            embed: Arc::new(embed),
+            options: QueryOptions { exclude_generated: true, ..Default::default() },
+            default_limit: 5,
+        }
+    }
+
+    pub fn with_options(mut self, options: QueryOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    async fn search(&self, args: SearchArgs) -> Result<String> {
+        let embed = Arc::clone(&self.embed);
+        let query = args.query;
+        let embedding = tokio::task::spawn_blocking(move || embed(&query))
+            .await
+            .map_err(|_| Error::WorkerPanicked)??;
+        let limit = args.limit.unwrap_or(self.default_limit).clamp(1, 20);
+        let hits = self.store.query_similar_with(&embedding, limit, &self.options).await?;
+
+        if hits.is_empty() {
+            return Ok("no matching code".to_string());
+        }
+        let paths: Vec<String> = hits.iter().map(|hit| hit.path.clone()).collect();
+        self.store.touch_embeddings(&paths).await?;
+
+        let results: Vec<String> = hits
+            .iter()
+            .map(|hit| {
+                let header = format!("{} ({}, {} lines)", hit.path, hit.language, hit.line_count);
+                match hit.content_preview.as_deref().filter(|p| !p.is_empty()) {
+                    Some(preview) => format!("{}\n{}", header, preview),
+                    None => header,
+                }
+            })
+            .collect();
+        Ok(results.join("\n\n"))
+    }
+}
+
+impl Tool for SearchCodeTool {
+    fn name(&self) -> &str {
+        "search_code"
+    }
+
+    fn description(&self) -> &str {
+        "Find source files related to a natural-language query or code snippet."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "what the code does or looks like" },
+                "limit": { "type": "integer", "description": "maximum number of files" }
+            },
+            "required": ["query"]
+        })
+    }
+
+    fn invoke(&self, arguments: Value) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move {
+            let args: SearchArgs = serde_json::from_value(arguments)?;
+            self.search(args).await
+        })
+    }
+}
+
+/// `read_file`: the contents of a file under `root`.
+pub struct ReadFileTool {
+    root: PathBuf,
+    /// longer output is cut off, so one file can't fill the context window
+    max_bytes: usize,
+    /// index whose `last_accessed` is bumped for every file read
+    store: Option<Arc<LanceDbClient>>,
+}
+
+#[derive(Deserialize)]
+struct ReadArgs {
+    path: String,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+}
+
+impl ReadFileTool {
+    pub fn new(root: &Path) -> Self {
+        Self { root: root.to_path_buf(), max_bytes: 32 * 1024, store: None }
+    }
+
+    /// Mark files read through the tool as accessed in `store`.
+    pub fn with_store(mut self, store: Arc<LanceDbClient>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// the selected text and the file's path as stored in the index;
+    /// blocking, so run it on the blocking pool
+    fn read(root: &Path, max_bytes: usize, args: ReadArgs) -> Result<(String, String)> {
+        let root = root.canonicalize()?;
+        let path = root
+            .join(&args.path)
+            .canonicalize()
+            .map_err(|e| Error::InvalidInput(format!("cannot read {}: {}", args.path, e)))?;
+        if !path.starts_with(&root) {
+            return Err(Error::InvalidInput(format!("{} is outside the repository", args.path)));
+        }
+
+        let content = fs::read_to_string(&path)?;
+        let start = args.start_line.unwrap_or(1).max(1);
+        let end = args.end_line.unwrap_or(usize::MAX);
+        let mut selected: String = content
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| (start..=end).contains(&(i + 1)))
+            .map(|(_, line)| format!("{}\n", line))
+            .collect();
+
+        if selected.len() > max_bytes {
+            let mut cut = max_bytes;
+            while !selected.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            selected.truncate(cut);
+            selected.push_str("\n[truncated]");
+        }
+        Ok((selected, relative_path(&root, &path)))
+    }
+}
+
+impl Tool for ReadFileTool {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        "Read a file from the repository, optionally limited to a range of lines."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "path relative to the repository root" },
+                "start_line": { "type": "integer", "description": "first line to return, 1-based" },
+                "end_line": { "type": "integer", "description": "last line to return, inclusive" }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn invoke(&self, arguments: Value) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async move {
+            let args: ReadArgs = serde_json::from_value(arguments)?;
+            let root = self.root.clone();
+            let max_bytes = self.max_bytes;
+            let (content, path) = tokio::task::spawn_blocking(move || Self::read(&root, max_bytes, args))
+                .await
+                .map_err(|_| Error::WorkerPanicked)??;
+            if let Some(store) = &self.store {
+                store.touch_embeddings(&[path]).await?;
+            }
+            Ok(content)
+        })
+    }
+}
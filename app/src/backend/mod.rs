@@ -0,0 +1,136 @@
+//! Chat and embeddings behind one trait, so the REPL and RAG code run against
+//! Ollama or any server speaking the OpenAI API, such as llama.cpp or vLLM.
+
+mod openai;
+
+pub use openai::{OpenAiClient, OpenAiConfig, DEFAULT_OPENAI_URL};
+
+use std::fs;
+use std::path::Path;
+
+use futures::future::BoxFuture;
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::ollama_client::chat::collect_reply;
+use crate::ollama_client::{ChatChunk, ChatMessage, ChunkStream, GenerateOptions, OllamaClient, OllamaConfig, ToolSpec};
+
+/// A server that can chat and embed.
+pub trait LlmBackend: Send + Sync {
+    /// Short name for messages, e.g. "ollama".
+    fn name(&self) -> &str;
+
+    fn base_url(&self) -> &str;
+
+    /// Names of the models the server can run.
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<String>>>;
+
+    /// Stream the assistant's reply to `messages` chunk by chunk. `options`
+    /// override the model's profile.
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, Result<ChunkStream<ChatChunk>>>;
+
+    /// Continue the conversation with `tools` on offer. The reply either
+    /// answers or carries `tool_calls` for the caller to run, see `agent::Agent`.
+    fn chat_with_tools<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        tools: &'a [ToolSpec],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, Result<ChatMessage>>;
+
+    /// Embed each of `inputs`, returning one vector per input in order.
+    fn embed<'a>(&'a self, model: &'a str, inputs: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>>;
+
+    /// Stream the reply to `messages` through `on_chunk` and return the full
+    /// assistant message.
+    fn reply<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        options: &'a GenerateOptions,
+        on_chunk: &'a mut (dyn FnMut(&ChatChunk) + Send),
+    ) -> BoxFuture<'a, Result<ChatMessage>> {
+        Box::pin(async move {
+            let stream = self.chat_stream(model, messages, options).await?;
+            collect_reply(stream, on_chunk).await
+        })
+    }
+}
+
+impl LlmBackend for OllamaClient {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn base_url(&self) -> &str {
+        OllamaClient::base_url(self)
+    }
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        Box::pin(async move {
+            Ok(OllamaClient::list_models(self).await?.into_iter().map(|model| model.name).collect())
+        })
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, Result<ChunkStream<ChatChunk>>> {
+        Box::pin(OllamaClient::chat_stream(self, model, messages, options))
+    }
+
+    fn chat_with_tools<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        tools: &'a [ToolSpec],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, Result<ChatMessage>> {
+        Box::pin(OllamaClient::chat_with_tools(self, model, messages, tools, options))
+    }
+
+    fn embed<'a>(&'a self, model: &'a str, inputs: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(OllamaClient::embed(self, model, inputs))
+    }
+}
+
+/// Which server to talk to, loaded from JSON such as
+/// `{"kind": "openai", "base_url": "http://gpu-box:8000", "api_key": "..."}`.
+/// The fields besides `kind` are that backend's own config.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum BackendConfig {
+    Ollama(OllamaConfig),
+    #[serde(rename = "openai")]
+    OpenAi(OpenAiConfig),
+}
+
+impl Default for BackendConfig {
+    fn default() -> Self {
+        BackendConfig::Ollama(OllamaConfig::default())
+    }
+}
+
+impl BackendConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::InvalidInput(format!("invalid backend config in {}: {}", path.display(), e)))
+    }
+
+    /// Build the configured client. Ollama still honours `OLLAMA_HOST`.
+    pub fn connect(self) -> Result<Box<dyn LlmBackend>> {
+        Ok(match self {
+            BackendConfig::Ollama(config) => Box::new(OllamaClient::builder().config(config).build()?),
+            BackendConfig::OpenAi(config) => Box::new(OpenAiClient::from_config(&config)?),
+        })
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::{future, StreamExt};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::backend::LlmBackend;
+use crate::error::{Error, Result};
+use crate::ollama_client::builder::http_client;
+use crate::ollama_client::cancel::guarded;
+use crate::ollama_client::stream::line_stream;
+use crate::ollama_client::{
+    ChatChunk, ChatMessage, ChunkStream, Format, FunctionCall, GenerateOptions, GenerationStats, ModelProfiles, ToolCall,
+    ToolSpec,
+};
+
+/// address llama.cpp's server listens on out of the box
+pub const DEFAULT_OPENAI_URL: &str = "http://127.0.0.1:8080";
+
+/// Connection settings for an OpenAI-compatible server.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct OpenAiConfig {
+    /// server root, with or without the trailing `/v1`
+    pub base_url: Option<String>,
+    /// sent as `Authorization: Bearer <key>`; local servers usually need none
+    pub api_key: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    pub connect_timeout_secs: Option<u64>,
+    /// longest wait between bytes of a response, so slow generations still stream
+    pub read_timeout_secs: Option<u64>,
+}
+
+/// Client for the `/v1/chat/completions`, `/v1/embeddings` and `/v1/models`
+/// endpoints served by llama.cpp, vLLM and similar.
+pub struct OpenAiClient {
+    client: Client,
+    base_url: String,
+    profiles: ModelProfiles,
+}
+
+impl OpenAiClient {
+    pub fn new(base_url: &str) -> Self {
+        Self::from_parts(Client::new(), base_url)
+    }
+
+    pub fn from_config(config: &OpenAiConfig) -> Result<Self> {
+        let client = http_client(
+            &config.headers,
+            config.api_key.as_ref(),
+            config.connect_timeout_secs.map(Duration::from_secs),
+            config.read_timeout_secs.map(Duration::from_secs),
+        )?;
+        Ok(Self::from_parts(client, config.base_url.as_deref().unwrap_or(DEFAULT_OPENAI_URL)))
+    }
+
+    fn from_parts(client: Client, base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        OpenAiClient {
+            client,
+            base_url: base_url.strip_suffix("/v1").unwrap_or(base_url).to_string(),
+            profiles: ModelProfiles::builtin(),
+        }
+    }
+
+    /// The server root, without `/v1`.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Use `profiles` for per-model default options instead of the built-in ones.
+    pub fn with_profiles(mut self, profiles: ModelProfiles) -> Self {
+        self.profiles = profiles;
+        self
+    }
+
+    /// Ids of the models the server offers.
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self.client
+            .get(format!("{}/v1/models", self.base_url))
+            .send()
+            .await
+            .map_err(|e| self.connection_error(e))?;
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+        let models: List<RawModel> = response.json().await?;
+        Ok(models.data.into_iter().map(|model| model.id).collect())
+    }
+
+    /// Stream the assistant's reply to `messages` chunk by chunk. `options`
+    /// override the model's profile; `num_ctx`, `keep_alive` and
+    /// `repeat_penalty` have no OpenAI equivalent and are ignored.
+    pub async fn chat_stream(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        options: &GenerateOptions,
+    ) -> Result<ChunkStream<ChatChunk>> {
+        let response = self.chat_request(model, messages, &[], options, true).await?;
+        Ok(guarded(completion_stream(response), options.cancel.as_ref()))
+    }
+
+    /// Continue the conversation with `tools` on offer. The reply either
+    /// answers or carries `tool_calls` for the caller to run, see `agent::Agent`.
+    /// It is requested whole, as streamed tool calls arrive in fragments.
+    pub async fn chat_with_tools(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+        options: &GenerateOptions,
+    ) -> Result<ChatMessage> {
+        let response = self.chat_request(model, messages, tools, options, false).await?;
+        let completion = async { Ok::<_, Error>(response.json::<RawCompletion>().await?) };
+        let completion = match &options.cancel {
+            Some(token) => token.run(completion).await?,
+            None => completion.await?,
+        };
+        completion
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.into())
+            .ok_or_else(|| Error::Api { status: 200, message: "completion has no choices".to_string() })
+    }
+
+    /// Embed each of `inputs` with `model`, returning one vector per input in order.
+    pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let request_body = serde_json::json!({ "model": model, "input": inputs });
+        let response = self.post_json("/v1/embeddings", &request_body, model).await?;
+
+        let mut embeddings = response.json::<List<RawEmbedding>>().await?.data;
+        embeddings.sort_by_key(|embedding| embedding.index);
+        Ok(embeddings.into_iter().map(|embedding| embedding.embedding).collect())
+    }
+
+    // Private:
+
+    async fn chat_request(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+        options: &GenerateOptions,
+        stream: bool,
+    ) -> Result<reqwest::Response> {
+        let messages: Vec<Value> = messages.iter().map(wire_message).collect();
+        let mut request_body = serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "stream": stream
+        });
+        if let Some(body) = request_body.as_object_mut() {
+            if !tools.is_empty() {
+                body.insert("tools".to_string(), serde_json::to_value(tools)?);
+            }
+            body.extend(request_fields(&options.or(&self.profiles.for_model(model))));
+        }
+
+        let request = self.post_json("/v1/chat/completions", &request_body, model);
+        match &options.cancel {
+            Some(token) => token.run(request).await,
+            None => request.await,
+        }
+    }
+
+    /// a 404 means `model` is unknown
+    async fn post_json(&self, path: &str, body: &Value, model: &str) -> Result<reqwest::Response> {
+        let response = self.client
+            .post(format!("{}{}", self.base_url, path))
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| self.connection_error(e))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(Error::ModelNotFound(model.to_string()));
+        }
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+        Ok(response)
+    }
+
+    fn connection_error(&self, error: reqwest::Error) -> Error {
+        if error.is_connect() {
+            Error::DaemonNotRunning { url: self.base_url.clone(), source: Some(error) }
+        } else {
+            Error::Http(error)
+        }
+    }
+}
+
+impl LlmBackend for OpenAiClient {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn base_url(&self) -> &str {
+        OpenAiClient::base_url(self)
+    }
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        Box::pin(OpenAiClient::list_models(self))
+    }
+
+    fn chat_stream<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, Result<ChunkStream<ChatChunk>>> {
+        Box::pin(OpenAiClient::chat_stream(self, model, messages, options))
+    }
+
+    fn chat_with_tools<'a>(
+        &'a self,
+        model: &'a str,
+        messages: &'a [ChatMessage],
+        tools: &'a [ToolSpec],
+        options: &'a GenerateOptions,
+    ) -> BoxFuture<'a, Result<ChatMessage>> {
+        Box::pin(OpenAiClient::chat_with_tools(self, model, messages, tools, options))
+    }
+
+    fn embed<'a>(&'a self, model: &'a str, inputs: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(OpenAiClient::embed(self, model, inputs))
+    }
+}
+
+/// `message` as the chat completion API expects it: tool calls carry their
+/// arguments as a JSON string, and tool results name the call they answer
+fn wire_message(message: &ChatMessage) -> Value {
+    let mut wire = serde_json::json!({ "role": message.role, "content": message.content });
+    if !message.tool_calls.is_empty() {
+        let calls: Vec<Value> = message
+            .tool_calls
+            .iter()
+            .map(|call| {
+                serde_json::json!({
+                    "id": call.id,
+                    "type": "function",
+                    "function": { "name": call.function.name, "arguments": call.function.arguments.to_string() }
+                })
+            })
+            .collect();
+        wire["tool_calls"] = calls.into();
+    }
+    if let Some(id) = &message.tool_call_id {
+        wire["tool_call_id"] = id.clone().into();
+    }
+    wire
+}
+
+/// map our options onto the chat completion request fields
+fn request_fields(options: &GenerateOptions) -> Map<String, Value> {
+    let mut fields = Map::new();
+    if let Some(temperature) = options.temperature {
+        fields.insert("temperature".to_string(), temperature.into());
+    }
+    if let Some(top_p) = options.top_p {
+        fields.insert("top_p".to_string(), top_p.into());
+    }
+    // not in OpenAI's API, but llama.cpp and vLLM both accept it
+    if let Some(top_k) = options.top_k {
+        fields.insert("top_k".to_string(), top_k.into());
+    }
+    if let Some(max_tokens) = options.num_predict.filter(|n| *n > 0) {
+        fields.insert("max_tokens".to_string(), max_tokens.into());
+    }
+    if let Some(seed) = options.seed {
+        fields.insert("seed".to_string(), seed.into());
+    }
+    if !options.stop.is_empty() {
+        fields.insert("stop".to_string(), options.stop.clone().into());
+    }
+    match &options.format {
+        Some(Format::Json) => {
+            fields.insert("response_format".to_string(), serde_json::json!({ "type": "json_object" }));
+        }
+        Some(Format::Schema(schema)) => {
+            fields.insert(
+                "response_format".to_string(),
+                serde_json::json!({ "type": "json_schema", "json_schema": { "name": "reply", "schema": schema } }),
+            );
+        }
+        None => {}
+    }
+    fields
+}
+
+/// `{"data": [...]}` wrapper used by `/v1/models` and `/v1/embeddings`
+#[derive(Deserialize)]
+struct List<T> {
+    #[serde(default = "Vec::new")]
+    data: Vec<T>,
+}
+
+#[derive(Deserialize)]
+struct RawModel {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct RawEmbedding {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// one `data:` event of a streamed chat completion
+#[derive(Deserialize)]
+struct RawCompletionChunk {
+    #[serde(default)]
+    choices: Vec<RawChoice>,
+    usage: Option<RawUsage>,
+}
+
+#[derive(Deserialize)]
+struct RawChoice {
+    #[serde(default)]
+    delta: RawDelta,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawDelta {
+    content: Option<String>,
+}
+
+/// a chat completion requested without streaming
+#[derive(Deserialize)]
+struct RawCompletion {
+    #[serde(default)]
+    choices: Vec<RawMessageChoice>,
+}
+
+#[derive(Deserialize)]
+struct RawMessageChoice {
+    message: RawMessage,
+}
+
+#[derive(Deserialize)]
+struct RawMessage {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<RawToolCall>,
+}
+
+#[derive(Deserialize)]
+struct RawToolCall {
+    id: Option<String>,
+    function: RawFunctionCall,
+}
+
+#[derive(Deserialize)]
+struct RawFunctionCall {
+    name: String,
+    /// a JSON-encoded string, unlike Ollama's object
+    #[serde(default)]
+    arguments: String,
+}
+
+impl From<RawMessage> for ChatMessage {
+    fn from(raw: RawMessage) -> Self {
+        let tool_calls = raw
+            .tool_calls
+            .into_iter()
+            .map(|call| ToolCall {
+                id: call.id,
+                function: FunctionCall {
+                    // left as a string when malformed, so validation reports it to the model
+                    arguments: serde_json::from_str(&call.function.arguments)
+                        .unwrap_or(Value::String(call.function.arguments)),
+                    name: call.function.name,
+                },
+            })
+            .collect();
+        ChatMessage { tool_calls, ..ChatMessage::assistant(raw.content.unwrap_or_default()) }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+impl From<RawCompletionChunk> for ChatChunk {
+    fn from(raw: RawCompletionChunk) -> Self {
+        let choice = raw.choices.into_iter().next();
+        let finish_reason = choice.as_ref().and_then(|choice| choice.finish_reason.clone());
+        let content = choice.and_then(|choice| choice.delta.content).unwrap_or_default();
+        ChatChunk {
+            message: ChatMessage::assistant(content),
+            done: finish_reason.is_some(),
+            done_reason: finish_reason,
+            stats: raw.usage.map(|usage| GenerationStats {
+                prompt_eval_count: usage.prompt_tokens,
+                eval_count: usage.completion_tokens,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// Decode a server-sent events body of completion chunks, ending at
+/// `data: [DONE]` or the first error.
+fn completion_stream(response: reqwest::Response) -> ChunkStream<ChatChunk> {
+    let events = line_stream(response).scan(false, |finished, line| {
+        if *finished {
+            return future::ready(None);
+        }
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                *finished = true;
+                return future::ready(Some(Some(Err(e))));
+            }
+        };
+        let line = String::from_utf8_lossy(&line);
+        // comments, `event:` and `id:` lines carry nothing we need
+        let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+            return future::ready(Some(None));
+        };
+        if data == "[DONE]" {
+            return future::ready(None);
+        }
+
+        let chunk = decode_event(data);
+        *finished = chunk.is_err();
+        future::ready(Some(Some(chunk)))
+    });
+    Box::pin(events.filter_map(future::ready))
+}
+
+fn decode_event(data: &str) -> Result<ChatChunk> {
+    let value: Value = serde_json::from_str(data)?;
+    if let Some(message) = error_message(&value) {
+        return Err(Error::Api { status: 200, message });
+    }
+    Ok(serde_json::from_value::<RawCompletionChunk>(value)?.into())
+}
+
+/// the message of an `{"error": {"message": ...}}` body; llama.cpp and some
+/// proxies send `{"error": "..."}` instead
+fn error_message(body: &Value) -> Option<String> {
+    match body.get("error")? {
+        Value::String(message) => Some(message.clone()),
+        error => error.get("message").and_then(|m| m.as_str()).map(str::to_string),
+    }
+}
+
+/// turn a non-success response into `Error::Api`, keeping the server's message if present
+async fn api_error(response: reqwest::Response) -> Error {
+    let status = response.status().as_u16();
+    let message = response.json::<Value>().await.ok().and_then(|body| error_message(&body)).unwrap_or_default();
+    Error::Api { status, message }
+}
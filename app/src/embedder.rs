@@ -1,50 +1,70 @@
+use std::sync::Arc;
+
 use ort::{session::Session, inputs, value::Value,};
 use tokenizers::Tokenizer;
 
 use ndarray::{Array, IxDyn};
 
+use crate::error::{Error, Result};
+
 const MAX_LEN: usize = 512; // max input sequence len
+/// marks inputs for UniXcoder's encoder-only mode
+const PREFIX: &str = "<encoder-only>";
 
 pub struct Embedder {
     session: Session,
-    tokenizer: Tokenizer,
+    tokenizer: Arc<Tokenizer>,
+    /// tokens the prefix and special tokens add to every input
+    overhead: usize,
 }
 
 impl Embedder {
     /// Create a new Embedder from ONNX model and tokenizer file paths
-    pub fn new(model_path: &str, tokenizer_path: &str) -> anyhow::Result<Self> {
-        let session = Session::builder()?
-            .with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3)?
-            .commit_from_file(model_path)?;
-        
-        let tokenizer = Tokenizer::from_file(tokenizer_path)
-            .map_err(|e| anyhow::anyhow!("Failed to load tokenizer: {}", e))?;
-        tokenizer
-            .save("../models/UniXcoder/unixcoder-tokenizer.json", true)
-            .map_err(|e| anyhow::anyhow!("Failed to save tokenizer: {}", e))?;
+    pub fn new(model_path: &str, tokenizer_path: &str) -> Result<Self> {
+        Self::with_tokenizer(model_path, Arc::new(load_tokenizer(tokenizer_path)?))
+    }
+
+    /// Create an Embedder sharing an already loaded tokenizer, so parallel
+    /// workers only pay for their own ort `Session`.
+    pub fn with_tokenizer(model_path: &str, tokenizer: Arc<Tokenizer>) -> Result<Self> {
+        let session = Session::builder()
+            .and_then(|builder| builder.with_optimization_level(ort::session::builder::GraphOptimizationLevel::Level3))
+            .and_then(|builder| builder.commit_from_file(model_path))
+            .map_err(Error::ModelLoad)?;
+        let overhead = tokenizer.encode(PREFIX, true)
+            .map_err(|e| Error::Tokenizer(e.to_string()))?
+            .len();
 
         Ok(Self {
             session,
             tokenizer,
+            overhead,
         })
     }
 
-    pub fn embed(&mut self, prompt: &str) -> anyhow::Result<Vec<f32>> {
-        let prompt = format!("<encoder-only>{}", prompt);
+    pub fn embed(&mut self, prompt: &str) -> Result<Vec<f32>> {
+        Ok(self.embed_counted(prompt)?.0)
+    }
+
+    /// Embed `prompt` and return its token count, as `count_tokens` would,
+    /// taken from the same encoding so the text is only tokenized once.
+    pub fn embed_counted(&mut self, prompt: &str) -> Result<(Vec<f32>, usize)> {
+        let prompt = format!("{}{}", PREFIX, prompt);
 
         let encoding = self.tokenizer.encode(prompt, true)
-            .map_err(|e| anyhow::anyhow!("Tokenization failed: {}", e))?;
+            .map_err(|e| Error::Tokenizer(e.to_string()))?;
+        let token_count = encoding.len().saturating_sub(self.overhead);
         let input_ids = encoding.get_ids();
         let attention_mask = encoding.get_attention_mask();
         let seq_len = input_ids.len().min(MAX_LEN);
 
         let input_ids_array = Array::from_shape_vec(
             IxDyn(&[1, seq_len]),
-            input_ids.iter().map(|&id| id as i64).collect(),
+            input_ids.iter().take(seq_len).map(|&id| id as i64).collect(),
         )?;
         let attention_mask_array = Array::from_shape_vec(
             IxDyn(&[1, seq_len]),
-            attention_mask.iter().map(|&mask| mask as i64).collect(),
+            attention_mask.iter().take(seq_len).map(|&mask| mask as i64).collect(),
         )?;
 
         let input_tensor = Value::from_array(input_ids_array)?;
@@ -59,10 +79,17 @@ impl Embedder {
         let shape: Vec<usize> = shape.iter().map(|&d| d as usize).collect();
 
         let output = ndarray::ArrayD::from_shape_vec(shape, data.to_vec())?;
-        Ok(output.iter().cloned().collect())
+        Ok((output.iter().cloned().collect(), token_count))
+    }
+
+    /// Number of tokens in `text`, ignoring the model's `MAX_LEN` truncation.
+    pub fn count_tokens(&self, text: &str) -> Result<usize> {
+        let encoding = self.tokenizer.encode(text, false)
+            .map_err(|e| Error::Tokenizer(e.to_string()))?;
+        Ok(encoding.len())
     }
 
-    pub fn embed_batch(&mut self, prompts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+    pub fn embed_batch(&mut self, prompts: &[String]) -> Result<Vec<Vec<f32>>> {
         let mut embeddings = Vec::with_capacity(prompts.len());
         
         for prompt in prompts {
@@ -73,3 +100,10 @@ impl Embedder {
         Ok(embeddings)
     }
 }
+
+/// Load the tokenizer at `tokenizer_path`. Call once and share the result
+/// between embedders.
+pub fn load_tokenizer(tokenizer_path: &str) -> Result<Tokenizer> {
+    Tokenizer::from_file(tokenizer_path)
+        .map_err(|e| Error::Tokenizer(format!("Failed to load tokenizer: {}", e)))
+}
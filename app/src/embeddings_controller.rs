@@ -0,0 +1,446 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use sha2::{Digest, Sha256};
+use tokenizers::Tokenizer;
+use tokio::sync::mpsc;
+
+use crate::error::{Error, Result};
+use crate::embedder::{load_tokenizer, Embedder};
+use crate::lancedb::{EmbeddingRecord, LanceDbClient};
+use crate::source_analysis::{content_preview, extract_symbols, is_generated, is_test_path};
+
+/// directories never worth embedding
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "__pycache__", "build", "dist"];
+
+/// Tuning knobs for the indexing pipeline.
+#[derive(Clone, Debug)]
+pub struct IndexerConfig {
+    pub model_path: String,
+    pub tokenizer_path: String,
+    /// threads reading files from disk
+    pub reader_threads: usize,
+    /// embedder workers, each owning its own ort `Session`
+    pub embedder_threads: usize,
+    /// records per `insert_embeddings` call
+    pub batch_size: usize,
+    /// capacity of each bounded queue between stages
+    pub queue_capacity: usize,
+}
+
+impl Default for IndexerConfig {
+    fn default() -> Self {
+        let cores = thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+        Self {
+            model_path: "../models/UniXcoder/unixcoder-embedding.onnx".to_string(),
+            tokenizer_path: "../models/UniXcoder/tokenizer.json".to_string(),
+            reader_threads: 2,
+            embedder_threads: (cores / 2).max(1),
+            batch_size: 64,
+            queue_capacity: 128,
+        }
+    }
+}
+
+impl IndexerConfig {
+    /// Identifies the embedding model in exported bundles: the model file's name.
+    pub fn model_id(&self) -> String {
+        Path::new(&self.model_path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.model_path.clone())
+    }
+}
+
+/// Why an indexed row no longer matches the file on disk.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Staleness {
+    /// the file was deleted or moved
+    Missing,
+    /// the file's content hash differs from the stored hash
+    Modified,
+}
+
+/// Summary of a finished indexing run.
+#[derive(Debug, Default)]
+pub struct IndexStats {
+    pub files_indexed: usize,
+    pub files_skipped: usize,
+    /// files the embedder rejected, with why; the rest of the run still completes
+    pub failed: Vec<(String, Error)>,
+}
+
+/// a source file read from disk, waiting for an embedder
+pub(crate) struct SourceFile {
+    pub(crate) path: String,
+    pub(crate) content: String,
+    pub(crate) language: &'static str,
+    pub(crate) last_modified: i64,
+}
+
+/// messages flowing from the embedder workers to the writer
+enum WorkerMessage {
+    Record(Box<EmbeddingRecord>),
+    Skipped,
+    /// one file couldn't be embedded
+    FileFailed(String, Error),
+    /// the worker can't continue, e.g. the model didn't load
+    Failed(Error),
+}
+
+/// Index every source file under `root` into `client`.
+///
+/// Files flow through bounded queues: a walker feeds readers, readers feed
+/// `embedder_threads` embedder workers, and the workers feed a batching writer
+/// running on the caller's task. A slow stage blocks the stages before it.
+///
+/// The working tree need not match any commit, so the table's indexed commit
+/// is cleared first and the next `git_index::index_commit` does a full pass.
+pub async fn index_directory(client: &LanceDbClient, root: &Path, config: &IndexerConfig) -> Result<IndexStats> {
+    client.clear_indexed_commit().await?;
+
+    let capacity = config.queue_capacity.max(1);
+    let (path_tx, path_rx) = sync_channel::<PathBuf>(capacity);
+    let (file_tx, file_rx) = sync_channel::<SourceFile>(capacity);
+
+    let mut handles: Vec<JoinHandle<()>> = Vec::new();
+
+    let walk_root = root.to_path_buf();
+    handles.push(thread::spawn(move || walk_directory(&walk_root, &path_tx)));
+
+    let path_rx = Arc::new(Mutex::new(path_rx));
+    for _ in 0..config.reader_threads.max(1) {
+        let path_rx = Arc::clone(&path_rx);
+        let file_tx = file_tx.clone();
+        let root = root.to_path_buf();
+        handles.push(thread::spawn(move || read_files(&root, &path_rx, &file_tx)));
+    }
+    drop(file_tx);
+
+    embed_and_write(client, config, file_rx, handles).await
+}
+
+/// Run the embedder workers and the batching writer over everything the
+/// producer threads in `handles` send on `file_rx`, then join all threads.
+/// The model is only loaded once a file arrives.
+pub(crate) async fn embed_and_write(
+    client: &LanceDbClient,
+    config: &IndexerConfig,
+    file_rx: Receiver<SourceFile>,
+    mut handles: Vec<JoinHandle<()>>,
+) -> Result<IndexStats> {
+    let (file_rx, first) = tokio::task::spawn_blocking(move || {
+        let first = file_rx.recv().ok();
+        (file_rx, first)
+    })
+    .await
+    .map_err(|_| Error::WorkerPanicked)?;
+    if first.is_none() {
+        join_workers(handles).await?;
+        return Ok(IndexStats::default());
+    }
+
+    let capacity = config.queue_capacity.max(1);
+    let (record_tx, mut record_rx) = mpsc::channel::<WorkerMessage>(capacity);
+
+    // loaded once and shared, rather than parsed again by every worker
+    let tokenizer_path = config.tokenizer_path.clone();
+    let tokenizer = tokio::task::spawn_blocking(move || load_tokenizer(&tokenizer_path))
+        .await
+        .map_err(|_| Error::WorkerPanicked)??;
+    let tokenizer = Arc::new(tokenizer);
+
+    let file_rx = Arc::new(Mutex::new(FileQueue { first, rx: file_rx }));
+    for _ in 0..config.embedder_threads.max(1) {
+        let file_rx = Arc::clone(&file_rx);
+        let record_tx = record_tx.clone();
+        let model_path = config.model_path.clone();
+        let tokenizer = Arc::clone(&tokenizer);
+        handles.push(thread::spawn(move || {
+            embed_files(&model_path, tokenizer, &file_rx, &record_tx)
+        }));
+    }
+    drop(record_tx);
+
+    let pb = ProgressBar::new_spinner();
+    pb.set_style(ProgressStyle::default_spinner()
+        .template("{spinner} {pos} files indexed ({per_sec}) {msg}")
+        .expect("template valid"));
+    pb.enable_steady_tick(Duration::from_millis(100));
+
+    let result = write_batches(client, &mut record_rx, config.batch_size.max(1), &pb).await;
+
+    // dropping the receiver unblocks any worker still waiting to send
+    drop(record_rx);
+    let joined = join_workers(handles).await;
+    pb.finish_and_clear();
+
+    // report a writer error ahead of a panicked worker
+    result.and_then(|stats| joined.map(|_| stats))
+}
+
+/// join `handles` without blocking the runtime
+async fn join_workers(handles: Vec<JoinHandle<()>>) -> Result<()> {
+    let panicked = tokio::task::spawn_blocking(move || {
+        handles.into_iter().map(JoinHandle::join).filter(|joined| joined.is_err()).count()
+    })
+    .await;
+    match panicked {
+        Ok(0) => Ok(()),
+        _ => Err(Error::WorkerPanicked),
+    }
+}
+
+/// the producers' queue, with the file `embed_and_write` peeked at put back in front
+struct FileQueue {
+    first: Option<SourceFile>,
+    rx: Receiver<SourceFile>,
+}
+
+impl FileQueue {
+    fn next(&mut self) -> Option<SourceFile> {
+        self.first.take().or_else(|| self.rx.recv().ok())
+    }
+}
+
+async fn write_batches(
+    client: &LanceDbClient,
+    record_rx: &mut mpsc::Receiver<WorkerMessage>,
+    batch_size: usize,
+    pb: &ProgressBar,
+) -> Result<IndexStats> {
+    let mut stats = IndexStats::default();
+    let mut batch = Vec::with_capacity(batch_size);
+
+    while let Some(message) = record_rx.recv().await {
+        match message {
+            WorkerMessage::Record(record) => {
+                pb.set_message(record.path.clone());
+                batch.push(*record);
+            }
+            WorkerMessage::Skipped => stats.files_skipped += 1,
+            WorkerMessage::FileFailed(path, e) => stats.failed.push((path, e)),
+            WorkerMessage::Failed(e) => return Err(e),
+        }
+
+        if batch.len() >= batch_size {
+            stats.files_indexed += flush_batch(client, &mut batch).await?;
+            pb.set_position(stats.files_indexed as u64);
+        }
+    }
+
+    stats.files_indexed += flush_batch(client, &mut batch).await?;
+    pb.set_position(stats.files_indexed as u64);
+
+    Ok(stats)
+}
+
+/// replace any stale rows for the batch's paths with the batch
+async fn flush_batch(client: &LanceDbClient, batch: &mut Vec<EmbeddingRecord>) -> Result<usize> {
+    let records = std::mem::take(batch);
+    let written = records.len();
+    client.upsert_embeddings(records).await?;
+
+    Ok(written)
+}
+
+/// List every indexed path under `root` whose file is gone or has changed
+/// since it was embedded, sorted by path.
+pub async fn find_stale_records(client: &LanceDbClient, root: &Path) -> Result<Vec<(String, Staleness)>> {
+    let mut stale = Vec::new();
+
+    for (path, hash) in client.list_hashes().await? {
+        match fs::read_to_string(root.join(&path)) {
+            Ok(content) if hash_content(&content) == hash => {}
+            Ok(_) => stale.push((path, Staleness::Modified)),
+            Err(_) => stale.push((path, Staleness::Missing)),
+        }
+    }
+
+    stale.sort();
+    Ok(stale)
+}
+
+// pipeline stages:
+
+fn walk_directory(root: &Path, path_tx: &SyncSender<PathBuf>) {
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let Ok(file_type) = entry.file_type() else { continue };
+
+            if file_type.is_dir() {
+                if !is_skipped_dir(&name) {
+                    pending.push(path);
+                }
+            } else if file_type.is_file()
+                && detect_language(&path).is_some()
+                && path_tx.send(path).is_err()
+            {
+                return; // downstream shut down
+            }
+        }
+    }
+}
+
+fn read_files(root: &Path, path_rx: &Mutex<Receiver<PathBuf>>, file_tx: &SyncSender<SourceFile>) {
+    loop {
+        let path = match path_rx.lock().expect("path queue poisoned").recv() {
+            Ok(path) => path,
+            Err(_) => return,
+        };
+
+        let Some(file) = read_source_file(root, &path) else { continue };
+
+        if file_tx.send(file).is_err() {
+            return;
+        }
+    }
+}
+
+fn embed_files(
+    model_path: &str,
+    tokenizer: Arc<Tokenizer>,
+    file_rx: &Mutex<FileQueue>,
+    record_tx: &mpsc::Sender<WorkerMessage>,
+) {
+    let mut embedder = match Embedder::with_tokenizer(model_path, tokenizer) {
+        Ok(embedder) => embedder,
+        Err(e) => {
+            let _ = record_tx.blocking_send(WorkerMessage::Failed(e));
+            return;
+        }
+    };
+
+    loop {
+        let Some(file) = file_rx.lock().expect("file queue poisoned").next() else { return };
+
+        let message = if file.content.trim().is_empty() {
+            WorkerMessage::Skipped
+        } else {
+            match embedder.embed_counted(&file.content) {
+                Ok((embedding, token_count)) => WorkerMessage::Record(Box::new(build_record(file, embedding, token_count))),
+                Err(e) => WorkerMessage::FileFailed(file.path, e),
+            }
+        };
+
+        if record_tx.blocking_send(message).is_err() {
+            return; // writer gave up
+        }
+    }
+}
+
+// helpers:
+
+/// read a source file and tag it with its path relative to `root`;
+/// returns None for unsupported, binary or unreadable files
+pub(crate) fn read_source_file(root: &Path, path: &Path) -> Option<SourceFile> {
+    let language = detect_language(path)?;
+    let content = fs::read_to_string(path).ok()?;
+
+    let last_modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(system_time_to_micros)
+        .unwrap_or_else(|_| now_micros());
+
+    Some(SourceFile {
+        path: relative_path(root, path),
+        content,
+        language,
+        last_modified,
+    })
+}
+
+pub(crate) fn build_record(file: SourceFile, embedding: Vec<f32>, token_count: usize) -> EmbeddingRecord {
+    let hash = hash_content(&file.content);
+    let line_count = file.content.lines().count().min(i32::MAX as usize) as i32;
+    let content_preview = Some(content_preview(&file.content));
+    let symbols = extract_symbols(&file.content);
+    let is_test = is_test_path(&file.path);
+    let is_generated = is_generated(&file.path, &file.content);
+
+    EmbeddingRecord {
+        path: file.path,
+        hash,
+        embedding,
+        language: file.language.to_string(),
+        last_modified: file.last_modified,
+        // only reads count as access; re-indexed rows keep their stored value
+        last_accessed: 0,
+        line_count,
+        imported_by: vec![],
+        content_preview,
+        byte_size: file.content.len() as i64,
+        token_count: token_count.min(i32::MAX as usize) as i32,
+        symbols,
+        is_test,
+        is_generated,
+        content: Some(file.content),
+    }
+}
+
+/// Hex SHA-256 of a file's content, as stored in the `hash` column.
+pub fn hash_content(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// `path` relative to `root` with forward slashes, as stored in the `path` column
+pub(crate) fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// hidden directories (.git, .vector_store, ...) and build output are never indexed
+pub(crate) fn is_skipped_dir(name: &str) -> bool {
+    name.starts_with('.') || SKIPPED_DIRS.contains(&name)
+}
+
+/// map a file extension to the language stored in the `language` column
+pub fn detect_language(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let language = match ext.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" | "jsx" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "scala" => "scala",
+        "sh" | "bash" => "shell",
+        "sql" => "sql",
+        "lua" => "lua",
+        "toml" => "toml",
+        "yaml" | "yml" => "yaml",
+        "md" => "markdown",
+        _ => return None,
+    };
+    Some(language)
+}
+
+fn system_time_to_micros(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_micros() as i64)
+        .unwrap_or(0)
+}
+
+fn now_micros() -> i64 {
+    system_time_to_micros(SystemTime::now())
+}
@@ -0,0 +1,147 @@
+use std::io;
+
+/// Result alias used throughout the library.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Every error the library returns.
+///
+/// Variants are grouped by subsystem and each has a stable `code()` that
+/// tooling can match on; display messages may change between releases.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    // ollama
+    #[error("LLM server is not running at {url}")]
+    DaemonNotRunning {
+        url: String,
+        #[source]
+        source: Option<reqwest::Error>,
+    },
+    #[error("Failed to start ollama daemon: {0}")]
+    DaemonStart(String),
+    #[error("Port for {0} is taken by something that is not an Ollama daemon")]
+    PortConflict(String),
+    #[error("Model not found: {0}")]
+    ModelNotFound(String),
+    #[error("Failed to create model: {0}")]
+    ModelCreate(String),
+    #[error("LLM API returned status {status}: {message}")]
+    Api { status: u16, message: String },
+    #[error("HTTP request failed")]
+    Http(#[from] reqwest::Error),
+    #[error("Model reply did not match the requested schema after {attempts} attempts: {message}")]
+    InvalidResponse { attempts: usize, message: String },
+    #[error("Generation was cancelled")]
+    Cancelled,
+
+    // agent
+    #[error("Agent gave no final answer within {0} steps")]
+    StepLimit(usize),
+
+    // embedder
+    #[error("Failed to load embedding model")]
+    ModelLoad(#[source] ort::Error),
+    #[error("Tokenization failed: {0}")]
+    Tokenizer(String),
+    #[error("Embedding inference failed")]
+    Inference(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    // vector store
+    #[error("Invalid embedding dimension: expected {expected}, got {actual}")]
+    DimensionMismatch { expected: usize, actual: usize },
+    #[error("Table not found: {0}")]
+    TableNotFound(String),
+    #[error("No embedding found for file: {0}")]
+    EmbeddingNotFound(String),
+    #[error("Unexpected table schema: {0}")]
+    Schema(String),
+    #[error("Incompatible bundle: {0}")]
+    IncompatibleBundle(String),
+    #[error("Invalid namespace '{0}': expected repo@branch")]
+    InvalidNamespace(String),
+    #[error("Vector store operation failed")]
+    Store(#[source] lancedb::Error),
+    #[error("Arrow operation failed")]
+    Arrow(#[from] arrow_schema::ArrowError),
+
+    // indexing
+    #[error("Unknown git revision: {0}")]
+    UnknownRevision(String),
+    #[error("git {command} failed: {stderr}")]
+    Git { command: String, stderr: String },
+    #[error("Indexing worker panicked")]
+    WorkerPanicked,
+    #[error("File watcher failed")]
+    Watch(#[from] notify::Error),
+
+    // sessions
+    #[error("Session not found: {0}")]
+    SessionNotFound(String),
+
+    // shared
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Invalid JSON")]
+    Json(#[from] serde_json::Error),
+}
+
+impl Error {
+    /// Stable, machine-readable identifier for the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::DaemonNotRunning { .. } => "ollama.daemon_not_running",
+            Error::DaemonStart(_) => "ollama.daemon_start_failed",
+            Error::PortConflict(_) => "ollama.port_conflict",
+            Error::ModelNotFound(_) => "ollama.model_not_found",
+            Error::ModelCreate(_) => "ollama.model_create_failed",
+            Error::Api { .. } => "ollama.api_error",
+            Error::Http(_) => "ollama.http_error",
+            Error::InvalidResponse { .. } => "ollama.invalid_response",
+            Error::Cancelled => "ollama.cancelled",
+            Error::StepLimit(_) => "agent.step_limit",
+            Error::ModelLoad(_) => "embedder.model_load_failed",
+            Error::Tokenizer(_) => "embedder.tokenizer_error",
+            Error::Inference(_) => "embedder.inference_failed",
+            Error::DimensionMismatch { .. } => "store.dimension_mismatch",
+            Error::TableNotFound(_) => "store.table_not_found",
+            Error::EmbeddingNotFound(_) => "store.embedding_not_found",
+            Error::Schema(_) => "store.schema_error",
+            Error::IncompatibleBundle(_) => "store.incompatible_bundle",
+            Error::InvalidNamespace(_) => "store.invalid_namespace",
+            Error::Store(_) => "store.error",
+            Error::Arrow(_) => "store.arrow_error",
+            Error::UnknownRevision(_) => "index.unknown_revision",
+            Error::Git { .. } => "index.git_failed",
+            Error::WorkerPanicked => "index.worker_panicked",
+            Error::Watch(_) => "index.watch_failed",
+            Error::SessionNotFound(_) => "session.not_found",
+            Error::InvalidInput(_) => "invalid_input",
+            Error::Io(_) => "io_error",
+            Error::Json(_) => "json_error",
+        }
+    }
+}
+
+impl From<lancedb::Error> for Error {
+    fn from(error: lancedb::Error) -> Self {
+        match error {
+            lancedb::Error::TableNotFound { name } => Error::TableNotFound(name),
+            lancedb::Error::Arrow { source } => Error::Arrow(source),
+            other => Error::Store(other),
+        }
+    }
+}
+
+impl From<ort::Error> for Error {
+    fn from(error: ort::Error) -> Self {
+        Error::Inference(Box::new(error))
+    }
+}
+
+impl From<ndarray::ShapeError> for Error {
+    fn from(error: ndarray::ShapeError) -> Self {
+        Error::Inference(Box::new(error))
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::error::{Error, Result};
+use crate::embeddings_controller::{
+    detect_language, embed_and_write, is_skipped_dir, IndexStats, IndexerConfig, SourceFile,
+};
+use crate::lancedb::LanceDbClient;
+
+/// Summary of an `index_commit` run.
+#[derive(Debug, Default)]
+pub struct CommitIndexStats {
+    /// full sha the table now reflects
+    pub commit: String,
+    /// sha the table reflected before, if it had been indexed from git
+    pub previous_commit: Option<String>,
+    pub indexed: IndexStats,
+    /// renamed without changes, so only their path was updated
+    pub files_renamed: usize,
+    pub files_deleted: usize,
+}
+
+/// one line of `git diff --name-status`, or a file of a full tree listing
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Change {
+    /// added, modified or type-changed: (re-)embed
+    Upsert(String),
+    Delete(String),
+    /// `exact` when git reports 100% similarity, i.e. identical content
+    Rename { from: String, to: String, exact: bool },
+}
+
+/// Index the tree of `rev` in the git repository at `repo` into `client`,
+/// reading blobs straight from the object store so nothing has to be checked out.
+///
+/// If the table was previously indexed at another commit, only the files in
+/// `git diff --name-status` between that commit and `rev` are touched. The
+/// resolved sha is stored in the table afterwards.
+///
+/// Rows are only deleted once the new ones are written, so a failed run
+/// leaves the previous contents searchable. Files that fail to embed are
+/// listed in `indexed.failed`, and the commit is then not recorded, so the
+/// next run diffs from the old commit again and retries them.
+pub async fn index_commit(client: &LanceDbClient, repo: &Path, rev: &str, config: &IndexerConfig) -> Result<CommitIndexStats> {
+    let commit = resolve_commit(repo, rev)?;
+    let previous_commit = client.indexed_commit().await?;
+
+    let mut stats = CommitIndexStats {
+        commit: commit.clone(),
+        previous_commit: previous_commit.clone(),
+        ..CommitIndexStats::default()
+    };
+    if previous_commit.as_deref() == Some(commit.as_str()) {
+        return Ok(stats);
+    }
+
+    // fall back to a full listing if the old commit was gc'd or came from another repo;
+    // rows from a working-tree index may then not exist at this commit
+    let (changes, known) = match previous_commit.as_deref().filter(|previous| resolve_commit(repo, previous).is_ok()) {
+        Some(previous) => (diff_changes(repo, previous, &commit)?, None),
+        None => (list_tree(repo, &commit)?, Some(client.list_hashes().await?)),
+    };
+
+    let mut to_embed = Vec::new();
+    let mut to_delete = Vec::new();
+    for change in changes {
+        match change {
+            Change::Upsert(path) => {
+                if is_indexable(&path) {
+                    to_embed.push(path);
+                }
+            }
+            Change::Delete(path) => to_delete.push(path),
+            Change::Rename { from, to, exact } => {
+                if exact && is_indexable(&to) && client.get_embedding(&from).await?.is_some() {
+                    client.rename_embedding(&from, &to).await?;
+                    stats.files_renamed += 1;
+                    continue;
+                }
+                to_delete.push(from);
+                if is_indexable(&to) {
+                    to_embed.push(to);
+                }
+            }
+        }
+    }
+
+    if let Some(known) = known {
+        let in_tree: HashSet<&String> = to_embed.iter().collect();
+        to_delete.extend(known.into_keys().filter(|path| !in_tree.contains(path)));
+    }
+
+    // pure renames and deletes never load the model
+    if !to_embed.is_empty() {
+        stats.indexed = embed_blobs(client, repo, &commit, to_embed, config).await?;
+    }
+
+    stats.files_deleted = to_delete.len();
+    client.delete_embeddings(&to_delete).await?;
+
+    if stats.indexed.failed.is_empty() {
+        client.set_indexed_commit(&commit).await?;
+    }
+    Ok(stats)
+}
+
+/// what the blob readers of one `embed_blobs` run couldn't embed
+#[derive(Default)]
+struct ReadReport {
+    /// empty or not valid utf-8 at the commit, so any old row must go
+    unembeddable: Vec<String>,
+    /// first git failure; the commit must not be recorded as indexed
+    error: Option<Error>,
+}
+
+/// feed blobs at `commit` through the shared embedding pipeline
+async fn embed_blobs(
+    client: &LanceDbClient,
+    repo: &Path,
+    commit: &str,
+    paths: Vec<String>,
+    config: &IndexerConfig,
+) -> Result<IndexStats> {
+    let last_modified = commit_time_micros(repo, commit)?;
+
+    let capacity = config.queue_capacity.max(1);
+    let (path_tx, path_rx) = sync_channel::<String>(capacity);
+    let (file_tx, file_rx) = sync_channel::<SourceFile>(capacity);
+
+    let mut handles: Vec<JoinHandle<()>> = Vec::new();
+    handles.push(thread::spawn(move || send_paths(paths, &path_tx)));
+
+    let report = Arc::new(Mutex::new(ReadReport::default()));
+    let path_rx = Arc::new(Mutex::new(path_rx));
+    for _ in 0..config.reader_threads.max(1) {
+        let path_rx = Arc::clone(&path_rx);
+        let file_tx = file_tx.clone();
+        let report = Arc::clone(&report);
+        let repo = repo.to_path_buf();
+        let commit = commit.to_string();
+        handles.push(thread::spawn(move || {
+            if let Err(e) = read_blobs(&repo, &commit, last_modified, &path_rx, &file_tx, &report) {
+                report.lock().expect("read report poisoned").error.get_or_insert(e);
+            }
+        }));
+    }
+    drop(file_tx);
+
+    let mut stats = embed_and_write(client, config, file_rx, handles).await?;
+
+    let report = std::mem::take(&mut *report.lock().expect("read report poisoned"));
+    if let Some(e) = report.error {
+        return Err(e);
+    }
+    client.delete_embeddings(&report.unembeddable).await?;
+    stats.files_skipped += report.unembeddable.len();
+
+    Ok(stats)
+}
+
+fn send_paths(paths: Vec<String>, path_tx: &SyncSender<String>) {
+    for path in paths {
+        if path_tx.send(path).is_err() {
+            return; // downstream shut down
+        }
+    }
+}
+
+fn read_blobs(
+    repo: &Path,
+    commit: &str,
+    last_modified: i64,
+    path_rx: &Mutex<Receiver<String>>,
+    file_tx: &SyncSender<SourceFile>,
+    report: &Mutex<ReadReport>,
+) -> Result<()> {
+    let mut blobs = BlobReader::spawn(repo)?;
+
+    loop {
+        let path = match path_rx.lock().expect("path queue poisoned").recv() {
+            Ok(path) => path,
+            Err(_) => return Ok(()),
+        };
+
+        let Some(language) = detect_language(Path::new(&path)) else { continue };
+        // binary blobs are not valid utf-8; like empty ones they can't be embedded
+        let content = blobs.read(&format!("{}:{}", commit, path))?.and_then(|blob| String::from_utf8(blob).ok());
+        let content = match content {
+            Some(content) if !content.trim().is_empty() => content,
+            _ => {
+                report.lock().expect("read report poisoned").unembeddable.push(path);
+                continue;
+            }
+        };
+
+        let file = SourceFile { path, content, language, last_modified };
+        if file_tx.send(file).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// One long-running `git cat-file --batch`, answering one object at a time.
+struct BlobReader {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl BlobReader {
+    fn spawn(repo: &Path) -> Result<Self> {
+        let mut child = Command::new("git")
+            .arg("-C").arg(repo)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        Ok(Self { child, stdin, stdout })
+    }
+
+    /// content of the blob named `object`, e.g. `<commit>:<path>`; `None` if
+    /// it doesn't exist or isn't a blob
+    fn read(&mut self, object: &str) -> Result<Option<Vec<u8>>> {
+        // the protocol is line based
+        if object.contains('\n') {
+            return Ok(None);
+        }
+        writeln!(self.stdin, "{}", object)?;
+        self.stdin.flush()?;
+
+        // `<sha> <type> <size>`, or `<object> missing`
+        let mut header = String::new();
+        self.stdout.read_line(&mut header)?;
+        let header = header.trim_end();
+        if header.ends_with(" missing") || header.ends_with(" ambiguous") {
+            return Ok(None);
+        }
+        let parsed = match header.split(' ').collect::<Vec<&str>>()[..] {
+            [_, kind, size] => size.parse::<usize>().ok().map(|size| (kind, size)),
+            _ => None,
+        };
+        let Some((kind, size)) = parsed else {
+            return Err(Error::Git {
+                command: "cat-file".to_string(),
+                stderr: format!("unexpected batch header {:?}", header),
+            });
+        };
+
+        // the content is followed by a newline
+        let mut content = vec![0; size + 1];
+        self.stdout.read_exact(&mut content)?;
+        content.pop();
+
+        Ok((kind == "blob").then_some(content))
+    }
+}
+
+impl Drop for BlobReader {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// git plumbing:
+
+/// Full sha of the commit `rev` points to (branch, tag or sha).
+pub fn resolve_commit(repo: &Path, rev: &str) -> Result<String> {
+    let output = git(repo, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)])
+        .map_err(|_| Error::UnknownRevision(rev.to_string()))?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+fn commit_time_micros(repo: &Path, commit: &str) -> Result<i64> {
+    let output = git(repo, &["show", "-s", "--format=%ct", commit])?;
+    let seconds: i64 = String::from_utf8_lossy(&output).trim().parse().map_err(|_| Error::Git {
+        command: "show".to_string(),
+        stderr: format!("unexpected commit time for {}", commit),
+    })?;
+    Ok(seconds * 1_000_000)
+}
+
+fn list_tree(repo: &Path, commit: &str) -> Result<Vec<Change>> {
+    let output = git(repo, &["ls-tree", "-r", "-z", "--name-only", commit])?;
+    Ok(split_nul(&output).into_iter().map(Change::Upsert).collect())
+}
+
+fn diff_changes(repo: &Path, from: &str, to: &str) -> Result<Vec<Change>> {
+    let output = git(repo, &["diff", "--name-status", "-z", "-M", from, to])?;
+    parse_name_status(&split_nul(&output))
+}
+
+/// parse `-z` name-status output: `status NUL path NUL`, with two paths for renames and copies
+fn parse_name_status(fields: &[String]) -> Result<Vec<Change>> {
+    let mut changes = Vec::new();
+    let mut fields = fields.iter();
+
+    while let Some(status) = fields.next() {
+        let mut path = || {
+            fields.next().cloned()
+                .ok_or_else(|| Error::Git {
+                    command: "diff".to_string(),
+                    stderr: format!("truncated output after status {}", status),
+                })
+        };
+
+        match status.chars().next() {
+            Some('A' | 'M' | 'T') => changes.push(Change::Upsert(path()?)),
+            Some('D') => changes.push(Change::Delete(path()?)),
+            Some('R') => {
+                let from = path()?;
+                let to = path()?;
+                changes.push(Change::Rename { from, to, exact: status == "R100" });
+            }
+            Some('C') => {
+                let _source = path()?;
+                changes.push(Change::Upsert(path()?));
+            }
+            // unmerged or unknown: nothing to index
+            _ => {
+                path()?;
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+fn split_nul(output: &[u8]) -> Vec<String> {
+    output
+        .split(|byte| *byte == 0)
+        .filter(|field| !field.is_empty())
+        .map(|field| String::from_utf8_lossy(field).into_owned())
+        .collect()
+}
+
+fn is_indexable(path: &str) -> bool {
+    let mut components: Vec<&str> = path.split('/').collect();
+    components.pop();
+    detect_language(Path::new(path)).is_some() && !components.iter().any(|dir| is_skipped_dir(dir))
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(repo).args(args).output()?;
+    if !output.status.success() {
+        return Err(Error::Git {
+            command: args.first().unwrap_or(&"").to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::lancedb::schema::{EMBEDDING_DIM, SCHEMA_VERSION};
+
+/// file in a bundle holding the rows, as an Arrow IPC file
+pub const DATA_FILE: &str = "embeddings.arrow";
+/// file in a bundle describing how the rows were produced
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Describes an exported bundle so importers can check it fits their setup.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    /// embedding model the vectors came from, see `IndexerConfig::model_id`
+    pub model_id: String,
+    pub dimension: i32,
+    pub schema_version: u32,
+    /// git commit the table was indexed at, if it was indexed from git
+    pub commit: Option<String>,
+    pub row_count: usize,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl BundleManifest {
+    /// Reject bundles whose vectors or rows this build cannot use.
+    pub fn validate(&self, model_id: &str) -> Result<()> {
+        if self.model_id != model_id {
+            return Err(Error::IncompatibleBundle(format!(
+                "built with model '{}', expected '{}'",
+                self.model_id,
+                model_id
+            )));
+        }
+        if self.dimension != EMBEDDING_DIM {
+            return Err(Error::IncompatibleBundle(format!(
+                "embedding dimension {}, expected {}",
+                self.dimension,
+                EMBEDDING_DIM
+            )));
+        }
+        if self.schema_version != SCHEMA_VERSION {
+            return Err(Error::IncompatibleBundle(format!(
+                "schema version {}, expected {}",
+                self.schema_version,
+                SCHEMA_VERSION
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let json = fs::read_to_string(&path)
+            .map_err(|e| Error::IncompatibleBundle(format!("cannot read {}: {}", path.display(), e)))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        fs::write(dir.join(MANIFEST_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -1,18 +1,32 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::Path;
 use std::sync::Arc;
-use anyhow::Result;
-use lancedb::query::{ExecutableQuery, QueryBase};
-use lancedb::{connect, table, Table};
+use lancedb::query::{ExecutableQuery, QueryBase, Select};
+use lancedb::{connect, Table};
+use lancedb::table::{AddDataMode, CompactionOptions, NativeTable, OptimizeAction, OptimizeOptions};
+use arrow_ipc::reader::FileReader;
+use arrow_ipc::writer::FileWriter;
 use lancedb::connection::Connection;
 use arrow_schema::{Field, DataType};
 use arrow_array::{
-    RecordBatch, StringArray, TimestampMicrosecondArray, Int16Array, 
+    RecordBatch, StringArray, TimestampMicrosecondArray, Int32Array, Int64Array, BooleanArray,
     ListArray, FixedSizeListArray, Float32Array, ArrayRef, Array
 };
-use arrow_buffer::{OffsetBuffer, Buffer};
+use arrow_buffer::OffsetBuffer;
 use arrow_array::RecordBatchIterator;
 use futures::TryStreamExt;
+use chrono::Utc;
 
-use crate::lancedb::schema::{self, verify_embeddings_table, EMBEDDING_DIM};
+use crate::error::{Error, Result};
+use crate::lancedb::bundle::{BundleManifest, DATA_FILE};
+use crate::lancedb::maintenance::{directory_size, IndexStatus, OptimizeReport, StoreStats, TableVersion};
+use crate::lancedb::namespace::{Namespace, NamespacedRecord, DEFAULT_TABLE};
+use crate::lancedb::ranking::{cap_per_directory, rank_by_recency, rerank_mmr, QueryOptions, RERANK_OVERFETCH};
+use crate::lancedb::schema::{verify_embeddings_table, EMBEDDING_DIM, SCHEMA_VERSION};
+
+/// table config key holding the commit sha the table was indexed at
+const INDEXED_COMMIT_KEY: &str = "llama_pack.indexed_commit";
 
 /// mirrors schema def
 #[derive(Clone, Debug)]
@@ -23,14 +37,32 @@ pub struct EmbeddingRecord {
     pub language: String,
     pub last_modified: i64,
     pub last_accessed: i64,
-    pub line_count: i16,
+    pub line_count: i32,
     pub imported_by: Vec<String>,
+    /// signature and leading docs, for display
     pub content_preview: Option<String>,
+    /// full text that was embedded, so prompts can be built without re-reading the file
+    pub content: Option<String>,
+    pub byte_size: i64,
+    /// tokenizer tokens in `content`, before truncation to the model's max input
+    pub token_count: i32,
+    /// names defined in the chunk (functions, types, classes, ...)
+    pub symbols: Vec<String>,
+    pub is_test: bool,
+    pub is_generated: bool,
+}
+
+impl AsRef<EmbeddingRecord> for EmbeddingRecord {
+    fn as_ref(&self) -> &EmbeddingRecord {
+        self
+    }
 }
 
 /// LanceDbClient is the main interface for reading and writing code embeddings.
 pub struct LanceDbClient {
+    db: Connection,
     table: Arc<Table>,
+    namespace: Option<Namespace>,
 }
 
 impl LanceDbClient {
@@ -38,8 +70,34 @@ impl LanceDbClient {
     /// Creates the `embeddings` table if it doesn't exist.
     pub async fn connect(path: &str) -> Result<Self> {
         let db: Connection = connect(path).execute().await?;
-        let table = verify_embeddings_table(&db).await?;
-        Ok(Self { table })
+        let table = verify_embeddings_table(&db, DEFAULT_TABLE).await?;
+        Ok(Self { db, table, namespace: None })
+    }
+
+    /// Connect scoped to one repository and branch.
+    /// Reads and writes only touch that namespace's table, which is created if needed.
+    pub async fn connect_namespace(path: &str, namespace: &Namespace) -> Result<Self> {
+        let db: Connection = connect(path).execute().await?;
+        let table = verify_embeddings_table(&db, &namespace.table_name()).await?;
+        Ok(Self { db, table, namespace: Some(namespace.clone()) })
+    }
+
+    /// The namespace this client is scoped to, `None` for the default table.
+    pub fn namespace(&self) -> Option<&Namespace> {
+        self.namespace.as_ref()
+    }
+
+    /// Every namespace with a table in this database, sorted.
+    pub async fn list_namespaces(&self) -> Result<Vec<Namespace>> {
+        let mut namespaces: Vec<Namespace> = self.db
+            .table_names()
+            .execute()
+            .await?
+            .iter()
+            .filter_map(|name| Namespace::from_table_name(name))
+            .collect();
+        namespaces.sort();
+        Ok(namespaces)
     }
 
     pub async fn insert_embeddings(&self, records: Vec<EmbeddingRecord>) -> Result<()> {
@@ -65,22 +123,335 @@ impl LanceDbClient {
         Ok(())
     }
 
+    /// Delete every record whose path is in `paths`.
+    pub async fn delete_embeddings(&self, paths: &[String]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let escaped: Vec<String> = paths
+            .iter()
+            .map(|p| format!("'{}'", p.replace("'", "''")))
+            .collect();
+        self.table
+            .delete(&format!("path IN ({})", escaped.join(", ")))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Move a record to a new path without touching its embedding.
+    /// Any row already at `new_path` is replaced, e.g. when an editor saves
+    /// by renaming a temp file over the original.
+    pub async fn rename_embedding(&self, old_path: &str, new_path: &str) -> Result<()> {
+        if old_path == new_path {
+            return Ok(());
+        }
+
+        self.delete_embedding(new_path).await?;
+        self.table
+            .update()
+            .only_if(format!("path = '{}'", old_path.replace("'", "''")))
+            .column("path", format!("'{}'", new_path.replace("'", "''")))
+            .execute()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Commit the table was last indexed at by `git_index::index_commit`, if any.
+    pub async fn indexed_commit(&self) -> Result<Option<String>> {
+        let manifest = self.native_table()?.manifest().await?;
+        Ok(manifest.config.get(INDEXED_COMMIT_KEY).cloned())
+    }
+
+    /// Record `commit` as the revision the table now reflects.
+    pub async fn set_indexed_commit(&self, commit: &str) -> Result<()> {
+        self.native_table()?
+            .update_config([(INDEXED_COMMIT_KEY.to_string(), commit.to_string())])
+            .await?;
+        Ok(())
+    }
+
+    /// Forget the indexed commit, e.g. once rows from a working tree are written.
+    pub async fn clear_indexed_commit(&self) -> Result<()> {
+        self.native_table()?.delete_config_keys(&[INDEXED_COMMIT_KEY]).await?;
+        Ok(())
+    }
+
+    /// Write every row to a bundle directory `dir` that `import` can load elsewhere,
+    /// e.g. to build the index once in CI and ship it to developer machines.
+    /// `model_id` names the embedding model, see `IndexerConfig::model_id`.
+    pub async fn export(&self, dir: &Path, model_id: &str) -> Result<BundleManifest> {
+        fs::create_dir_all(dir)?;
+
+        let schema = self.table.schema().await?;
+        let mut writer = FileWriter::try_new(File::create(dir.join(DATA_FILE))?, &schema)?;
+        let mut row_count = 0;
+
+        let mut stream = self.table.query().execute().await?;
+        while let Some(batch) = stream.try_next().await? {
+            row_count += batch.num_rows();
+            writer.write(&batch.with_schema(schema.clone())?)?;
+        }
+        writer.finish()?;
+
+        let manifest = BundleManifest {
+            model_id: model_id.to_string(),
+            dimension: EMBEDDING_DIM,
+            schema_version: SCHEMA_VERSION,
+            commit: self.indexed_commit().await?,
+            row_count,
+            created_at: Utc::now(),
+        };
+        manifest.write(dir)?;
+
+        Ok(manifest)
+    }
+
+    /// Replace the table's rows with a bundle written by `export`.
+    /// Fails without touching the table if the bundle was built with another
+    /// model, dimension or schema version.
+    pub async fn import(&self, dir: &Path, model_id: &str) -> Result<BundleManifest> {
+        let manifest = BundleManifest::read(dir)?;
+        manifest.validate(model_id)?;
+
+        let schema = self.table.schema().await?;
+        let reader = FileReader::try_new(File::open(dir.join(DATA_FILE))?, None)?;
+        let batches = reader
+            .map(|batch| batch.and_then(|b| b.with_schema(schema.clone())))
+            .collect::<std::result::Result<Vec<RecordBatch>, _>>()?;
+
+        let row_count: usize = batches.iter().map(|b| b.num_rows()).sum();
+        if row_count != manifest.row_count {
+            return Err(Error::IncompatibleBundle(format!(
+                "manifest lists {} rows, data has {}",
+                manifest.row_count,
+                row_count
+            )));
+        }
+
+        // one overwrite keeps the swap atomic for concurrent readers
+        let data = RecordBatchIterator::new(batches.into_iter().map(Ok), schema);
+        self.table.add(data).mode(AddDataMode::Overwrite).execute().await?;
+
+        match &manifest.commit {
+            Some(commit) => self.set_indexed_commit(commit).await?,
+            None => self.clear_indexed_commit().await?,
+        }
+
+        Ok(manifest)
+    }
+
+    /// Compact small fragments, delete versions older than `older_than` and
+    /// bring indices up to date with newly added rows.
+    ///
+    /// Every write creates a version and a fragment, so long watch sessions and
+    /// re-indexes should be followed by this.
+    pub async fn optimize(&self, older_than: std::time::Duration) -> Result<OptimizeReport> {
+        let mut report = OptimizeReport::default();
+
+        let compacted = self.table
+            .optimize(OptimizeAction::Compact { options: CompactionOptions::default(), remap_options: None })
+            .await?;
+        if let Some(metrics) = compacted.compaction {
+            report.fragments_removed = metrics.fragments_removed;
+            report.fragments_added = metrics.fragments_added;
+        }
+
+        let pruned = self.table
+            .optimize(OptimizeAction::Prune {
+                older_than: Some(chrono::Duration::from_std(older_than)
+                    .map_err(|_| Error::InvalidInput(format!("Retention period too long: {:?}", older_than)))?),
+                delete_unverified: Some(false),
+                error_if_tagged_old_versions: Some(false),
+            })
+            .await?;
+        if let Some(removal) = pruned.prune {
+            report.versions_removed = removal.old_versions;
+            report.bytes_removed = removal.bytes_removed;
+        }
+
+        self.table.optimize(OptimizeAction::Index(OptimizeOptions::default())).await?;
+
+        Ok(report)
+    }
+
+    /// Row, fragment, version and index counts plus on-disk size.
+    pub async fn stats(&self) -> Result<StoreStats> {
+        let statistics = self.table.stats().await?;
+
+        let mut indices = Vec::new();
+        for index in self.table.list_indices().await? {
+            let coverage = self.table.index_stats(&index.name).await?;
+            indices.push(IndexStatus {
+                name: index.name,
+                columns: index.columns,
+                indexed_rows: coverage.as_ref().map(|c| c.num_indexed_rows).unwrap_or(0),
+                unindexed_rows: coverage.as_ref().map(|c| c.num_unindexed_rows).unwrap_or(0),
+            });
+        }
+
+        let uri = self.table.dataset_uri();
+        let local = Path::new(uri.strip_prefix("file://").unwrap_or(uri));
+
+        Ok(StoreStats {
+            version: self.table.version().await?,
+            version_count: self.table.list_versions().await?.len(),
+            row_count: statistics.num_rows,
+            fragment_count: statistics.fragment_stats.num_fragments,
+            small_fragment_count: statistics.fragment_stats.num_small_fragments,
+            data_bytes: statistics.total_bytes,
+            disk_bytes: local.is_dir().then(|| directory_size(local)),
+            indices,
+        })
+    }
+
+    /// Versions still on disk, oldest first. `optimize` prunes old ones.
+    pub async fn list_versions(&self) -> Result<Vec<TableVersion>> {
+        let mut versions: Vec<TableVersion> = self.table
+            .list_versions()
+            .await?
+            .into_iter()
+            .map(|v| TableVersion { version: v.version, timestamp: v.timestamp })
+            .collect();
+        versions.sort_by_key(|v| v.version);
+        Ok(versions)
+    }
+
+    /// Version this client currently reads from.
+    pub async fn version(&self) -> Result<u64> {
+        Ok(self.table.version().await?)
+    }
+
+    /// A read-only client over the table as it was at `version`, e.g. to
+    /// compare retrieval before and after a refactor. `self` is unaffected.
+    pub async fn checkout_version(&self, version: u64) -> Result<Self> {
+        let table = self.db.open_table(self.table.name()).execute().await?;
+        table.checkout(version).await?;
+
+        Ok(Self {
+            db: self.db.clone(),
+            table: Arc::new(table),
+            namespace: self.namespace.clone(),
+        })
+    }
+
+    /// Move a client from `checkout_version` back to the newest version,
+    /// making it writable again.
+    pub async fn checkout_latest(&self) -> Result<()> {
+        self.table.checkout_latest().await?;
+        Ok(())
+    }
+
+    /// Roll the table back to `version` by committing a new version with its
+    /// contents. Later versions stay available until pruned.
+    pub async fn restore_version(&self, version: u64) -> Result<()> {
+        // a checkout is read-only, so it happens on a separate handle: if the
+        // restore fails, `self` must still be writable
+        let table = self.db.open_table(self.table.name()).execute().await?;
+        table.checkout(version).await?;
+        table.restore().await?;
+
+        self.table.checkout_latest().await?;
+        Ok(())
+    }
+
+    fn native_table(&self) -> Result<&NativeTable> {
+        self.table
+            .as_native()
+            .ok_or_else(|| Error::Store(lancedb::Error::NotSupported {
+                message: "table metadata requires a local database".to_string(),
+            }))
+    }
+
+    /// Map of every indexed path to its content hash.
+    pub async fn list_hashes(&self) -> Result<HashMap<String, String>> {
+        let mut stream = self.table
+            .query()
+            .select(Select::columns(&["path", "hash"]))
+            .execute()
+            .await?;
+
+        let mut hashes = HashMap::new();
+        while let Some(batch) = stream.try_next().await? {
+            let paths = Self::column::<StringArray>(&batch, "path")?;
+            let hash_values = Self::column::<StringArray>(&batch, "hash")?;
+
+            for row_index in 0..batch.num_rows() {
+                hashes.insert(paths.value(row_index).to_string(), hash_values.value(row_index).to_string());
+            }
+        }
+
+        Ok(hashes)
+    }
+
     pub async fn update_embedding(&self, path: &str, record: EmbeddingRecord) -> Result<()> {
         if record.path != path {
-            return Err(anyhow::anyhow!(
+            return Err(Error::InvalidInput(format!(
                 "Path mismatch: method parameter '{}' != record.path '{}'", 
                 path, 
                 record.path
-            ));
+            )));
         }
         
-        // Delete existing record with the same path (ignore if it doesn't exist)
-        let _ = self.delete_embedding(path).await; // Don't fail if record doesn't exist
-        
-        self.insert_embeddings(vec![record]).await?;
+        self.upsert_embeddings(vec![record]).await
+    }
+
+    /// Replace the rows for each record's path, or insert them if new, in
+    /// one merge so a failure can't lose rows and each call is one version.
+    /// A stored `last_accessed` is kept: rewriting a file is not using it.
+    pub async fn upsert_embeddings(&self, mut records: Vec<EmbeddingRecord>) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let paths: Vec<String> = records.iter().map(|r| r.path.clone()).collect();
+        let accessed = self.last_accessed_times(&paths).await?;
+        for record in &mut records {
+            if let Some(&last_accessed) = accessed.get(&record.path) {
+                record.last_accessed = record.last_accessed.max(last_accessed);
+            }
+        }
+
+        let arrays = Self::create_arrow_arrays(&records)?;
+        let batch = Self::create_record_batch(arrays, &self.table).await?;
+        let schema = batch.schema();
+        let batches = RecordBatchIterator::new(vec![Ok(batch)].into_iter(), schema);
+
+        let mut merge = self.table.merge_insert(&["path"]);
+        merge.when_matched_update_all(None).when_not_matched_insert_all();
+        merge.execute(Box::new(batches)).await?;
+
         Ok(())
     }
 
+    /// `last_accessed` of each record in `paths` that exists.
+    async fn last_accessed_times(&self, paths: &[String]) -> Result<HashMap<String, i64>> {
+        let escaped: Vec<String> = paths
+            .iter()
+            .map(|p| format!("'{}'", p.replace("'", "''")))
+            .collect();
+        let mut stream = self.table
+            .query()
+            .only_if(format!("path IN ({})", escaped.join(", ")))
+            .select(Select::columns(&["path", "last_accessed"]))
+            .execute()
+            .await?;
+
+        let mut times = HashMap::new();
+        while let Some(batch) = stream.try_next().await? {
+            let paths = Self::column::<StringArray>(&batch, "path")?;
+            let last_accessed = Self::column::<TimestampMicrosecondArray>(&batch, "last_accessed")?;
+
+            for row_index in 0..batch.num_rows() {
+                times.insert(paths.value(row_index).to_string(), last_accessed.value(row_index));
+            }
+        }
+
+        Ok(times)
+    }
+
     pub async fn get_embedding(&self, path: &str) -> Result<Option<EmbeddingRecord>> {
         let query = format!("path = '{}'", path.replace("'", "''"));
         let mut stream = self.table
@@ -99,34 +470,112 @@ impl LanceDbClient {
     }
 
     pub async fn query_similar(&self, embedding: &[f32], limit: usize) -> Result<Vec<EmbeddingRecord>> {
+        self.query_similar_with(embedding, limit, &QueryOptions::default()).await
+    }
+
+    /// Vector search followed by the optional ranking stages in `options`.
+    /// Read-only: see `touch_embeddings` for marking hits as used.
+    pub async fn query_similar_with(&self, embedding: &[f32], limit: usize, options: &QueryOptions) -> Result<Vec<EmbeddingRecord>> {
+        let candidates = Self::search_table(&self.table, embedding, limit, options).await?;
+        Ok(rank(candidates, embedding, limit, options).into_iter().map(|(record, _)| record).collect())
+    }
+
+    /// Search several namespaces at once and merge the hits, best first.
+    /// Each result carries the namespace it came from. Ranking stages run
+    /// once over the merged candidates, so MMR and `max_per_directory`
+    /// apply across namespaces.
+    pub async fn query_namespaces(
+        &self,
+        namespaces: &[Namespace],
+        embedding: &[f32],
+        limit: usize,
+        options: &QueryOptions,
+    ) -> Result<Vec<NamespacedRecord>> {
+        let mut candidates: Vec<(NamespacedRecord, f32)> = Vec::new();
+
+        for namespace in namespaces {
+            let table = self.db
+                .open_table(namespace.table_name())
+                .execute()
+                .await
+                .map_err(|e| match Error::from(e) {
+                    Error::TableNotFound(_) => Error::TableNotFound(namespace.to_string()),
+                    other => other,
+                })?;
+
+            let hits = Self::search_table(&table, embedding, limit, options).await?;
+            candidates.extend(hits.into_iter().map(|(record, distance)| {
+                (NamespacedRecord { namespace: namespace.clone(), record, distance }, distance)
+            }));
+        }
+
+        candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+        Ok(rank(candidates, embedding, limit, options).into_iter().map(|(hit, _)| hit).collect())
+    }
+
+    /// vector search on one table, returning `(record, distance)` candidates
+    /// nearest first; enough of them for `rank` to pick `limit` from
+    async fn search_table(table: &Table, embedding: &[f32], limit: usize, options: &QueryOptions) -> Result<Vec<(EmbeddingRecord, f32)>> {
         if embedding.len() != EMBEDDING_DIM as usize {
-            return Err(anyhow::anyhow!(
-                "Invalid embedding dimension: expected {}, got {}", 
-                EMBEDDING_DIM, 
-                embedding.len()
-            ));
+            return Err(Error::DimensionMismatch {
+                expected: EMBEDDING_DIM as usize,
+                actual: embedding.len(),
+            });
         }
+
+        let candidates = if options.reranks() {
+            limit.saturating_mul(RERANK_OVERFETCH)
+        } else {
+            limit
+        };
     
-        let mut stream = self.table
+        let mut search = table
             .vector_search(embedding)?
-            .limit(limit)
-            .execute()
-            .await?;
+            .limit(candidates);
+        if let Some(filter) = options.filter() {
+            search = search.only_if(filter);
+        }
+        let mut stream = search.execute().await?;
 
-        let mut results = Vec::new();
+        let mut hits = Vec::new();
         while let Some(batch) = stream.try_next().await? {
+            let distances = batch.column_by_name("_distance")
+                .and_then(|c| c.as_any().downcast_ref::<Float32Array>().cloned());
             for row_index in 0..batch.num_rows() {
                 let record = Self::record_batch_to_embedding_record(&batch, row_index)?;
-                results.push(record);
+                let distance = distances.as_ref().map(|d| d.value(row_index)).unwrap_or(0.0);
+                hits.push((record, distance));
             }
         }
 
-        Ok(results)
+        Ok(hits)
+    }
+
+    /// Set `last_accessed` to now for every record in `paths`.
+    /// Call this whenever a file is opened by the user or used as prompt context;
+    /// searches never do it themselves, since every update commits a new version.
+    pub async fn touch_embeddings(&self, paths: &[String]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let escaped: Vec<String> = paths
+            .iter()
+            .map(|p| format!("'{}'", p.replace("'", "''")))
+            .collect();
+        self.table
+            .update()
+            .only_if(format!("path IN ({})", escaped.join(", ")))
+            .column("last_accessed", format!("to_timestamp_micros({})", Utc::now().timestamp_micros()))
+            .execute()
+            .await?;
+
+        Ok(())
     }
 
     pub async fn query_similar_to_file(&self, file_path: &str, limit: usize) -> Result<Vec<EmbeddingRecord>> {
         let file_record = self.get_embedding(file_path).await?
-            .ok_or_else(|| anyhow::anyhow!("No embedding found for file: {}", file_path))?;
+            .ok_or_else(|| Error::EmbeddingNotFound(file_path.to_string()))?;
         
         let mut similar_records = self.query_similar(&file_record.embedding, limit + 1).await?;
         similar_records.retain(|record| record.path != file_path);
@@ -137,7 +586,7 @@ impl LanceDbClient {
 
     // private helpers:
 
-    fn create_arrow_arrays(records: &[EmbeddingRecord]) -> Result<Vec<ArrayRef>> {
+    fn create_arrow_arrays(records: &[EmbeddingRecord]) -> Result<Vec<(&'static str, ArrayRef)>> {
         Self::validate_embeddings(records)?;
         
         let (paths, hashes, languages, last_modified, last_accessed, line_counts, content_previews) = 
@@ -159,107 +608,101 @@ impl LanceDbClient {
         let language_array = Arc::new(StringArray::from(languages)) as ArrayRef;
         let last_modified_array = Arc::new(TimestampMicrosecondArray::from(last_modified)) as ArrayRef;
         let last_accessed_array = Arc::new(TimestampMicrosecondArray::from(last_accessed)) as ArrayRef;
-        let line_count_array = Arc::new(Int16Array::from(line_counts)) as ArrayRef;
+        let line_count_array = Arc::new(Int32Array::from(line_counts)) as ArrayRef;
             
-        let (imported_by_values, imported_by_offsets) = Self::extract_imported_by(records);
-        let imported_by_string_array = Arc::new(StringArray::from(imported_by_values));
-        let imported_by_offsets = OffsetBuffer::new(imported_by_offsets.into());
-        let imported_by_array = Arc::new(ListArray::new(
-            Arc::new(Field::new("item", DataType::Utf8, false)),
-            imported_by_offsets,
-            imported_by_string_array,
-            None,
-        )) as ArrayRef;
+        let imported_by_array = Self::create_string_list_array(records.iter().map(|r| &r.imported_by), false);
 
         let content_preview_array = Arc::new(StringArray::from(content_previews)) as ArrayRef;
+        let contents: Vec<Option<String>> = records.iter().map(|r| r.content.clone()).collect();
+        let content_array = Arc::new(StringArray::from(contents)) as ArrayRef;
+
+        let byte_sizes: Vec<i64> = records.iter().map(|r| r.byte_size).collect();
+        let byte_size_array = Arc::new(Int64Array::from(byte_sizes)) as ArrayRef;
+        let token_counts: Vec<i32> = records.iter().map(|r| r.token_count).collect();
+        let token_count_array = Arc::new(Int32Array::from(token_counts)) as ArrayRef;
+        let symbols_array = Self::create_string_list_array(records.iter().map(|r| &r.symbols), true);
+        let is_test: Vec<bool> = records.iter().map(|r| r.is_test).collect();
+        let is_test_array = Arc::new(BooleanArray::from(is_test)) as ArrayRef;
+        let is_generated: Vec<bool> = records.iter().map(|r| r.is_generated).collect();
+        let is_generated_array = Arc::new(BooleanArray::from(is_generated)) as ArrayRef;
         
         let arrays = vec![
-            path_array,
-            hash_array,
-            embedding_array,
-            language_array,
-            last_modified_array,
-            last_accessed_array,
-            line_count_array,
-            imported_by_array, 
-            content_preview_array,
+            ("path", path_array),
+            ("hash", hash_array),
+            ("embedding", embedding_array),
+            ("language", language_array),
+            ("last_modified", last_modified_array),
+            ("last_accessed", last_accessed_array),
+            ("line_count", line_count_array),
+            ("imported_by", imported_by_array),
+            ("content_preview", content_preview_array),
+            ("content", content_array),
+            ("byte_size", byte_size_array),
+            ("token_count", token_count_array),
+            ("symbols", symbols_array),
+            ("is_test", is_test_array),
+            ("is_generated", is_generated_array),
         ];
 
         Ok(arrays)
     }
 
-    async fn create_record_batch(arrays: Vec<ArrayRef>, table: &Arc<Table>) -> Result<RecordBatch> {
+    /// arrange `arrays` in the table's column order, which a migrated table may not share
+    async fn create_record_batch(arrays: Vec<(&'static str, ArrayRef)>, table: &Arc<Table>) -> Result<RecordBatch> {
         let schema = table.schema().await?;
-        RecordBatch::try_new(schema, arrays)
-            .map_err(|e| anyhow::anyhow!("Failed to create record batch: {}", e))
+        let mut arrays: HashMap<&str, ArrayRef> = arrays.into_iter().collect();
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| arrays.remove(field.name().as_str())
+                .ok_or_else(|| Error::Schema(format!("No data for column {}", field.name()))))
+            .collect::<Result<Vec<ArrayRef>>>()?;
+        Ok(RecordBatch::try_new(schema, columns)?)
     }
 
     fn record_batch_to_embedding_record(batch: &RecordBatch, row_index: usize) -> Result<EmbeddingRecord> {
         if row_index >= batch.num_rows() {
-            return Err(anyhow::anyhow!("Row index {} out of bounds", row_index));
+            return Err(Error::InvalidInput(format!("Row index {} out of bounds", row_index)));
         }
 
-        let path_array = batch.column(0)
-            .as_any().downcast_ref::<StringArray>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast path column"))?;
-        let path = path_array.value(row_index).to_string();
-
-        let hash_array = batch.column(1)
-            .as_any().downcast_ref::<StringArray>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast hash column"))?;
-        let hash = hash_array.value(row_index).to_string();
-
-        let embedding_array = batch.column(2)
-            .as_any().downcast_ref::<FixedSizeListArray>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast embedding column"))?;
-        let embedding_list = embedding_array.value(row_index);
+        // columns are looked up by name: migrated tables and projections need not
+        // follow the order of `build_embeddings_schema`
+        let path = Self::column::<StringArray>(batch, "path")?.value(row_index).to_string();
+        let hash = Self::column::<StringArray>(batch, "hash")?.value(row_index).to_string();
 
+        let embedding_list = Self::column::<FixedSizeListArray>(batch, "embedding")?.value(row_index);
         let float_array = embedding_list
             .as_any().downcast_ref::<Float32Array>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast embedding values"))?;
+            .ok_or_else(|| Error::Schema("Failed to cast embedding values".to_string()))?;
         let embedding: Vec<f32> = (0..float_array.len())
             .map(|i| float_array.value(i))
             .collect();
 
-        let language_array = batch.column(3)
-            .as_any().downcast_ref::<StringArray>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast language column"))?;
-        let language = language_array.value(row_index).to_string();
-
-        let last_modified_array = batch.column(4)
-            .as_any().downcast_ref::<TimestampMicrosecondArray>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast last_modified column"))?;
-        let last_modified = last_modified_array.value(row_index);
-
-        let last_accessed_array = batch.column(5)
-            .as_any().downcast_ref::<TimestampMicrosecondArray>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast last_accessed column"))?;
-        let last_accessed = last_accessed_array.value(row_index);
-
-        let line_count_array = batch.column(6)
-            .as_any().downcast_ref::<Int16Array>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast line_count column"))?;
-        let line_count = line_count_array.value(row_index);
-
-        let imported_by_array = batch.column(7)
-            .as_any().downcast_ref::<ListArray>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast imported_by column"))?;
-        let imported_by_list = imported_by_array.value(row_index);
-        let string_array = imported_by_list
-            .as_any().downcast_ref::<StringArray>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast imported_by values"))?;
-        let imported_by: Vec<String> = (0..string_array.len())
-            .map(|i| string_array.value(i).to_string())
-            .collect();
-
-        let content_preview_array = batch.column(8)
-            .as_any().downcast_ref::<StringArray>()
-            .ok_or_else(|| anyhow::anyhow!("Failed to cast content_preview column"))?;
-        let content_preview = if content_preview_array.is_null(row_index) {
-            None
-        } else {
-            Some(content_preview_array.value(row_index).to_string())
-        };
+        let language = Self::column::<StringArray>(batch, "language")?.value(row_index).to_string();
+        let last_modified = Self::column::<TimestampMicrosecondArray>(batch, "last_modified")?.value(row_index);
+        let last_accessed = Self::column::<TimestampMicrosecondArray>(batch, "last_accessed")?.value(row_index);
+        let line_count = Self::column::<Int32Array>(batch, "line_count")?.value(row_index);
+
+        // nullable columns may be missing from a projection, and columns added
+        // by migrations are null on rows written before them
+        let imported_by = Self::read_string_list(batch, "imported_by", row_index)?;
+        let content_preview = Self::optional_column::<StringArray>(batch, "content_preview")?
+            .filter(|array| !array.is_null(row_index))
+            .map(|array| array.value(row_index).to_string());
+        let content = Self::optional_column::<StringArray>(batch, "content")?
+            .filter(|array| !array.is_null(row_index))
+            .map(|array| array.value(row_index).to_string());
+        let byte_size = Self::optional_column::<Int64Array>(batch, "byte_size")?
+            .filter(|array| !array.is_null(row_index))
+            .map_or(0, |array| array.value(row_index));
+        let token_count = Self::optional_column::<Int32Array>(batch, "token_count")?
+            .filter(|array| !array.is_null(row_index))
+            .map_or(0, |array| array.value(row_index));
+        let symbols = Self::read_string_list(batch, "symbols", row_index)?;
+        let is_test = Self::optional_column::<BooleanArray>(batch, "is_test")?
+            .is_some_and(|array| !array.is_null(row_index) && array.value(row_index));
+        let is_generated = Self::optional_column::<BooleanArray>(batch, "is_generated")?
+            .is_some_and(|array| !array.is_null(row_index) && array.value(row_index));
 
         Ok(EmbeddingRecord {
             path,
@@ -271,6 +714,12 @@ impl LanceDbClient {
             line_count,
             imported_by,
             content_preview,
+            content,
+            byte_size,
+            token_count,
+            symbols,
+            is_test,
+            is_generated,
         })
     }
 
@@ -279,17 +728,17 @@ impl LanceDbClient {
     fn validate_embeddings(records: &[EmbeddingRecord]) -> Result<()> {
         for record in records {
             if record.embedding.len() != EMBEDDING_DIM as usize {
-                return Err(anyhow::anyhow!(
-                    "Invalid embedding dimension: expected {}, got {}", 
-                    EMBEDDING_DIM, 
-                    record.embedding.len()
-                ));
+                return Err(Error::DimensionMismatch {
+                    expected: EMBEDDING_DIM as usize,
+                    actual: record.embedding.len(),
+                });
             }
         }
         Ok(())
     }
 
-    fn extract_basic_fields(records: &[EmbeddingRecord]) -> (Vec<String>, Vec<String>, Vec<String>, Vec<i64>, Vec<i64>, Vec<i16>, Vec<Option<String>>) {
+    #[allow(clippy::type_complexity)]
+    fn extract_basic_fields(records: &[EmbeddingRecord]) -> (Vec<String>, Vec<String>, Vec<String>, Vec<i64>, Vec<i64>, Vec<i32>, Vec<Option<String>>) {
         let paths = records.iter().map(|r| r.path.clone()).collect();
         let hashes = records.iter().map(|r| r.hash.clone()).collect();
         let languages = records.iter().map(|r| r.language.clone()).collect();
@@ -309,16 +758,75 @@ impl LanceDbClient {
         embedding_values
     }
 
-    fn extract_imported_by(records: &[EmbeddingRecord]) -> (Vec<String>, Vec<i32>) {
-        let mut imported_by_values = Vec::new();
-        let mut imported_by_offsets = vec![0i32];
+    fn create_string_list_array<'a>(lists: impl Iterator<Item = &'a Vec<String>>, items_nullable: bool) -> ArrayRef {
+        let mut values = Vec::new();
+        let mut offsets = vec![0i32];
         
-        for record in records {
-            imported_by_values.extend(record.imported_by.iter().cloned());
-            imported_by_offsets.push(imported_by_values.len() as i32);
+        for list in lists {
+            values.extend(list.iter().cloned());
+            offsets.push(values.len() as i32);
         }
         
-        (imported_by_values, imported_by_offsets)
+        Arc::new(ListArray::new(
+            Arc::new(Field::new("item", DataType::Utf8, items_nullable)),
+            OffsetBuffer::new(offsets.into()),
+            Arc::new(StringArray::from(values)),
+            None,
+        )) as ArrayRef
+    }
+
+    /// a required column, downcast to its array type
+    fn column<'b, T: Array + 'static>(batch: &'b RecordBatch, name: &str) -> Result<&'b T> {
+        Self::optional_column(batch, name)?
+            .ok_or_else(|| Error::Schema(format!("Missing {} column", name)))
+    }
+
+    /// a nullable column, `None` if the batch doesn't have it
+    fn optional_column<'b, T: Array + 'static>(batch: &'b RecordBatch, name: &str) -> Result<Option<&'b T>> {
+        match batch.column_by_name(name) {
+            Some(column) => column
+                .as_any().downcast_ref::<T>()
+                .map(Some)
+                .ok_or_else(|| Error::Schema(format!("Failed to cast {} column", name))),
+            None => Ok(None),
+        }
     }
 
+    fn read_string_list(batch: &RecordBatch, name: &str, row_index: usize) -> Result<Vec<String>> {
+        let Some(list_array) = Self::optional_column::<ListArray>(batch, name)? else {
+            return Ok(vec![]);
+        };
+        if list_array.is_null(row_index) {
+            return Ok(vec![]);
+        }
+
+        let list = list_array.value(row_index);
+        let string_array = list
+            .as_any().downcast_ref::<StringArray>()
+            .ok_or_else(|| Error::Schema(format!("Failed to cast {} values", name)))?;
+        Ok((0..string_array.len())
+            .map(|i| string_array.value(i).to_string())
+            .collect())
+    }
+}
+
+/// run the ranking stages in `options` over nearest-first candidates and keep the best `limit`
+fn rank<R: AsRef<EmbeddingRecord>>(
+    mut hits: Vec<(R, f32)>,
+    query: &[f32],
+    limit: usize,
+    options: &QueryOptions,
+) -> Vec<(R, f32)> {
+    let now = Utc::now().timestamp_micros();
+    if let Some(boost) = &options.recency {
+        hits = rank_by_recency(hits, query, boost, now);
+    }
+    if let Some(mmr) = &options.mmr {
+        hits = rerank_mmr(hits, query, mmr, options.recency.as_ref(), now);
+    }
+    if let Some(max) = options.max_per_directory {
+        hits = cap_per_directory(hits, max);
+    }
+    hits.truncate(limit);
+    hits
 }
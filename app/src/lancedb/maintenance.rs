@@ -0,0 +1,64 @@
+use std::fs;
+use std::path::Path;
+
+/// Result of `LanceDbClient::optimize`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OptimizeReport {
+    /// small fragments merged away by compaction
+    pub fragments_removed: usize,
+    /// fragments written by compaction
+    pub fragments_added: usize,
+    /// old table versions deleted by pruning
+    pub versions_removed: u64,
+    pub bytes_removed: u64,
+}
+
+/// Snapshot of the table's size and layout from `LanceDbClient::stats`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoreStats {
+    pub version: u64,
+    /// versions still kept on disk, including the current one
+    pub version_count: usize,
+    pub row_count: usize,
+    pub fragment_count: usize,
+    /// fragments small enough that `optimize` would compact them
+    pub small_fragment_count: usize,
+    /// bytes of live data in the current version
+    pub data_bytes: usize,
+    /// everything under the table directory, including old versions;
+    /// `None` for remote stores
+    pub disk_bytes: Option<u64>,
+    pub indices: Vec<IndexStatus>,
+}
+
+/// Coverage of one index, see `StoreStats::indices`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexStatus {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub indexed_rows: usize,
+    /// rows added since the index was last built or optimised
+    pub unindexed_rows: usize,
+}
+
+/// One entry of `LanceDbClient::list_versions`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableVersion {
+    pub version: u64,
+    /// when the write that created this version committed
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// total size of the files below `dir`
+pub(crate) fn directory_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else { return 0 };
+
+    entries
+        .flatten()
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => directory_size(&entry.path()),
+            Ok(_) => entry.metadata().map(|m| m.len()).unwrap_or(0),
+            Err(_) => 0,
+        })
+        .sum()
+}
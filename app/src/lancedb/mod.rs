@@ -1,4 +1,12 @@
 pub mod lancedb_client;
 pub mod schema;
+pub mod ranking;
+pub mod namespace;
+pub mod bundle;
+pub mod maintenance;
 
 pub use lancedb_client::{LanceDbClient, EmbeddingRecord};
+pub use ranking::{Mmr, QueryOptions, RecencyBoost};
+pub use namespace::{Namespace, NamespacedRecord};
+pub use bundle::BundleManifest;
+pub use maintenance::{IndexStatus, OptimizeReport, StoreStats, TableVersion};
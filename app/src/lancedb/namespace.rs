@@ -0,0 +1,102 @@
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::lancedb::lancedb_client::EmbeddingRecord;
+
+/// name of the table used when no namespace is given
+pub const DEFAULT_TABLE: &str = "embeddings";
+
+/// separates the prefix, repository and branch in a namespaced table name
+const SEPARATOR: &str = "__";
+
+/// A repository at a branch or commit. Each namespace gets its own table, so
+/// the same relative `path` can be indexed for several repos and branches.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Namespace {
+    pub repo: String,
+    /// branch name or commit sha
+    pub branch: String,
+}
+
+impl Namespace {
+    pub fn new(repo: &str, branch: &str) -> Self {
+        Self {
+            repo: repo.to_string(),
+            branch: branch.to_string(),
+        }
+    }
+
+    /// Parse `repo@branch`, e.g. `billing@main` or `org/billing@feature/retry`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.rsplit_once('@') {
+            Some((repo, branch)) if !repo.is_empty() && !branch.is_empty() => Ok(Self::new(repo, branch)),
+            _ => Err(Error::InvalidNamespace(spec.to_string())),
+        }
+    }
+
+    /// Table holding this namespace's embeddings.
+    pub fn table_name(&self) -> String {
+        format!("{DEFAULT_TABLE}{SEPARATOR}{}{SEPARATOR}{}", encode(&self.repo), encode(&self.branch))
+    }
+
+    /// Inverse of `table_name`; `None` for tables that are not namespaced.
+    pub fn from_table_name(name: &str) -> Option<Self> {
+        let rest = name.strip_prefix(DEFAULT_TABLE)?.strip_prefix(SEPARATOR)?;
+        let (repo, branch) = rest.split_once(SEPARATOR)?;
+        Some(Self {
+            repo: decode(repo)?,
+            branch: decode(branch)?,
+        })
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.repo, self.branch)
+    }
+}
+
+/// A search hit from `LanceDbClient::query_namespaces`, tagged with where it came from.
+#[derive(Clone, Debug)]
+pub struct NamespacedRecord {
+    pub namespace: Namespace,
+    pub record: EmbeddingRecord,
+    /// L2 distance from the query embedding
+    pub distance: f32,
+}
+
+impl AsRef<EmbeddingRecord> for NamespacedRecord {
+    fn as_ref(&self) -> &EmbeddingRecord {
+        &self.record
+    }
+}
+
+/// table names only allow `[A-Za-z0-9.-]` and `_`; everything else, including
+/// `_` itself, becomes `_xx` so the separator never appears inside a part
+fn encode(part: &str) -> String {
+    let mut encoded = String::with_capacity(part.len());
+    for byte in part.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'.' {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("_{:02x}", byte));
+        }
+    }
+    encoded
+}
+
+fn decode(part: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(part.len());
+    let mut rest = part.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'_' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
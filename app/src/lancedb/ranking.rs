@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::lancedb::lancedb_client::EmbeddingRecord;
+
+/// candidates fetched per requested result when a re-ranking stage is enabled
+pub const RERANK_OVERFETCH: usize = 4;
+
+/// Optional post-processing applied by `LanceDbClient::query_similar_with`.
+#[derive(Clone, Debug, Default)]
+pub struct QueryOptions {
+    /// blend vector similarity with how recently each file was touched
+    pub recency: Option<RecencyBoost>,
+    /// skip test sources and fixtures
+    pub exclude_tests: bool,
+    /// skip generated code and bindings
+    pub exclude_generated: bool,
+    /// diversify results so near-duplicates don't crowd out other code
+    pub mmr: Option<Mmr>,
+    /// keep at most this many hits from any one directory
+    pub max_per_directory: Option<usize>,
+}
+
+impl QueryOptions {
+    /// SQL filter for the vector search, if any exclusions are set.
+    /// rows migrated from older schemas have null flags and are kept.
+    pub fn filter(&self) -> Option<String> {
+        let mut conditions = Vec::new();
+        if self.exclude_tests {
+            conditions.push("(is_test IS NULL OR is_test = false)");
+        }
+        if self.exclude_generated {
+            conditions.push("(is_generated IS NULL OR is_generated = false)");
+        }
+
+        (!conditions.is_empty()).then(|| conditions.join(" AND "))
+    }
+
+    /// whether any stage may reorder or drop vector search hits, so extra candidates are needed
+    pub fn reranks(&self) -> bool {
+        self.recency.is_some() || self.mmr.is_some() || self.max_per_directory.is_some()
+    }
+}
+
+/// Maximal marginal relevance: each pick maximises
+/// `lambda * relevance - (1 - lambda) * max_similarity_to_already_picked`.
+#[derive(Clone, Debug)]
+pub struct Mmr {
+    /// `1.0` is pure relevance, `0.0` pure diversity
+    pub lambda: f32,
+}
+
+impl Default for Mmr {
+    fn default() -> Self {
+        Self { lambda: 0.5 }
+    }
+}
+
+/// Boosts records that were recently accessed or modified.
+///
+/// The final score is `(1 - weight) * similarity + weight * recency`, where
+/// similarity is the cosine between query and record embeddings and recency
+/// halves every `half_life` since the record was last touched.
+#[derive(Clone, Debug)]
+pub struct RecencyBoost {
+    /// share of the score given to recency, in `0.0..=1.0`
+    pub weight: f32,
+    pub half_life: Duration,
+}
+
+impl Default for RecencyBoost {
+    fn default() -> Self {
+        Self {
+            weight: 0.3,
+            half_life: Duration::from_secs(60 * 60 * 24), // one day
+        }
+    }
+}
+
+impl RecencyBoost {
+    /// decay factor in `0.0..=1.0` for a record, given the current time in microseconds
+    pub fn recency(&self, record: &EmbeddingRecord, now_micros: i64) -> f32 {
+        let last_touched = record.last_accessed.max(record.last_modified);
+        let age_secs = (now_micros - last_touched).max(0) as f64 / 1_000_000.0;
+        let half_life_secs = self.half_life.as_secs_f64().max(f64::EPSILON);
+
+        0.5f64.powf(age_secs / half_life_secs) as f32
+    }
+
+    /// blended score for a hit against the `query` embedding, higher is better
+    pub fn score(&self, record: &EmbeddingRecord, query: &[f32], now_micros: i64) -> f32 {
+        let weight = self.weight.clamp(0.0, 1.0);
+        (1.0 - weight) * similarity(query, record) + weight * self.recency(record, now_micros)
+    }
+}
+
+/// Cosine similarity of a record to the query, in `-1.0..=1.0`. Unlike the
+/// L2 distance of the search, it stays comparable to recency and redundancy
+/// for embeddings that aren't normalised.
+pub fn similarity(query: &[f32], record: &EmbeddingRecord) -> f32 {
+    cosine_similarity(query, &record.embedding)
+}
+
+/// Reorder `(record, distance)` hits so that each next hit is the most relevant
+/// one that is least like the hits before it. Relevance is the recency score
+/// when `boost` is given, plain similarity to `query` otherwise; both are on
+/// the same scale as the cosine redundancy.
+///
+/// The ranking functions take anything holding a record, so hits merged from
+/// several namespaces keep their tag.
+pub fn rerank_mmr<R: AsRef<EmbeddingRecord>>(
+    hits: Vec<(R, f32)>,
+    query: &[f32],
+    mmr: &Mmr,
+    boost: Option<&RecencyBoost>,
+    now_micros: i64,
+) -> Vec<(R, f32)> {
+    let lambda = mmr.lambda.clamp(0.0, 1.0);
+    let relevance: Vec<f32> = hits
+        .iter()
+        .map(|(record, _)| match boost {
+            Some(boost) => boost.score(record.as_ref(), query, now_micros),
+            None => similarity(query, record.as_ref()),
+        })
+        .collect();
+
+    let mut remaining: Vec<usize> = (0..hits.len()).collect();
+    // highest similarity of each remaining hit to anything picked so far
+    let mut redundancy = vec![0.0f32; hits.len()];
+    let mut order = Vec::with_capacity(hits.len());
+
+    while !remaining.is_empty() {
+        let (slot, _) = remaining
+            .iter()
+            .enumerate()
+            .map(|(slot, &i)| (slot, lambda * relevance[i] - (1.0 - lambda) * redundancy[i]))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("remaining is not empty");
+        let picked = remaining.swap_remove(slot);
+        order.push(picked);
+
+        for &i in &remaining {
+            let similarity = cosine_similarity(&hits[picked].0.as_ref().embedding, &hits[i].0.as_ref().embedding);
+            redundancy[i] = redundancy[i].max(similarity);
+        }
+    }
+
+    let mut hits: Vec<Option<(R, f32)>> = hits.into_iter().map(Some).collect();
+    order.into_iter().filter_map(|i| hits[i].take()).collect()
+}
+
+/// Drop hits beyond the first `max` from each directory, keeping order.
+pub fn cap_per_directory<R: AsRef<EmbeddingRecord>>(hits: Vec<(R, f32)>, max: usize) -> Vec<(R, f32)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+
+    hits.into_iter()
+        .filter(|(record, _)| {
+            let directory = record.as_ref().path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+            let count = counts.entry(directory.to_string()).or_insert(0);
+            *count += 1;
+            *count <= max
+        })
+        .collect()
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Reorder `(record, distance)` hits by the blended recency score, best first.
+pub fn rank_by_recency<R: AsRef<EmbeddingRecord>>(
+    hits: Vec<(R, f32)>,
+    query: &[f32],
+    boost: &RecencyBoost,
+    now_micros: i64,
+) -> Vec<(R, f32)> {
+    let mut scored: Vec<(f32, (R, f32))> = hits
+        .into_iter()
+        .map(|(record, distance)| (boost.score(record.as_ref(), query, now_micros), (record, distance)))
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().map(|(_, hit)| hit).collect()
+}
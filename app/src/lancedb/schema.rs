@@ -1,14 +1,19 @@
 use std::sync::Arc;
-use anyhow::Result;
-use ndarray::Data;
 use std::iter;
 use arrow_schema::{DataType, Field, Schema, TimeUnit};
 use arrow_array::{RecordBatchIterator};
 use lancedb::connection::Connection;
 use lancedb::Table;
+use lancedb::table::{ColumnAlteration, NewColumnTransform};
+
+use crate::error::{Error, Result};
 
 pub const EMBEDDING_DIM: i32 = 768;
 
+/// Bumped whenever `build_embeddings_schema` changes.
+/// 1: initial columns; 2: content; 3: Int32 line_count, size/token/symbol/test/generated metadata.
+pub const SCHEMA_VERSION: u32 = 3;
+
 fn build_embeddings_schema() -> Schema {
     Schema::new(vec![
         Field::new("path", DataType::Utf8, false),
@@ -22,27 +27,72 @@ fn build_embeddings_schema() -> Schema {
         Field::new("language", DataType::Utf8, false),
         Field::new("last_modified", DataType::Timestamp(TimeUnit::Microsecond, None), false),
         Field::new("last_accessed", DataType::Timestamp(TimeUnit::Microsecond, None), false),
-        Field::new("line_count", DataType::Int16, false),
+        Field::new("line_count", DataType::Int32, false),
         Field::new("imported_by", DataType::List(Arc::new(Field::new("item", DataType::Utf8, false))), true),
         Field::new("content_preview", DataType::Utf8, true), 
+        Field::new("content", DataType::Utf8, true),
+        Field::new("byte_size", DataType::Int64, true),
+        Field::new("token_count", DataType::Int32, true),
+        // nested fields must be nullable too for the column to be added by migration
+        Field::new("symbols", DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))), true),
+        Field::new("is_test", DataType::Boolean, true),
+        Field::new("is_generated", DataType::Boolean, true),
     ])
 }
 
-/// verify the embeddings table exists; create if it does not.
-pub async fn verify_embeddings_table(db: &Connection) -> Result<Arc<Table>> {
+/// verify the embeddings table `name` exists; create if it does not.
+pub async fn verify_embeddings_table(db: &Connection, name: &str) -> Result<Arc<Table>> {
     let schema = build_embeddings_schema();
 
-    match db.open_table("embeddings").execute().await {
-        Ok(table) => Ok(Arc::new(table)),
+    match db.open_table(name).execute().await {
+        Ok(table) => {
+            migrate_embeddings_table(&table, &schema).await?;
+            Ok(Arc::new(table))
+        }
         Err(_) => {
             let schema_arc = Arc::new(schema.clone());
             let empty_batches = RecordBatchIterator::new(iter::empty(), schema_arc.clone());
 
             let table = db
-                .create_table("embeddings", Box::new(empty_batches))
+                .create_table(name, Box::new(empty_batches))
                 .execute()
                 .await?;
             Ok(Arc::new(table))
         }
     }
 }
+
+/// bring a table created by an older version up to `schema`.
+/// new columns are only ever appended, as nullable columns.
+async fn migrate_embeddings_table(table: &Table, schema: &Schema) -> Result<()> {
+    let existing = table.schema().await?;
+
+    // line_count was Int16 and overflowed on files past 32,767 lines
+    if let Ok(line_count) = existing.field_with_name("line_count") {
+        if line_count.data_type() == &DataType::Int16 {
+            table
+                .alter_columns(&[ColumnAlteration::new("line_count".to_string()).cast_to(DataType::Int32)])
+                .await?;
+        }
+    }
+
+    let missing: Vec<Field> = schema
+        .fields()
+        .iter()
+        .filter(|field| existing.field_with_name(field.name()).is_err())
+        .map(|field| field.as_ref().clone())
+        .collect();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    if let Some(field) = missing.iter().find(|field| !field.is_nullable()) {
+        return Err(Error::Schema(format!("Cannot migrate embeddings table: new column '{}' is not nullable", field.name())));
+    }
+
+    table
+        .add_columns(NewColumnTransform::AllNulls(Arc::new(Schema::new(missing))), None)
+        .await?;
+    Ok(())
+}
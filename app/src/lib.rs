@@ -1,4 +1,13 @@
+pub mod error;
+pub mod agent;
 pub mod session;
 pub mod embedder;
 pub mod ollama_client;
-pub mod lancedb;
\ No newline at end of file
+pub mod backend;
+pub mod lancedb;
+pub mod embeddings_controller;
+pub mod watcher;
+pub mod git_index;
+pub mod source_analysis;
+
+pub use error::{Error, Result};
@@ -1,116 +1,488 @@
-// use std::env;
-// use std::error::Error;
-// use std::io::{self, Write};
-
-// use llama_pack::session::SessionManager;
-// use llama_pack::ollama_client::OllamaClient;
-// use llama_pack::embedder::Embedder;
-
-// fn main() -> Result<(), Box<dyn Error>> {
-//     // println!("Ollama Code");
-//     println!("===========================");
-
-//     let mut ollama_client = OllamaClient::new();
-
-//     match ollama_client.validate_daemon() {
-//         Ok(true) => {
-//             // Daemon is running, continue
-//             println!("Ollama daemon is running.")
-//         }
-//         Ok(false) | Err(_) => {
-//             println!("Launching Ollama daemon.");
-//             match ollama_client.launch_daemon() {
-//                 Ok(()) => {
-//                     println!("Daemon started successfully.");
-//                 }
-//                 Err(e) => {
-//                     eprintln!("Failed to start daemon: {}", e);
-//                     eprintln!("Please ensure Ollama is installed and try running 'ollama serve' manually.");
-//                     std::process::exit(1);
-//                 }
-//             }
-//         }
-//     }
-
-//     let selected_model = match ollama_client.select_model() {
-//         Ok(model) => {
-//             println!("Selected LLM: {}", model);
-//             model
-//         }
-//         Err(e) => {
-//             eprintln!("Failed to select LLM: {}", e);
-
-//             std::process::exit(1);
-//         }
-//     };
-    
-//     // Get current working directory
-//     let current_dir = env::current_dir()?;
-//     println!("Working directory: {}", current_dir.display());
-    
-//     // Auto-start new session
-//     let mut session_manager = SessionManager::new_session()?;
-//     println!("New session started. Type 'exit' to quit.\n");
-    
-//     // Start prompt loop
-//     prompt_loop(&mut session_manager, &ollama_client, &selected_model)?;
-
-//     Ok(())
-// }
-
-// fn prompt_loop(session_manager: &mut SessionManager, ollama_client: &OllamaClient, model: &str) -> Result<(), Box<dyn Error>> {
-//     loop {
-//         print!("{}> ", model.split(':').next().unwrap_or(model));
-//         io::stdout().flush()?;
-
-//         let mut input = String::new();
-//         io::stdin().read_line(&mut input)?;
-//         let input = input.trim();
-
-//         // Check for exit command
-//         if input == "exit" || input == "quit" {
-//             println!("Goodbye!");
-//             break;
-//         }
-
-//         // Skip empty inputs
-//         if input.is_empty() {
-//             continue;
-//         }
-
-//         // Query Ollama with selected model
-//         println!("Thinking...");
-//         match ollama_client.query_model(model, input) {
-//             Ok(response) => {
-//                 println!("\n{}\n", response);
-                
-//                 // Save to session log
-//                 if let Err(e) = session_manager.save_log(input, &response) {
-//                     eprintln!("Warning: Failed to save to session: {}", e);
-//                 }
-                
-
-//             }
-//             Err(e) => {
-//                 eprintln!("Error querying Ollama: {}", e);
-//                 eprintln!("Make sure Ollama is running and the '{}' model is available.", model);
-//             }
-//         }
-//     }
-
-//     Ok(())
-// }
-
-mod lancedb;
-
-use lancedb::LanceDbClient;
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use llama_pack::backend::{BackendConfig, LlmBackend, OpenAiClient};
+use llama_pack::lancedb::{LanceDbClient, Namespace};
+use llama_pack::embeddings_controller::{find_stale_records, index_directory, IndexerConfig, Staleness};
+use llama_pack::git_index::index_commit;
+use llama_pack::ollama_client::{
+    CancellationToken, ChatChunk, ChatMessage, DaemonConfig, DaemonManager, DaemonMode, DaemonStatus, GenerateOptions, ModelProfiles,
+    OllamaClient, OllamaConfig, SystemPrompts,
+};
+use llama_pack::session::SessionManager;
+use llama_pack::watcher::{watch_directory_with, WatchConfig};
+use llama_pack::Error;
 use anyhow::Result;
 
+/// estimated tokens of earlier turns sent back with each chat prompt
+const HISTORY_TOKEN_BUDGET: usize = 4096;
+/// optional per-model generation defaults, merged over the built-in profiles
+const PROFILES_FILE: &str = ".coder_profiles.json";
+/// optional team system prompts, see `SystemPrompts`
+const PROMPTS_FILE: &str = ".coder_prompts.json";
+/// optional Ollama host, auth and timeouts, see `OllamaConfig`
+const OLLAMA_CONFIG_FILE: &str = ".coder_ollama.json";
+/// optional choice of server, e.g. an OpenAI-compatible one; see `BackendConfig`
+const BACKEND_CONFIG_FILE: &str = ".coder_backend.json";
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let mut args: Vec<String> = env::args().collect();
+
+    // `--ns repo@branch` scopes any command to one namespace
+    let namespace = match args.iter().position(|arg| arg == "--ns") {
+        Some(index) if index + 1 < args.len() => {
+            let spec = args.remove(index + 1);
+            args.remove(index);
+            Some(Namespace::parse(&spec)?)
+        }
+        Some(_) => {
+            eprintln!("--ns expects repo@branch");
+            std::process::exit(1);
+        }
+        None => None,
+    };
+
     println!("Connecting to LanceDB...");
-    let client = LanceDbClient::connect("./.vector_store").await?;
+    let client = match &namespace {
+        Some(namespace) => LanceDbClient::connect_namespace("./.vector_store", namespace).await?,
+        None => LanceDbClient::connect("./.vector_store").await?,
+    };
     println!("LanceDbClient initialized successfully.");
 
+    match args.get(1).map(String::as_str) {
+        Some("index") => {
+            let root = match args.get(2) {
+                Some(path) => PathBuf::from(path),
+                None => env::current_dir()?,
+            };
+            println!("Indexing {}", root.display());
+
+            let stats = index_directory(&client, &root, &IndexerConfig::default()).await?;
+            println!("Indexed {} files ({} skipped).", stats.files_indexed, stats.files_skipped);
+            print_failures(&stats.failed);
+        }
+        Some("index-commit") => {
+            let rev = args.get(2).map(String::as_str).unwrap_or("HEAD");
+            let repo = match args.get(3) {
+                Some(path) => PathBuf::from(path),
+                None => env::current_dir()?,
+            };
+            println!("Indexing {} at {}", repo.display(), rev);
+
+            let stats = index_commit(&client, &repo, rev, &IndexerConfig::default()).await?;
+            match &stats.previous_commit {
+                Some(previous) if *previous == stats.commit => println!("Already indexed at {}.", stats.commit),
+                _ => println!(
+                    "Indexed {} files ({} skipped), renamed {}, deleted {}; now at {}.",
+                    stats.indexed.files_indexed,
+                    stats.indexed.files_skipped,
+                    stats.files_renamed,
+                    stats.files_deleted,
+                    stats.commit,
+                ),
+            }
+            print_failures(&stats.indexed.failed);
+        }
+        Some("watch") => {
+            let root = match args.get(2) {
+                Some(path) => PathBuf::from(path),
+                None => env::current_dir()?,
+            };
+            let config = WatchConfig::default();
+
+            println!("Indexing {}", root.display());
+            let stats = index_directory(&client, &root, &config.indexer).await?;
+            print_failures(&stats.failed);
+
+            println!("Watching {} for changes. Press Ctrl-C to stop.", root.display());
+            let shutdown = async {
+                let _ = tokio::signal::ctrl_c().await;
+            };
+            watch_directory_with(&client, &root, &config, shutdown, |stats| print_failures(&stats.failed)).await?;
+        }
+        Some("stale") => {
+            let root = match args.get(2) {
+                Some(path) => PathBuf::from(path),
+                None => env::current_dir()?,
+            };
+
+            let stale = find_stale_records(&client, &root).await?;
+            for (path, staleness) in &stale {
+                match staleness {
+                    Staleness::Missing => println!("missing   {}", path),
+                    Staleness::Modified => println!("modified  {}", path),
+                }
+            }
+            println!("{} stale records.", stale.len());
+        }
+        Some("export") => {
+            let Some(dir) = args.get(2) else {
+                eprintln!("Usage: export <dir>");
+                std::process::exit(1);
+            };
+            let manifest = client.export(Path::new(dir), &IndexerConfig::default().model_id()).await?;
+            println!("Exported {} rows to {}.", manifest.row_count, dir);
+        }
+        Some("import") => {
+            let Some(dir) = args.get(2) else {
+                eprintln!("Usage: import <dir>");
+                std::process::exit(1);
+            };
+            let manifest = client.import(Path::new(dir), &IndexerConfig::default().model_id()).await?;
+            println!("Imported {} rows from {}.", manifest.row_count, dir);
+            if let Some(commit) = &manifest.commit {
+                println!("Index reflects commit {}.", commit);
+            }
+        }
+        Some("optimize") => {
+            let days: u64 = match args.get(2) {
+                Some(days) => days.parse()?,
+                None => 7,
+            };
+            let report = client.optimize(Duration::from_secs(days * 24 * 60 * 60)).await?;
+            println!(
+                "Compacted {} fragments into {}, removed {} old versions ({} bytes).",
+                report.fragments_removed, report.fragments_added, report.versions_removed, report.bytes_removed,
+            );
+        }
+        Some("stats") => {
+            let stats = client.stats().await?;
+            println!("version:    {} ({} kept)", stats.version, stats.version_count);
+            println!("rows:       {}", stats.row_count);
+            println!("fragments:  {} ({} small)", stats.fragment_count, stats.small_fragment_count);
+            println!("data size:  {} bytes", stats.data_bytes);
+            if let Some(disk_bytes) = stats.disk_bytes {
+                println!("disk size:  {} bytes", disk_bytes);
+            }
+            if stats.indices.is_empty() {
+                println!("indices:    none");
+            }
+            for index in &stats.indices {
+                println!(
+                    "index:      {} on {} ({} indexed, {} unindexed)",
+                    index.name, index.columns.join(", "), index.indexed_rows, index.unindexed_rows,
+                );
+            }
+        }
+        Some("versions") => {
+            let current = client.version().await?;
+            for version in client.list_versions().await? {
+                let marker = if version.version == current { "*" } else { " " };
+                println!("{} {:>6}  {}", marker, version.version, version.timestamp.to_rfc3339());
+            }
+        }
+        Some("rollback") => {
+            let Some(version) = args.get(2) else {
+                eprintln!("Usage: rollback <version>");
+                std::process::exit(1);
+            };
+            client.restore_version(version.parse()?).await?;
+            println!("Restored version {} as version {}.", version, client.version().await?);
+        }
+        Some("namespaces") => {
+            for namespace in client.list_namespaces().await? {
+                println!("{}", namespace);
+            }
+        }
+        Some("models") => {
+            for model in ollama_client()?.list_models().await? {
+                println!("{:<40} {:>8} {:>12} bytes", model.name, model.details.parameter_size, model.size);
+            }
+        }
+        Some("ps") => {
+            for model in ollama_client()?.running_models().await? {
+                let expires = model.expires_at.map(|at| at.to_rfc3339()).unwrap_or_default();
+                println!("{:<40} {:>12} bytes ({} in VRAM)  until {}", model.name, model.size, model.size_vram, expires);
+            }
+        }
+        Some("pull") => {
+            let Some(model) = args.get(2) else {
+                eprintln!("Usage: pull <model>");
+                std::process::exit(1);
+            };
+            let mut last_status = String::new();
+            ollama_client()?.pull_with(model, |progress| {
+                if progress.status != last_status {
+                    println!("{}", progress.status);
+                    last_status = progress.status.clone();
+                }
+            }).await?;
+        }
+        Some("show") => {
+            let Some(model) = args.get(2) else {
+                eprintln!("Usage: show <model>");
+                std::process::exit(1);
+            };
+            let info = ollama_client()?.show_model(model).await?;
+            println!("family:        {}", info.details.family);
+            println!("parameters:    {}", info.details.parameter_size);
+            println!("quantization:  {}", info.details.quantization_level);
+            println!("capabilities:  {}", info.capabilities.join(", "));
+            if !info.parameters.is_empty() {
+                println!("defaults:\n{}", info.parameters);
+            }
+        }
+        Some("rm") => {
+            let Some(model) = args.get(2) else {
+                eprintln!("Usage: rm <model>");
+                std::process::exit(1);
+            };
+            ollama_client()?.delete_model(model).await?;
+            println!("Deleted {}.", model);
+        }
+        Some("cp") => {
+            let (Some(source), Some(destination)) = (args.get(2), args.get(3)) else {
+                eprintln!("Usage: cp <source> <destination>");
+                std::process::exit(1);
+            };
+            ollama_client()?.copy_model(source, destination).await?;
+            println!("Copied {} to {}.", source, destination);
+        }
+        Some("chat") | None => chat().await?,
+        Some(other) => {
+            eprintln!("Unknown command: {}", other);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// files an indexing run couldn't embed
+fn print_failures(failed: &[(String, Error)]) {
+    for (path, e) in failed {
+        eprintln!("Failed to embed {}: {}", path, e);
+    }
+}
+
+/// `.coder_backend.json` if present, else Ollama configured by `.coder_ollama.json`
+fn backend_config() -> Result<BackendConfig> {
+    let backend_path = Path::new(BACKEND_CONFIG_FILE);
+    let ollama_path = Path::new(OLLAMA_CONFIG_FILE);
+    Ok(if backend_path.exists() {
+        BackendConfig::load(backend_path)?
+    } else if ollama_path.exists() {
+        BackendConfig::Ollama(OllamaConfig::load(ollama_path)?)
+    } else {
+        BackendConfig::default()
+    })
+}
+
+fn ollama_client() -> Result<OllamaClient> {
+    match backend_config()? {
+        BackendConfig::Ollama(config) => Ok(OllamaClient::builder().config(config).build()?),
+        _ => anyhow::bail!("model management needs the Ollama backend; see {}", BACKEND_CONFIG_FILE),
+    }
+}
+
+async fn chat() -> Result<()> {
+    println!("===========================");
+
+    let mut profiles = ModelProfiles::builtin();
+    let profiles_path = Path::new(PROFILES_FILE);
+    if profiles_path.exists() {
+        profiles.extend(ModelProfiles::load(profiles_path)?);
+    }
+    let prompts_path = Path::new(PROMPTS_FILE);
+    let prompts = if prompts_path.exists() {
+        SystemPrompts::load(prompts_path)?
+    } else {
+        SystemPrompts::default()
+    };
+
+    // held until the session ends, when a daemon this process started is stopped
+    let mut daemon = None;
+    let (backend, selected_model): (Box<dyn LlmBackend>, String) = match backend_config()? {
+        BackendConfig::OpenAi(config) => {
+            let client = OpenAiClient::from_config(&config)?.with_profiles(profiles);
+            println!("Using the OpenAI-compatible server at {}.", client.base_url());
+            let model = select_served_model(&client)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to select LLM: {}", e))?;
+            (Box::new(client), model)
+        }
+        BackendConfig::Ollama(config) => {
+            let ollama_client = OllamaClient::builder()
+                .config(config)
+                .profiles(profiles)
+                .system_prompts(prompts.clone())
+                .build()?;
+            daemon = Some(start_daemon(&ollama_client).await?);
+            // an error drops `daemon`, which still stops what this process started
+            let model = ollama_client
+                .select_model()
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to select LLM: {}", e))?;
+            (Box::new(ollama_client), model)
+        }
+    };
+    println!("Selected LLM: {}", selected_model);
+
+    let current_dir = env::current_dir()?;
+    println!("Working directory: {}", current_dir.display());
+
+    let mut session_manager = SessionManager::new_session()?;
+    println!("New session started. Type 'exit' to quit.\n");
+
+    let result = prompt_loop(&mut session_manager, backend.as_ref(), &prompts, &selected_model).await;
+    if let Some(mut daemon) = daemon {
+        if let Err(e) = daemon.shutdown().await {
+            eprintln!("Failed to stop Ollama daemon: {}", e);
+        }
+    }
+    result
+}
+
+/// make sure a daemon is up
+async fn start_daemon(ollama_client: &OllamaClient) -> Result<DaemonManager> {
+    // shared, so several llama_pack sessions reuse one daemon and the last one out stops it
+    let mut daemon = DaemonManager::new(DaemonConfig::new(ollama_client.base_url(), DaemonMode::Shared));
+    match daemon.ensure_running(ollama_client).await {
+        Ok(DaemonStatus::AlreadyRunning) => println!("Ollama daemon is running."),
+        Ok(DaemonStatus::Joined) => println!("Using the Ollama daemon started by another llama_pack session."),
+        Ok(DaemonStatus::Started) => {
+            println!("Daemon started successfully. Logs: {}", daemon.config().log_file.display());
+        }
+        Err(e) => anyhow::bail!(
+            "Failed to start daemon: {}\nPlease ensure Ollama is installed and try running 'ollama serve' manually.",
+            e
+        ),
+    }
+    Ok(daemon)
+}
+
+/// pick one of the models an OpenAI-compatible server offers
+async fn select_served_model(backend: &dyn LlmBackend) -> Result<String> {
+    let models = backend.list_models().await?;
+    match models.as_slice() {
+        [] => anyhow::bail!("{} serves no models", backend.base_url()),
+        [model] => return Ok(model.clone()),
+        _ => {}
+    }
+
+    println!("LLMs on this server:");
+    for (i, model) in models.iter().enumerate() {
+        println!("{}. {}", i + 1, model);
+    }
+    loop {
+        print!("Select a LLM (1-{}): ", models.len());
+        io::stdout().flush()?;
+
+        // off the runtime, like the REPL's reads
+        let input = tokio::task::spawn_blocking(|| {
+            let mut input = String::new();
+            io::stdin().read_line(&mut input).map(|_| input)
+        })
+        .await??;
+        match input.trim().parse::<usize>() {
+            Ok(choice) if (1..=models.len()).contains(&choice) => return Ok(models[choice - 1].clone()),
+            _ => println!("Invalid selection. Please try again."),
+        }
+    }
+}
+
+async fn prompt_loop(
+    session_manager: &mut SessionManager,
+    backend: &dyn LlmBackend,
+    prompts: &SystemPrompts,
+    model: &str,
+) -> Result<()> {
+    // Ctrl-C cancels the reply being generated; at the prompt it ends the session
+    let generating: Arc<Mutex<Option<CancellationToken>>> = Arc::default();
+    let (quit_tx, mut quit) = tokio::sync::mpsc::unbounded_channel();
+    let interrupts = tokio::spawn({
+        let generating = generating.clone();
+        async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                match generating.lock().unwrap().as_ref() {
+                    Some(token) => token.cancel(),
+                    None => {
+                        let _ = quit_tx.send(());
+                    }
+                }
+            }
+        }
+    });
+
+    // a plain thread rather than the blocking pool, which the runtime would
+    // wait on at exit while it sits in a read
+    let (lines_tx, mut lines) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in io::stdin().lines() {
+            if lines_tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    loop {
+        print!("{}> ", model.split(':').next().unwrap_or(model));
+        io::stdout().flush()?;
+
+        let line = tokio::select! {
+            line = lines.recv() => line,
+            _ = quit.recv() => {
+                println!();
+                None
+            }
+        };
+        let Some(input) = line else {
+            break; // EOF or Ctrl-C
+        };
+        let input = input?;
+        let input = input.trim();
+
+        if input == "exit" || input == "quit" {
+            println!("Goodbye!");
+            break;
+        }
+        if input.is_empty() {
+            continue;
+        }
+
+        let mut messages = vec![ChatMessage::system(prompts.for_model(model))];
+        messages.extend(session_manager.history(HISTORY_TOKEN_BUDGET));
+        messages.push(ChatMessage::user(input));
+
+        println!("Thinking... (Ctrl-C to stop)");
+        let token = CancellationToken::new();
+        *generating.lock().unwrap() = Some(token.clone());
+        let options = GenerateOptions { cancel: Some(token), ..Default::default() };
+        let mut partial = String::new();
+        let mut print_chunk = |chunk: &ChatChunk| {
+            print!("{}", chunk.message.content);
+            let _ = io::stdout().flush();
+            partial.push_str(&chunk.message.content);
+        };
+        let printed = backend.reply(model, &messages, &options, &mut print_chunk).await;
+        *generating.lock().unwrap() = None;
+
+        match printed {
+            Ok(reply) => {
+                println!("\n");
+
+                if let Err(e) = session_manager.save_log(input, &reply.content) {
+                    eprintln!("Warning: Failed to save to session: {}", e);
+                }
+            }
+            Err(Error::Cancelled) => {
+                println!("\n[interrupted]\n");
+
+                if let Err(e) = session_manager.save_interrupted(input, &partial) {
+                    eprintln!("Warning: Failed to save to session: {}", e);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error querying {}: {}", backend.name(), e);
+                eprintln!("Make sure the server at {} is running and the '{}' model is available.", backend.base_url(), model);
+            }
+        }
+    }
+
+    interrupts.abort();
     Ok(())
 }
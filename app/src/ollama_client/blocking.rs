@@ -0,0 +1,91 @@
+//! Synchronous wrapper around the async `OllamaClient`, for callers without a
+//! tokio runtime. Calling it from inside a runtime panics; use the async
+//! client there instead.
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::error::Result;
+use crate::ollama_client::{ChatChunk, ChatMessage, GenerationChunk, ModelProfiles, OllamaClientBuilder, SystemPrompts};
+
+pub struct OllamaClient {
+    inner: super::OllamaClient,
+    runtime: Runtime,
+}
+
+impl OllamaClient {
+    pub fn new() -> Result<Self> {
+        Self::from_async(super::OllamaClient::new())
+    }
+
+    pub fn with_base_url(base_url: &str) -> Result<Self> {
+        Self::from_async(super::OllamaClient::with_base_url(base_url))
+    }
+
+    /// Blocking client from a configured `OllamaClientBuilder`.
+    pub fn from_builder(builder: OllamaClientBuilder) -> Result<Self> {
+        Self::from_async(builder.build()?)
+    }
+
+    /// See `OllamaClient::with_profiles`.
+    pub fn with_profiles(self, profiles: ModelProfiles) -> Self {
+        let Self { inner, runtime } = self;
+        Self { inner: inner.with_profiles(profiles), runtime }
+    }
+
+    /// See `OllamaClient::with_system_prompts`.
+    pub fn with_system_prompts(self, prompts: SystemPrompts) -> Self {
+        let Self { inner, runtime } = self;
+        Self { inner: inner.with_system_prompts(prompts), runtime }
+    }
+
+    fn from_async(inner: super::OllamaClient) -> Result<Self> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(Self { inner, runtime })
+    }
+
+    pub fn validate_daemon(&self) -> Result<bool> {
+        self.runtime.block_on(self.inner.validate_daemon())
+    }
+
+    pub fn wait_for_daemon(&self) -> Result<()> {
+        self.runtime.block_on(self.inner.wait_for_daemon())
+    }
+
+    pub fn launch_daemon(&mut self) -> Result<()> {
+        self.runtime.block_on(self.inner.launch_daemon())
+    }
+
+    pub fn select_model(&self) -> Result<String> {
+        self.runtime.block_on(self.inner.select_model())
+    }
+
+    pub fn query_model(&self, model: &str, prompt: &str) -> Result<String> {
+        self.runtime.block_on(self.inner.query_model(model, prompt))
+    }
+
+    /// See `OllamaClient::query_model_with`; `on_chunk` runs on the calling thread.
+    pub fn query_model_with<F>(&self, model: &str, prompt: &str, on_chunk: F) -> Result<String>
+    where
+        F: FnMut(&GenerationChunk),
+    {
+        self.runtime.block_on(self.inner.query_model_with(model, prompt, on_chunk))
+    }
+
+    pub fn chat(&self, model: &str, messages: &[ChatMessage]) -> Result<ChatMessage> {
+        self.runtime.block_on(self.inner.chat(model, messages))
+    }
+
+    /// See `OllamaClient::chat_with`; `on_chunk` runs on the calling thread.
+    pub fn chat_with<F>(&self, model: &str, messages: &[ChatMessage], on_chunk: F) -> Result<ChatMessage>
+    where
+        F: FnMut(&ChatChunk),
+    {
+        self.runtime.block_on(self.inner.chat_with(model, messages, on_chunk))
+    }
+
+    pub fn query_structured<T: DeserializeOwned>(&self, model: &str, prompt: &str, schema: &Value) -> Result<T> {
+        self.runtime.block_on(self.inner.query_structured(model, prompt, schema))
+    }
+}
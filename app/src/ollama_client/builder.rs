@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::ollama_client::{ModelProfiles, OllamaClient, SystemPrompts};
+
+/// address Ollama listens on out of the box
+pub const DEFAULT_HOST: &str = "http://127.0.0.1:11434";
+
+const DEFAULT_PORT: u16 = 11434;
+
+/// Normalise an `OLLAMA_HOST`-style address: the scheme defaults to http and
+/// the port to 11434, so `gpu-box`, `:8080` and `https://llm.internal` all work.
+pub fn parse_host(host: &str) -> String {
+    let host = host.trim().trim_end_matches('/');
+    let (scheme, rest) = match host.split_once("://") {
+        Some((scheme, rest)) => (scheme, rest),
+        None => ("http", host),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, ""),
+    };
+
+    let has_port = match authority.rfind(':') {
+        Some(colon) => !authority[colon..].contains(']'),
+        None => false,
+    };
+    let authority = match (authority, has_port) {
+        ("", _) => format!("127.0.0.1:{}", DEFAULT_PORT),
+        (authority, true) if authority.starts_with(':') => format!("127.0.0.1{}", authority),
+        (authority, true) => authority.to_string(),
+        (authority, false) if scheme == "https" => authority.to_string(),
+        (authority, false) => format!("{}:{}", authority, DEFAULT_PORT),
+    };
+    format!("{}://{}{}", scheme, authority, path)
+}
+
+/// Exponential backoff used while waiting for a daemon to come up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    /// delays stop doubling once they reach this
+    pub max_delay: Duration,
+    pub max_attempts: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(250),
+            max_delay: Duration::from_secs(4),
+            max_attempts: 12,
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay before each retry, `max_attempts - 1` in total.
+    pub fn delays(&self) -> impl Iterator<Item = Duration> + '_ {
+        let mut next = self.initial;
+        (1..self.max_attempts).map(move |_| {
+            let delay = next.min(self.max_delay);
+            next = next.saturating_mul(2);
+            delay
+        })
+    }
+}
+
+/// Connection settings read from a JSON config file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct OllamaConfig {
+    pub host: Option<String>,
+    /// sent as `Authorization: Bearer <token>`, e.g. for a reverse proxy
+    pub bearer_token: Option<String>,
+    pub headers: BTreeMap<String, String>,
+    pub connect_timeout_secs: Option<u64>,
+    /// longest wait between bytes of a response, so slow generations still stream
+    pub read_timeout_secs: Option<u64>,
+}
+
+impl OllamaConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::InvalidInput(format!("invalid Ollama config in {}: {}", path.display(), e)))
+    }
+}
+
+/// Builds an `OllamaClient`. The host comes from, in order: `base_url`, the
+/// `OLLAMA_HOST` environment variable, the config file, then `DEFAULT_HOST`.
+#[derive(Clone, Debug, Default)]
+pub struct OllamaClientBuilder {
+    base_url: Option<String>,
+    config: OllamaConfig,
+    headers: BTreeMap<String, String>,
+    bearer_token: Option<String>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    backoff: Backoff,
+    profiles: Option<ModelProfiles>,
+    prompts: Option<SystemPrompts>,
+    ignore_env: bool,
+}
+
+impl OllamaClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.to_string());
+        self
+    }
+
+    /// Fallback settings; anything set explicitly on the builder wins.
+    pub fn config(mut self, config: OllamaConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Don't read `OLLAMA_HOST`.
+    pub fn ignore_env(mut self) -> Self {
+        self.ignore_env = true;
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn bearer_token(mut self, token: &str) -> Self {
+        self.bearer_token = Some(token.to_string());
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Longest wait between bytes of a response. There is no total timeout, so
+    /// long generations keep streaming.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Retry policy for `OllamaClient::wait_for_daemon`.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn profiles(mut self, profiles: ModelProfiles) -> Self {
+        self.profiles = Some(profiles);
+        self
+    }
+
+    pub fn system_prompts(mut self, prompts: SystemPrompts) -> Self {
+        self.prompts = Some(prompts);
+        self
+    }
+
+    pub fn build(self) -> Result<OllamaClient> {
+        let env_host = if self.ignore_env { None } else { env::var("OLLAMA_HOST").ok().filter(|h| !h.is_empty()) };
+        let base_url = match self.base_url.or(env_host).or(self.config.host.clone()) {
+            Some(host) => parse_host(&host),
+            None => DEFAULT_HOST.to_string(),
+        };
+
+        let http = http_client(
+            self.config.headers.iter().chain(&self.headers),
+            self.bearer_token.as_ref().or(self.config.bearer_token.as_ref()),
+            self.connect_timeout.or(self.config.connect_timeout_secs.map(Duration::from_secs)),
+            self.read_timeout.or(self.config.read_timeout_secs.map(Duration::from_secs)),
+        )?;
+
+        Ok(OllamaClient::from_parts(
+            http,
+            &base_url,
+            self.backoff,
+            self.profiles.unwrap_or_else(ModelProfiles::builtin),
+            self.prompts.unwrap_or_default(),
+        ))
+    }
+}
+
+/// HTTP client sending `headers` and the bearer token with every request
+pub(crate) fn http_client<'a>(
+    headers: impl IntoIterator<Item = (&'a String, &'a String)>,
+    bearer_token: Option<&String>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+) -> Result<reqwest::Client> {
+    let mut header_map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| Error::InvalidInput(format!("invalid header name '{}': {}", name, e)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| Error::InvalidInput(format!("invalid value for header '{}': {}", name, e)))?;
+        header_map.insert(name, value);
+    }
+    if let Some(token) = bearer_token {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|e| Error::InvalidInput(format!("invalid bearer token: {}", e)))?;
+        value.set_sensitive(true);
+        header_map.insert(AUTHORIZATION, value);
+    }
+
+    let mut http = reqwest::Client::builder().default_headers(header_map);
+    if let Some(timeout) = connect_timeout {
+        http = http.connect_timeout(timeout);
+    }
+    if let Some(timeout) = read_timeout {
+        http = http.read_timeout(timeout);
+    }
+    Ok(http.build()?)
+}
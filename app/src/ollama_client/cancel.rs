@@ -0,0 +1,88 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use futures::StreamExt;
+use tokio::sync::Notify;
+
+use crate::error::{Error, Result};
+use crate::ollama_client::stream::ChunkStream;
+
+/// Stops a generation in progress. Clones share one flag, so a Ctrl-C handler
+/// can cancel a request made elsewhere; set it on `GenerateOptions::cancel`.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every request using this token. Cannot be undone.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            // registered before the check, so a concurrent `cancel` isn't missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Run `future`, giving up with `Error::Cancelled` if the token fires first.
+    pub async fn run<T>(&self, future: impl Future<Output = Result<T>>) -> Result<T> {
+        tokio::select! {
+            biased;
+            _ = self.cancelled() => Err(Error::Cancelled),
+            result = future => result,
+        }
+    }
+
+    /// End `stream` with `Error::Cancelled` once the token fires. The inner
+    /// stream, and with it the HTTP response, is dropped straight away so the
+    /// server stops generating.
+    pub fn guard<T: Send + 'static>(&self, stream: ChunkStream<T>) -> ChunkStream<T> {
+        Box::pin(futures::stream::unfold((Some(stream), self.clone()), |(stream, token)| async move {
+            let mut stream = stream?;
+            tokio::select! {
+                biased;
+                _ = token.cancelled() => Some((Err(Error::Cancelled), (None, token))),
+                item = stream.next() => item.map(|item| (item, (Some(stream), token))),
+            }
+        }))
+    }
+}
+
+impl PartialEq for CancellationToken {
+    /// tokens are equal when they are clones of one another
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+/// `stream` guarded by `cancel`, if there is one
+pub(crate) fn guarded<T: Send + 'static>(stream: ChunkStream<T>, cancel: Option<&CancellationToken>) -> ChunkStream<T> {
+    match cancel {
+        Some(token) => token.guard(stream),
+        None => stream,
+    }
+}
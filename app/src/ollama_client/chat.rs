@@ -0,0 +1,154 @@
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::ollama_client::stream::{ndjson_stream, ChunkStream, GenerationStats, RawStats};
+
+/// Who a chat message comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    /// output of a tool the assistant asked to run
+    Tool,
+}
+
+/// One entry of the `messages` array sent to `/api/chat`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+    /// name of the tool that produced a `Role::Tool` message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    /// `ToolCall::id` of the call a `Role::Tool` message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// tools the assistant wants run before it answers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl ChatMessage {
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        ChatMessage { role, content: content.into(), tool_name: None, tool_call_id: None, tool_calls: Vec::new() }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(Role::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(Role::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(Role::Assistant, content)
+    }
+
+    pub fn tool(tool_name: impl Into<String>, content: impl Into<String>) -> Self {
+        ChatMessage { tool_name: Some(tool_name.into()), ..Self::new(Role::Tool, content) }
+    }
+
+    /// The output of `call`, linked to it by id where the server gave one.
+    pub fn tool_result(call: &ToolCall, content: impl Into<String>) -> Self {
+        ChatMessage { tool_call_id: call.id.clone(), ..Self::tool(&call.function.name, content) }
+    }
+}
+
+/// A tool invocation requested by the model.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// set by OpenAI-compatible servers, which expect it back on the result
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub function: FunctionCall,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    /// arguments as a JSON object, shaped by the tool's parameter schema
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+/// A tool offered to the model in the `tools` array of `/api/chat`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(into = "Value")]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the arguments object
+    pub parameters: Value,
+}
+
+impl From<ToolSpec> for Value {
+    fn from(spec: ToolSpec) -> Self {
+        serde_json::json!({
+            "type": "function",
+            "function": {
+                "name": spec.name,
+                "description": spec.description,
+                "parameters": spec.parameters,
+            }
+        })
+    }
+}
+
+/// One piece of a streamed `/api/chat` response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatChunk {
+    /// the part of the assistant message generated since the previous chunk
+    pub message: ChatMessage,
+    pub done: bool,
+    pub done_reason: Option<String>,
+    pub stats: Option<GenerationStats>,
+}
+
+/// wire format of one chat line; durations are nanoseconds
+#[derive(Deserialize)]
+struct RawChatChunk {
+    message: Option<ChatMessage>,
+    #[serde(default)]
+    done: bool,
+    done_reason: Option<String>,
+    #[serde(flatten)]
+    stats: RawStats,
+}
+
+impl From<RawChatChunk> for ChatChunk {
+    fn from(raw: RawChatChunk) -> Self {
+        ChatChunk {
+            message: raw.message.unwrap_or_else(|| ChatMessage::assistant("")),
+            done: raw.done,
+            done_reason: raw.done_reason,
+            stats: raw.done.then(|| raw.stats.into_stats()),
+        }
+    }
+}
+
+/// Decode a streamed `/api/chat` body.
+pub(crate) fn chat_stream(response: reqwest::Response) -> ChunkStream<ChatChunk> {
+    Box::pin(ndjson_stream::<RawChatChunk>(response).map(|chunk| chunk.map(ChatChunk::from)))
+}
+
+/// Join a chat stream into one assistant message, tool calls included.
+pub(crate) async fn collect_reply<F>(mut stream: ChunkStream<ChatChunk>, mut on_chunk: F) -> Result<ChatMessage>
+where
+    F: FnMut(&ChatChunk),
+{
+    let mut reply = ChatMessage::assistant("");
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        on_chunk(&chunk);
+        reply.content.push_str(&chunk.message.content);
+        reply.tool_calls.extend(chunk.message.tool_calls);
+    }
+
+    Ok(reply)
+}
@@ -0,0 +1,560 @@
+use futures::StreamExt;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::error::{Error, Result};
+use crate::ollama_client::builder::{parse_host, Backoff, OllamaClientBuilder, DEFAULT_HOST};
+use crate::ollama_client::cancel::guarded;
+use crate::ollama_client::chat::{self, collect_reply, ChatChunk, ChatMessage, ToolSpec};
+use crate::ollama_client::daemon::{DaemonConfig, DaemonManager, DaemonMode};
+use crate::ollama_client::modelfile::Modelfile;
+use crate::ollama_client::models::{CreateModel, Embeddings, ModelInfo, ModelList, ModelSummary, PullProgress, RunningModel};
+use crate::ollama_client::options::{Format, GenerateOptions, ModelProfiles};
+use crate::ollama_client::prompts::SystemPrompts;
+use crate::ollama_client::structured::{self, STRUCTURED_ATTEMPTS};
+use crate::ollama_client::stream::{generation_stream, ndjson_stream, ChunkStream, GenerationChunk};
+
+pub struct OllamaClient {
+    client: Client,
+    base_url: String,
+    profiles: ModelProfiles,
+    prompts: SystemPrompts,
+    backoff: Backoff,
+    /// daemon started by `launch_daemon`, stopped on drop
+    daemon: Option<DaemonManager>,
+}
+
+impl OllamaClient {
+    /// Client for the daemon at `OLLAMA_HOST`, or the default local address.
+    pub fn new() -> Self {
+        match env::var("OLLAMA_HOST") {
+            Ok(host) if !host.is_empty() => Self::with_base_url(&parse_host(&host)),
+            _ => Self::with_base_url(DEFAULT_HOST),
+        }
+    }
+
+    /// Client for a daemon listening somewhere other than the default port.
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self::from_parts(Client::new(), base_url, Backoff::default(), ModelProfiles::builtin(), SystemPrompts::default())
+    }
+
+    /// Configure headers, timeouts and retries; see `OllamaClientBuilder`.
+    pub fn builder() -> OllamaClientBuilder {
+        OllamaClientBuilder::new()
+    }
+
+    pub(crate) fn from_parts(
+        client: Client,
+        base_url: &str,
+        backoff: Backoff,
+        profiles: ModelProfiles,
+        prompts: SystemPrompts,
+    ) -> Self {
+        OllamaClient {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            profiles,
+            prompts,
+            backoff,
+            daemon: None,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Use `profiles` for per-model default options instead of the built-in ones.
+    pub fn with_profiles(mut self, profiles: ModelProfiles) -> Self {
+        self.profiles = profiles;
+        self
+    }
+
+    pub fn profiles(&self) -> &ModelProfiles {
+        &self.profiles
+    }
+
+    /// Use `prompts` for models created by `select_model` instead of the default prompt.
+    pub fn with_system_prompts(mut self, prompts: SystemPrompts) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    pub fn system_prompts(&self) -> &SystemPrompts {
+        &self.prompts
+    }
+
+    pub async fn validate_daemon(&self) -> Result<bool> {
+        let response = self.client
+            .get(format!("{}/api/tags", self.base_url))
+            .send()
+            .await
+            .map_err(|e| self.connection_error(e))?;
+        if response.status().is_success() {
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Start a local daemon owned by this client, stopped again when the client
+    /// is dropped. Use `DaemonManager` directly for detached or shared daemons.
+    pub async fn launch_daemon(&mut self) -> Result<()> {
+        let mut daemon = DaemonManager::new(DaemonConfig::new(&self.base_url, DaemonMode::Owned));
+        daemon.ensure_running(self).await?;
+        self.daemon = Some(daemon);
+        Ok(())
+    }
+
+    /// Poll the daemon until it answers, backing off exponentially while the
+    /// connection is refused. Other errors are returned straight away.
+    pub async fn wait_for_daemon(&self) -> Result<()> {
+        let mut delays = self.backoff.delays();
+        loop {
+            let error = match self.validate_daemon().await {
+                Ok(true) => return Ok(()),
+                Ok(false) => Error::DaemonNotRunning { url: self.base_url.clone(), source: None },
+                Err(e @ Error::DaemonNotRunning { .. }) => e,
+                Err(e) => return Err(e),
+            };
+            match delays.next() {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Err(error),
+            }
+        }
+    }
+
+    pub async fn select_model(&self) -> Result<String> {
+        println!("================");
+        let models = self.list_available_models().await?;
+        
+        if models.is_empty() {
+            println!("No LLMs on this machine.");
+            println!("Would you like to pull a model? (Y/N)");
+            
+            let input = read_line().await?.to_lowercase();
+            
+            if input == "y" || input == "yes" {
+                return self.prompt_and_pull_model().await;
+            } else {
+                return Err(Error::InvalidInput("No LLMs available and user declined to pull a model".to_string()));
+            }
+        }
+        
+        println!("LLMs on this machine:");
+        for (i, model) in models.iter().enumerate() {
+            println!("{}. {}", i + 1, model);
+        }
+        println!("{}. Pull a new model", models.len() + 1);
+        
+        loop {
+            print!("Select a LLM (1-{}): ", models.len() + 1);
+            io::stdout().flush()?;
+            
+            let input = read_line().await?;
+            
+            if let Ok(choice) = input.parse::<usize>() {
+                if choice >= 1 && choice <= models.len() {
+                    return Ok(models[choice - 1].clone());
+                } else if choice == models.len() + 1 {
+                    return self.prompt_and_pull_model().await;
+                }
+            }
+            
+            println!("Invalid selection. Please try again.");
+        }
+    }
+
+    /// Stream a completion for `prompt` chunk by chunk. `options` override the
+    /// model's profile.
+    pub async fn generate_stream(
+        &self,
+        model: &str,
+        prompt: &str,
+        options: &GenerateOptions,
+    ) -> Result<ChunkStream<GenerationChunk>> {
+        let request_body = serde_json::json!({
+            "model": model,
+            "prompt": prompt,
+            "stream": true
+        });
+
+        let response = self.post_streaming("/api/generate", model, request_body, options).await?;
+        Ok(guarded(generation_stream(response), options.cancel.as_ref()))
+    }
+
+    /// Generate a completion, passing every chunk to `on_chunk` as it arrives,
+    /// and return the full text.
+    pub async fn query_model_with<F>(&self, model: &str, prompt: &str, mut on_chunk: F) -> Result<String>
+    where
+        F: FnMut(&GenerationChunk),
+    {
+        let mut stream = self.generate_stream(model, prompt, &GenerateOptions::default()).await?;
+        let mut full_response = String::new();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            on_chunk(&chunk);
+            full_response.push_str(&chunk.text);
+        }
+
+        if full_response.is_empty() {
+            full_response = "No response received".to_string();
+        }
+
+        Ok(full_response)
+    }
+
+    /// Generate a completion and return the full text.
+    pub async fn query_model(&self, model: &str, prompt: &str) -> Result<String> {
+        self.query_model_with(model, prompt, |_| {}).await
+    }
+
+    /// Stream the assistant's reply to `messages` chunk by chunk. `options`
+    /// override the model's profile.
+    pub async fn chat_stream(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        options: &GenerateOptions,
+    ) -> Result<ChunkStream<ChatChunk>> {
+        self.chat_request(model, messages, &[], options).await
+    }
+
+    /// Continue the conversation with `tools` on offer. The reply either
+    /// answers or carries `tool_calls` for the caller to run, see `agent::Agent`.
+    pub async fn chat_with_tools(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+        options: &GenerateOptions,
+    ) -> Result<ChatMessage> {
+        let stream = self.chat_request(model, messages, tools, options).await?;
+        collect_reply(stream, |_| {}).await
+    }
+
+    /// Continue the conversation in `messages`, passing every chunk to
+    /// `on_chunk` as it arrives, and return the full assistant message.
+    pub async fn chat_with<F>(&self, model: &str, messages: &[ChatMessage], on_chunk: F) -> Result<ChatMessage>
+    where
+        F: FnMut(&ChatChunk),
+    {
+        self.collect_chat(model, messages, &GenerateOptions::default(), on_chunk).await
+    }
+
+    /// Continue the conversation in `messages` and return the assistant's reply.
+    pub async fn chat(&self, model: &str, messages: &[ChatMessage]) -> Result<ChatMessage> {
+        self.chat_with(model, messages, |_| {}).await
+    }
+
+    /// Embed each of `inputs` with `model`, returning one vector per input in order.
+    pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let request_body = serde_json::json!({ "model": model, "input": inputs });
+        let response = self.send_json(reqwest::Method::POST, "/api/embed", &request_body, Some(model)).await?;
+        Ok(response.json::<Embeddings>().await?.embeddings)
+    }
+
+    /// Ask for a reply matching the JSON `schema` and deserialise it into `T`.
+    /// Replies that fail to parse or validate are sent back with the error,
+    /// up to `STRUCTURED_ATTEMPTS` times in total.
+    pub async fn query_structured<T: DeserializeOwned>(&self, model: &str, prompt: &str, schema: &Value) -> Result<T> {
+        let options = GenerateOptions { format: Some(Format::Schema(schema.clone())), ..Default::default() };
+        let mut messages = vec![ChatMessage::user(prompt)];
+        let mut last_error = String::new();
+
+        for _ in 0..STRUCTURED_ATTEMPTS {
+            let reply = self.collect_chat(model, &messages, &options, |_| {}).await?;
+            match structured::parse(&reply.content, schema) {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    messages.push(reply);
+                    messages.push(ChatMessage::user(structured::retry_prompt(&e)));
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(Error::InvalidResponse { attempts: STRUCTURED_ATTEMPTS, message: last_error })
+    }
+
+    /// Models installed on the daemon.
+    pub async fn list_models(&self) -> Result<Vec<ModelSummary>> {
+        Ok(self.get_json::<ModelList<ModelSummary>>("/api/tags").await?.models)
+    }
+
+    /// Models currently loaded in memory.
+    pub async fn running_models(&self) -> Result<Vec<RunningModel>> {
+        Ok(self.get_json::<ModelList<RunningModel>>("/api/ps").await?.models)
+    }
+
+    /// Stream download progress while the daemon pulls `model` from a registry.
+    pub async fn pull_stream(&self, model: &str) -> Result<ChunkStream<PullProgress>> {
+        let request_body = serde_json::json!({ "model": model, "stream": true });
+        let response = self.send_json(reqwest::Method::POST, "/api/pull", &request_body, Some(model)).await?;
+        Ok(ndjson_stream(response))
+    }
+
+    /// Pull `model`, passing every progress update to `on_progress`.
+    pub async fn pull_with<F>(&self, model: &str, mut on_progress: F) -> Result<()>
+    where
+        F: FnMut(&PullProgress),
+    {
+        let mut stream = self.pull_stream(model).await?;
+        while let Some(progress) = stream.next().await {
+            on_progress(&progress?);
+        }
+        Ok(())
+    }
+
+    pub async fn pull(&self, model: &str) -> Result<()> {
+        self.pull_with(model, |_| {}).await
+    }
+
+    /// Create a model on the daemon, e.g. a base model with our system prompt.
+    pub async fn create_model(&self, request: &CreateModel) -> Result<()> {
+        let mut request_body = serde_json::to_value(request)?;
+        request_body["stream"] = Value::Bool(false);
+        self.send_json(reqwest::Method::POST, "/api/create", &request_body, request.from.as_deref())
+            .await
+            .map_err(|e| match e {
+                Error::Api { message, .. } => Error::ModelCreate(message),
+                other => other,
+            })?;
+        Ok(())
+    }
+
+    /// Create `model` from a Modelfile, uploading any adapter files as blobs first.
+    pub async fn create_from_modelfile(&self, model: &str, modelfile: &Modelfile) -> Result<()> {
+        let mut request = modelfile.to_create_model(model);
+        for adapter in &modelfile.adapters {
+            let path = Path::new(adapter);
+            let digest = self.upload_blob(path).await?;
+            let name = path.file_name().map_or(adapter.clone(), |name| name.to_string_lossy().into_owned());
+            request.adapters.insert(name, digest);
+        }
+        self.create_model(&request).await
+    }
+
+    pub async fn show_model(&self, model: &str) -> Result<ModelInfo> {
+        let request_body = serde_json::json!({ "model": model });
+        let response = self.send_json(reqwest::Method::POST, "/api/show", &request_body, Some(model)).await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn delete_model(&self, model: &str) -> Result<()> {
+        let request_body = serde_json::json!({ "model": model });
+        self.send_json(reqwest::Method::DELETE, "/api/delete", &request_body, Some(model)).await?;
+        Ok(())
+    }
+
+    /// Copy `source` to a new name, e.g. to tag a customised model.
+    pub async fn copy_model(&self, source: &str, destination: &str) -> Result<()> {
+        let request_body = serde_json::json!({ "source": source, "destination": destination });
+        self.send_json(reqwest::Method::POST, "/api/copy", &request_body, Some(source)).await?;
+        Ok(())
+    }
+
+    // Private:
+
+    async fn collect_chat<F>(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        options: &GenerateOptions,
+        on_chunk: F,
+    ) -> Result<ChatMessage>
+    where
+        F: FnMut(&ChatChunk),
+    {
+        let stream = self.chat_stream(model, messages, options).await?;
+        collect_reply(stream, on_chunk).await
+    }
+
+    async fn chat_request(
+        &self,
+        model: &str,
+        messages: &[ChatMessage],
+        tools: &[ToolSpec],
+        options: &GenerateOptions,
+    ) -> Result<ChunkStream<ChatChunk>> {
+        let mut request_body = serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "stream": true
+        });
+        if !tools.is_empty() {
+            request_body["tools"] = serde_json::to_value(tools)?;
+        }
+
+        let response = self.post_streaming("/api/chat", model, request_body, options).await?;
+        Ok(guarded(chat::chat_stream(response), options.cancel.as_ref()))
+    }
+
+    /// send a streaming request with the model's profile and `options` merged in
+    async fn post_streaming(
+        &self,
+        path: &str,
+        model: &str,
+        mut request_body: Value,
+        options: &GenerateOptions,
+    ) -> Result<reqwest::Response> {
+        let options = options.or(&self.profiles.for_model(model));
+        if let Some(body) = request_body.as_object_mut() {
+            body.extend(options.request_fields());
+        }
+
+        let request = self.send_json(reqwest::Method::POST, path, &request_body, Some(model));
+        match &options.cancel {
+            Some(token) => token.run(request).await,
+            None => request.await,
+        }
+    }
+
+    /// send a JSON request; a 404 means `model` (when given) is unknown
+    async fn send_json(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: &Value,
+        model: Option<&str>,
+    ) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path))
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| self.connection_error(e))?;
+
+        if let (reqwest::StatusCode::NOT_FOUND, Some(model)) = (response.status(), model) {
+            return Err(Error::ModelNotFound(model.to_string()));
+        }
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        Ok(response)
+    }
+
+    /// push a file to the daemon's blob store unless it is already there; returns its digest
+    async fn upload_blob(&self, path: &Path) -> Result<String> {
+        let bytes = tokio::fs::read(path).await?;
+        let digest = format!("sha256:{:x}", Sha256::digest(&bytes));
+        let url = format!("{}/api/blobs/{}", self.base_url, digest);
+
+        let exists = self.client.head(&url).send().await.map_err(|e| self.connection_error(e))?;
+        if exists.status().is_success() {
+            return Ok(digest);
+        }
+
+        let response = self.client.post(&url).body(bytes).send().await.map_err(|e| self.connection_error(e))?;
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+        Ok(digest)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let response = self.client
+            .get(format!("{}{}", self.base_url, path))
+            .send()
+            .await
+            .map_err(|e| self.connection_error(e))?;
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn list_available_models(&self) -> Result<Vec<String>> {
+        Ok(self.list_models().await?.into_iter().map(|model| model.name).collect())
+    }
+
+    async fn prompt_and_pull_model(&self) -> Result<String> {
+        println!("Enter the path of desired model");
+        println!("(Examples: codellama, hf.co/TheBloke/CodeLlama-34B-GGUF:Q4_K_M, etc)");
+        print!("Model path: ");
+        io::stdout().flush()?;
+        
+        let input = read_line().await?;
+        let base_model_name = input.as_str();
+        
+        if base_model_name.is_empty() {
+            return Err(Error::InvalidInput("No model path provided".to_string()));
+        }
+        
+        self.pull_model(base_model_name).await
+    }
+
+    async fn pull_model(&self, base_model: &str) -> Result<String> {
+        println!("Manifesting '{}'", base_model);
+
+        let pb = ProgressBar::new(0);
+        pb.set_style(ProgressStyle::default_bar()
+            .template("{msg} [{bar:30}] {bytes}/{total_bytes}")
+            .expect("template valid"));
+        self.pull_with(base_model, |progress| {
+            if let Some(total) = progress.total {
+                pb.set_length(total);
+                pb.set_position(progress.completed.unwrap_or(0));
+            }
+            pb.set_message(progress.status.clone());
+        }).await?;
+
+        pb.set_message("Creating model...");
+        let model_name = base_model.to_string();
+        // no TEMPLATE: the model keeps the chat template it was trained with
+        let modelfile = Modelfile::from(base_model).system(self.prompts.for_model(base_model));
+        self.create_from_modelfile(&model_name, &modelfile).await?;
+        pb.finish_and_clear();
+
+        println!("'{}' created successfully.", model_name);
+        Ok(model_name)
+    }
+
+    /// refused connections mean the daemon isn't up; anything else is a plain HTTP error
+    fn connection_error(&self, error: reqwest::Error) -> Error {
+        if error.is_connect() {
+            Error::DaemonNotRunning { url: self.base_url.clone(), source: Some(error) }
+        } else {
+            Error::Http(error)
+        }
+    }
+
+}
+
+/// read one trimmed line from stdin without stalling the runtime
+async fn read_line() -> Result<String> {
+    let line = tokio::task::spawn_blocking(|| {
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).map(|_| input)
+    })
+    .await
+    .map_err(|e| Error::Io(io::Error::other(e)))??;
+    Ok(line.trim().to_string())
+}
+
+/// turn a non-success response into `Error::Api`, keeping Ollama's `error` message if present
+async fn api_error(response: reqwest::Response) -> Error {
+    let status = response.status().as_u16();
+    let message = response
+        .json::<Value>()
+        .await
+        .ok()
+        .and_then(|body| body.get("error").and_then(|e| e.as_str()).map(str::to_string))
+        .unwrap_or_default();
+    Error::Api { status, message }
+}
+
+impl Default for OllamaClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
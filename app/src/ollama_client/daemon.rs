@@ -0,0 +1,400 @@
+//! Starting and stopping a local `ollama serve` without disturbing a daemon
+//! the user already runs.
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::ollama_client::OllamaClient;
+
+/// What happens to a daemon `DaemonManager` started once the manager is dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DaemonMode {
+    /// stop it
+    Owned,
+    /// leave it running
+    Detached,
+    /// stop it once no llama_pack process recorded in the lockfile still uses it
+    Shared,
+}
+
+/// How `DaemonManager::ensure_running` found the daemon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DaemonStatus {
+    /// something else runs it; it is never stopped
+    AlreadyRunning,
+    /// another llama_pack process started it in shared mode
+    Joined,
+    Started,
+}
+
+#[derive(Clone, Debug)]
+pub struct DaemonConfig {
+    /// the `ollama` executable
+    pub binary: PathBuf,
+    /// address the daemon should listen on, passed to it as `OLLAMA_HOST`
+    pub base_url: String,
+    pub mode: DaemonMode,
+    /// the daemon's stdout and stderr are appended here
+    pub log_file: PathBuf,
+    /// records which processes share the daemon, in `DaemonMode::Shared`
+    pub lock_file: PathBuf,
+    /// how long a freshly started daemon gets to answer
+    pub ready_timeout: Duration,
+}
+
+impl DaemonConfig {
+    /// Defaults keeping the log and lockfile in the user's cache directory.
+    pub fn new(base_url: &str, mode: DaemonMode) -> Self {
+        let state_dir = dirs::cache_dir().unwrap_or_else(env::temp_dir).join("llama_pack");
+        let port = reqwest::Url::parse(base_url).ok().and_then(|url| url.port_or_known_default()).unwrap_or(11434);
+        Self {
+            binary: PathBuf::from("ollama"),
+            base_url: base_url.to_string(),
+            mode,
+            log_file: state_dir.join("ollama.log"),
+            lock_file: state_dir.join(format!("ollama-{}.lock", port)),
+            ready_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// lockfile contents: the shared daemon and the processes using it
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DaemonLock {
+    daemon_pid: u32,
+    clients: Vec<u32>,
+}
+
+/// how long `DaemonManager::shutdown` waits for another process holding the lockfile
+const LEAVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Starts `ollama serve` when no daemon answers, and stops it again according
+/// to its `DaemonMode`. A daemon it did not start is never stopped.
+///
+/// Call `shutdown` when done; dropping the manager only stops the daemon on a
+/// best-effort basis, since it can't wait for a shared lockfile.
+pub struct DaemonManager {
+    config: DaemonConfig,
+    child: Option<Child>,
+    /// whether this process is recorded in the shared lockfile
+    joined: bool,
+}
+
+impl DaemonManager {
+    pub fn new(config: DaemonConfig) -> Self {
+        Self { config, child: None, joined: false }
+    }
+
+    pub fn config(&self) -> &DaemonConfig {
+        &self.config
+    }
+
+    /// Make sure a daemon answers at `client`'s address, starting one if needed.
+    pub async fn ensure_running(&mut self, client: &OllamaClient) -> Result<DaemonStatus> {
+        // hold the guard while checking and starting so concurrent processes start one daemon
+        let _guard = match self.config.mode {
+            DaemonMode::Shared => Some(LockGuard::acquire(&self.config.lock_file, self.config.ready_timeout).await?),
+            _ => None,
+        };
+
+        match client.validate_daemon().await {
+            Ok(true) => {
+                if self.config.mode == DaemonMode::Shared && self.join_shared()? {
+                    return Ok(DaemonStatus::Joined);
+                }
+                return Ok(DaemonStatus::AlreadyRunning);
+            }
+            // something answers, but not like Ollama
+            Ok(false) => return Err(Error::PortConflict(client.base_url().to_string())),
+            Err(Error::DaemonNotRunning { .. }) => {}
+            Err(e) => return Err(e),
+        }
+
+        let url = reqwest::Url::parse(client.base_url()).map_err(|e| Error::InvalidInput(e.to_string()))?;
+        if !is_local(&url) {
+            return Err(Error::DaemonNotRunning { url: client.base_url().to_string(), source: None });
+        }
+
+        self.child = Some(self.spawn(&url)?);
+        if let Err(e) = self.wait_ready(client).await {
+            self.kill_child();
+            return Err(e);
+        }
+
+        if self.config.mode == DaemonMode::Shared {
+            let daemon_pid = self.child.as_ref().map_or(0, Child::id);
+            write_lock(&self.config.lock_file, &DaemonLock { daemon_pid, clients: vec![process::id()] })?;
+            self.joined = true;
+        }
+        Ok(DaemonStatus::Started)
+    }
+
+    /// Stop the daemon if this manager is responsible for it; see `DaemonMode`.
+    /// In shared mode this waits, without blocking the runtime, for any other
+    /// process holding the lockfile.
+    pub async fn shutdown(&mut self) -> Result<()> {
+        if self.config.mode == DaemonMode::Shared && self.joined {
+            let guard = LockGuard::acquire(&self.config.lock_file, LEAVE_TIMEOUT).await?;
+            return self.leave_shared(guard);
+        }
+        self.stop()
+    }
+
+    /// Like `shutdown`, but never waits: in shared mode it fails if another
+    /// process holds the lockfile right now.
+    pub fn stop(&mut self) -> Result<()> {
+        match self.config.mode {
+            DaemonMode::Owned => self.kill_child(),
+            DaemonMode::Detached => self.child = None,
+            DaemonMode::Shared if self.joined => {
+                let guard = LockGuard::try_acquire(&self.config.lock_file)?
+                    .ok_or_else(|| LockGuard::timed_out(&self.config.lock_file))?;
+                self.leave_shared(guard)?;
+            }
+            DaemonMode::Shared => {}
+        }
+        Ok(())
+    }
+
+    fn spawn(&self, url: &reqwest::Url) -> Result<Child> {
+        if let Some(dir) = self.config.log_file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut log = OpenOptions::new().create(true).append(true).open(&self.config.log_file)?;
+        writeln!(log, "--- llama_pack started ollama serve at {} ---", chrono::Utc::now().to_rfc3339())?;
+
+        let host = format!("{}:{}", url.host_str().unwrap_or("127.0.0.1"), url.port_or_known_default().unwrap_or(11434));
+        let mut command = Command::new(&self.config.binary);
+        command
+            .arg("serve")
+            .env("OLLAMA_HOST", host)
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log);
+        // keep Ctrl-C in the terminal from reaching a daemon that should outlive us
+        #[cfg(unix)]
+        if self.config.mode != DaemonMode::Owned {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+
+        command.spawn().map_err(|e| {
+            Error::DaemonStart(format!(
+                "cannot run {}: {}. Make sure 'ollama' is installed and in PATH.",
+                self.config.binary.display(),
+                e
+            ))
+        })
+    }
+
+    /// poll with the client's `Backoff` until the daemon answers, giving up
+    /// after `ready_timeout` or as soon as the child exits
+    async fn wait_ready(&mut self, client: &OllamaClient) -> Result<()> {
+        let Some(child) = self.child.as_mut() else {
+            return Ok(());
+        };
+
+        let ready = tokio::select! {
+            status = wait_exit(child) => Err(status?),
+            ready = tokio::time::timeout(self.config.ready_timeout, client.wait_for_daemon()) => Ok(ready),
+        };
+        let ready = match ready {
+            Ok(ready) => ready,
+            Err(status) => {
+                self.child = None;
+                return Err(Error::DaemonStart(format!(
+                    "ollama exited with {} before it was ready:\n{}",
+                    status,
+                    log_tail(&self.config.log_file, 5)
+                )));
+            }
+        };
+
+        match ready {
+            Ok(Err(e)) if !matches!(e, Error::DaemonNotRunning { .. }) => Err(e),
+            Ok(Ok(())) => Ok(()),
+            _ => Err(Error::DaemonStart(format!(
+                "ollama did not become ready within {:?}; see {}",
+                self.config.ready_timeout,
+                self.config.log_file.display()
+            ))),
+        }
+    }
+
+    /// record this process as a user of a daemon another llama_pack process started
+    fn join_shared(&mut self) -> Result<bool> {
+        let Some(mut lock) = read_lock(&self.config.lock_file)? else {
+            return Ok(false);
+        };
+        if !process_alive(lock.daemon_pid) {
+            fs::remove_file(&self.config.lock_file)?;
+            return Ok(false);
+        }
+
+        lock.clients.retain(|pid| process_alive(*pid));
+        lock.clients.push(process::id());
+        write_lock(&self.config.lock_file, &lock)?;
+        self.joined = true;
+        Ok(true)
+    }
+
+    /// drop this process from the lockfile, stopping the daemon if it was the last user;
+    /// `_guard` must be held for the lockfile
+    fn leave_shared(&mut self, _guard: LockGuard) -> Result<()> {
+        self.joined = false;
+        let Some(mut lock) = read_lock(&self.config.lock_file)? else {
+            return Ok(());
+        };
+
+        let me = process::id();
+        lock.clients.retain(|pid| *pid != me && process_alive(*pid));
+        if !lock.clients.is_empty() {
+            // someone still uses the daemon; let it outlive our handle
+            self.child = None;
+            return write_lock(&self.config.lock_file, &lock);
+        }
+
+        match self.child.take() {
+            Some(mut child) if child.id() == lock.daemon_pid => {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            _ => kill_process(lock.daemon_pid),
+        }
+        fs::remove_file(&self.config.lock_file)?;
+        Ok(())
+    }
+
+    fn kill_child(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl Drop for DaemonManager {
+    fn drop(&mut self) {
+        // best effort; `shutdown` reports failures
+        let _ = self.stop();
+    }
+}
+
+/// resolves once the child has exited
+async fn wait_exit(child: &mut Child) -> io::Result<ExitStatus> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+fn is_local(url: &reqwest::Url) -> bool {
+    matches!(url.host_str(), Some("127.0.0.1" | "localhost" | "[::1]" | "::1" | "0.0.0.0"))
+}
+
+fn read_lock(path: &Path) -> Result<Option<DaemonLock>> {
+    match fs::read_to_string(path) {
+        Ok(json) => Ok(serde_json::from_str(&json).ok()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_lock(path: &Path, lock: &DaemonLock) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string(lock)?)?;
+    Ok(())
+}
+
+/// last `lines` lines of the daemon log, for error messages
+fn log_tail(path: &Path, lines: usize) -> String {
+    let log = fs::read_to_string(path).unwrap_or_default();
+    let tail: Vec<&str> = log.lines().rev().take(lines).collect();
+    tail.into_iter().rev().collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+fn kill_process(pid: u32) {
+    #[cfg(unix)]
+    let _ = Command::new("kill").arg(pid.to_string()).stderr(Stdio::null()).status();
+    #[cfg(windows)]
+    let _ = Command::new("taskkill").args(["/PID", &pid.to_string(), "/F"]).status();
+}
+
+/// Cross-process mutex around the lockfile: a `.guard` file created exclusively.
+struct LockGuard {
+    path: PathBuf,
+}
+
+impl LockGuard {
+    /// guards left by a crashed process are ignored after this long
+    const STALE_AFTER: Duration = Duration::from_secs(120);
+
+    async fn acquire(lock_file: &Path, timeout: Duration) -> Result<Self> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(guard) = Self::try_acquire(lock_file)? {
+                return Ok(guard);
+            }
+            if Instant::now() >= deadline {
+                return Err(Self::timed_out(lock_file));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    fn try_acquire(lock_file: &Path) -> Result<Option<Self>> {
+        let path = lock_file.with_extension("lock.guard");
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => Ok(Some(Self { path })),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let stale = fs::metadata(&path)
+                    .and_then(|meta| meta.modified())
+                    .is_ok_and(|modified| modified.elapsed().unwrap_or_default() > Self::STALE_AFTER);
+                if stale {
+                    let _ = fs::remove_file(&path);
+                }
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn timed_out(lock_file: &Path) -> Error {
+        Error::DaemonStart(format!("timed out waiting for another process holding {}", lock_file.display()))
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,23 @@
+mod client;
+pub mod blocking;
+pub mod builder;
+pub mod cancel;
+pub mod chat;
+pub mod daemon;
+pub mod modelfile;
+pub mod models;
+pub mod options;
+pub mod prompts;
+pub mod stream;
+pub mod structured;
+
+pub use builder::{Backoff, OllamaClientBuilder, OllamaConfig};
+pub use cancel::CancellationToken;
+pub use chat::{ChatChunk, ChatMessage, FunctionCall, Role, ToolCall, ToolSpec};
+pub use client::OllamaClient;
+pub use daemon::{DaemonConfig, DaemonManager, DaemonMode, DaemonStatus};
+pub use modelfile::Modelfile;
+pub use models::{CreateModel, ModelDetails, ModelInfo, ModelSummary, PullProgress, RunningModel};
+pub use options::{Format, GenerateOptions, KeepAlive, ModelProfiles};
+pub use prompts::SystemPrompts;
+pub use stream::{ChunkStream, GenerationChunk, GenerationStats};
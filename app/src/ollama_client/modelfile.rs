@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::ollama_client::{ChatMessage, CreateModel, Role};
+
+/// A typed Ollama Modelfile, built in code or parsed from text.
+///
+/// `render` writes it back in Modelfile syntax, so parse and render round-trip.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Modelfile {
+    /// base model name or path to weights
+    pub from: String,
+    pub system: Option<String>,
+    pub template: Option<String>,
+    /// `PARAMETER` lines in order; `stop` may repeat
+    pub parameters: Vec<(String, String)>,
+    /// paths to LoRA adapters
+    pub adapters: Vec<String>,
+    pub license: Vec<String>,
+    /// example conversation the model starts from
+    pub messages: Vec<ChatMessage>,
+}
+
+impl Modelfile {
+    pub fn from(base: &str) -> Self {
+        Modelfile { from: base.to_string(), ..Default::default() }
+    }
+
+    pub fn system(mut self, system: &str) -> Self {
+        self.system = Some(system.to_string());
+        self
+    }
+
+    pub fn template(mut self, template: &str) -> Self {
+        self.template = Some(template.to_string());
+        self
+    }
+
+    pub fn parameter(mut self, name: &str, value: impl ToString) -> Self {
+        self.parameters.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn adapter(mut self, path: &str) -> Self {
+        self.adapters.push(path.to_string());
+        self
+    }
+
+    pub fn license(mut self, license: &str) -> Self {
+        self.license.push(license.to_string());
+        self
+    }
+
+    pub fn message(mut self, role: Role, content: &str) -> Self {
+        self.messages.push(ChatMessage::new(role, content));
+        self
+    }
+
+    /// Parse Modelfile text. Instructions are case-insensitive; values may be
+    /// bare, `"quoted"` or `"""triple quoted"""` across lines.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut modelfile = Modelfile::default();
+        let mut rest = text;
+
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            if rest.starts_with('#') {
+                rest = rest.split_once('\n').map_or("", |(_, after)| after);
+                continue;
+            }
+
+            let (instruction, after) = take_word(rest);
+            rest = after;
+            match instruction.to_ascii_uppercase().as_str() {
+                "FROM" => (modelfile.from, rest) = take_value(rest)?,
+                "SYSTEM" => {
+                    let (system, after) = take_value(rest)?;
+                    (modelfile.system, rest) = (Some(system), after);
+                }
+                "TEMPLATE" => {
+                    let (template, after) = take_value(rest)?;
+                    (modelfile.template, rest) = (Some(template), after);
+                }
+                "PARAMETER" => {
+                    let (name, after) = take_word(rest);
+                    if name.is_empty() {
+                        return Err(modelfile_error("PARAMETER needs a name and a value"));
+                    }
+                    let (value, after) = take_value(after)?;
+                    modelfile.parameters.push((name.to_string(), value));
+                    rest = after;
+                }
+                "ADAPTER" => {
+                    let (adapter, after) = take_value(rest)?;
+                    modelfile.adapters.push(adapter);
+                    rest = after;
+                }
+                "LICENSE" => {
+                    let (license, after) = take_value(rest)?;
+                    modelfile.license.push(license);
+                    rest = after;
+                }
+                "MESSAGE" => {
+                    let (role, after) = take_word(rest);
+                    let role = match role.to_ascii_lowercase().as_str() {
+                        "system" => Role::System,
+                        "user" => Role::User,
+                        "assistant" => Role::Assistant,
+                        other => return Err(modelfile_error(&format!("unknown MESSAGE role '{}'", other))),
+                    };
+                    let (content, after) = take_value(after)?;
+                    modelfile.messages.push(ChatMessage::new(role, content));
+                    rest = after;
+                }
+                other => return Err(modelfile_error(&format!("unknown instruction '{}'", other))),
+            }
+        }
+
+        if modelfile.from.is_empty() {
+            return Err(modelfile_error("missing FROM"));
+        }
+        Ok(modelfile)
+    }
+
+    /// `/api/create` request for a model called `name`. Adapters are left out:
+    /// their files must be uploaded first, see `OllamaClient::create_from_modelfile`.
+    pub fn to_create_model(&self, name: &str) -> CreateModel {
+        let mut parameters = BTreeMap::new();
+        for (key, value) in &self.parameters {
+            let value = parameter_value(value);
+            if key == "stop" {
+                let stops = parameters.entry(key.clone()).or_insert_with(|| Value::Array(Vec::new()));
+                if let Value::Array(stops) = stops {
+                    stops.push(value);
+                }
+            } else {
+                parameters.insert(key.clone(), value);
+            }
+        }
+
+        CreateModel {
+            model: name.to_string(),
+            from: Some(self.from.clone()),
+            system: self.system.clone(),
+            template: self.template.clone(),
+            parameters,
+            license: self.license.clone(),
+            messages: self.messages.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Modelfile text for `parse`. Fails if a multi-line value contains `"""`
+    /// or ends with `"`, since triple-quoted values can't escape anything.
+    pub fn render(&self) -> Result<String> {
+        let mut lines = vec![format!("FROM {}", quote(&self.from)?)];
+        for adapter in &self.adapters {
+            lines.push(format!("ADAPTER {}", quote(adapter)?));
+        }
+        for (name, value) in &self.parameters {
+            lines.push(format!("PARAMETER {} {}", name, quote(value)?));
+        }
+        if let Some(template) = &self.template {
+            lines.push(format!("TEMPLATE {}", quote(template)?));
+        }
+        if let Some(system) = &self.system {
+            lines.push(format!("SYSTEM {}", quote(system)?));
+        }
+        for license in &self.license {
+            lines.push(format!("LICENSE {}", quote(license)?));
+        }
+        for message in &self.messages {
+            let role = match message.role {
+                Role::System => "system",
+                Role::User => "user",
+                Role::Assistant | Role::Tool => "assistant",
+            };
+            lines.push(format!("MESSAGE {} {}", role, quote(&message.content)?));
+        }
+        Ok(lines.into_iter().map(|line| line + "\n").collect())
+    }
+}
+
+impl FromStr for Modelfile {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse(text)
+    }
+}
+
+fn modelfile_error(message: &str) -> Error {
+    Error::InvalidInput(format!("invalid Modelfile: {}", message))
+}
+
+/// next whitespace-separated word on the current line
+fn take_word(input: &str) -> (&str, &str) {
+    let input = input.trim_start_matches([' ', '\t']);
+    let end = input.find(char::is_whitespace).unwrap_or(input.len());
+    (&input[..end], &input[end..])
+}
+
+/// the value up to the end of the line, or a quoted value which may span lines
+fn take_value(input: &str) -> Result<(String, &str)> {
+    let input = input.trim_start_matches([' ', '\t']);
+
+    if let Some(body) = input.strip_prefix("\"\"\"") {
+        let end = body.find("\"\"\"").ok_or_else(|| modelfile_error("unterminated \"\"\""))?;
+        return Ok((body[..end].to_string(), &body[end + 3..]));
+    }
+
+    let (line, rest) = input.split_once('\n').unwrap_or((input, ""));
+    let line = line.trim();
+    let value = match line.strip_prefix('"').and_then(|l| l.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\\\"", "\""),
+        None => line.to_string(),
+    };
+    Ok((value, rest))
+}
+
+fn quote(value: &str) -> Result<String> {
+    if value.contains('\n') {
+        // the first `"""` closes the value, so it can't appear inside or end it
+        if value.contains("\"\"\"") || value.ends_with('"') {
+            return Err(modelfile_error(&format!("cannot write multi-line value {:?}", value)));
+        }
+        Ok(format!("\"\"\"{}\"\"\"", value))
+    } else if value.is_empty() || value.contains(char::is_whitespace) || value.contains('"') {
+        Ok(format!("\"{}\"", value.replace('"', "\\\"")))
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// numbers and booleans as JSON values, anything else as a string
+fn parameter_value(value: &str) -> Value {
+    serde_json::from_str::<Value>(value)
+        .ok()
+        .filter(|v| v.is_number() || v.is_boolean())
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
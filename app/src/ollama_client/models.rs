@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::ollama_client::ChatMessage;
+
+/// One status line streamed by `/api/pull`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct PullProgress {
+    /// e.g. "pulling manifest", "pulling <digest>", "success"
+    pub status: String,
+    /// layer being downloaded, if any
+    pub digest: Option<String>,
+    /// layer size in bytes
+    pub total: Option<u64>,
+    /// bytes of the layer downloaded so far
+    pub completed: Option<u64>,
+}
+
+impl PullProgress {
+    pub fn is_success(&self) -> bool {
+        self.status == "success"
+    }
+}
+
+/// Body of `/api/create`: a new model derived from an existing one. Usually
+/// built from a `Modelfile`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct CreateModel {
+    /// name of the model to create
+    pub model: String,
+    /// model it is based on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// default sampling parameters, e.g. `temperature` or `stop`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, Value>,
+    /// adapter file names mapped to the digests of their uploaded blobs
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub adapters: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub license: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<ChatMessage>,
+}
+
+/// Format and size of a model, as reported by `/api/tags`, `/api/show` and `/api/ps`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ModelDetails {
+    pub parent_model: String,
+    pub format: String,
+    pub family: String,
+    pub families: Option<Vec<String>>,
+    pub parameter_size: String,
+    pub quantization_level: String,
+}
+
+/// A model installed on the daemon, from `/api/tags`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct ModelSummary {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub digest: String,
+    pub modified_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    #[serde(default)]
+    pub details: ModelDetails,
+}
+
+/// Everything `/api/show` reports about one model.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ModelInfo {
+    pub modelfile: String,
+    /// default parameters, one `name value` pair per line
+    pub parameters: String,
+    pub template: String,
+    pub system: String,
+    pub details: ModelDetails,
+    /// architecture metadata such as context length, keyed like `llama.context_length`
+    pub model_info: BTreeMap<String, Value>,
+    /// e.g. "completion", "tools", "vision"
+    pub capabilities: Vec<String>,
+}
+
+/// A model currently loaded in memory, from `/api/ps`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct RunningModel {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    /// bytes of the model held in GPU memory
+    #[serde(default)]
+    pub size_vram: u64,
+    #[serde(default)]
+    pub digest: String,
+    /// when the daemon will unload it
+    pub expires_at: Option<chrono::DateTime<chrono::FixedOffset>>,
+    #[serde(default)]
+    pub details: ModelDetails,
+}
+
+/// `{"models": [...]}` wrapper used by `/api/tags` and `/api/ps`
+#[derive(Deserialize)]
+pub(crate) struct ModelList<T> {
+    #[serde(default = "Vec::new")]
+    pub(crate) models: Vec<T>,
+}
+
+/// response of `/api/embed`, one vector per input
+#[derive(Deserialize)]
+pub(crate) struct Embeddings {
+    pub(crate) embeddings: Vec<Vec<f32>>,
+}
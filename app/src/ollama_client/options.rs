@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+use crate::ollama_client::CancellationToken;
+
+/// Sampling and runtime options for a generate or chat request. Unset fields
+/// fall back to the model's profile, then to Ollama's own defaults.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerateOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// context window in tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    /// maximum tokens to generate; `-1` means no limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+    /// fixed seed, for reproducible output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// sequences that end generation when produced
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f64>,
+    /// how long the model stays loaded after the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<KeepAlive>,
+    /// constrain the reply to JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<Format>,
+    /// stops the request early when cancelled; never sent to the server
+    #[serde(skip)]
+    pub cancel: Option<CancellationToken>,
+}
+
+impl GenerateOptions {
+    /// Options with `self`'s fields where set and `base`'s everywhere else.
+    pub fn or(&self, base: &GenerateOptions) -> GenerateOptions {
+        GenerateOptions {
+            temperature: self.temperature.or(base.temperature),
+            top_p: self.top_p.or(base.top_p),
+            top_k: self.top_k.or(base.top_k),
+            num_ctx: self.num_ctx.or(base.num_ctx),
+            num_predict: self.num_predict.or(base.num_predict),
+            seed: self.seed.or(base.seed),
+            stop: if self.stop.is_empty() { base.stop.clone() } else { self.stop.clone() },
+            repeat_penalty: self.repeat_penalty.or(base.repeat_penalty),
+            keep_alive: self.keep_alive.or(base.keep_alive),
+            format: self.format.clone().or_else(|| base.format.clone()),
+            cancel: self.cancel.clone().or_else(|| base.cancel.clone()),
+        }
+    }
+
+    /// Request body fields: `keep_alive` and `format` go at the top level,
+    /// everything else under `options`.
+    pub(crate) fn request_fields(&self) -> Map<String, Value> {
+        let mut fields = match serde_json::to_value(self) {
+            Ok(Value::Object(fields)) => fields,
+            _ => Map::new(),
+        };
+        let keep_alive = fields.remove("keep_alive");
+        let format = fields.remove("format");
+
+        let mut body = Map::new();
+        if !fields.is_empty() {
+            body.insert("options".to_string(), Value::Object(fields));
+        }
+        if let Some(keep_alive) = keep_alive {
+            body.insert("keep_alive".to_string(), keep_alive);
+        }
+        if let Some(format) = format {
+            body.insert("format".to_string(), format);
+        }
+        body
+    }
+}
+
+/// How long Ollama keeps a model in memory after a request.
+///
+/// Sent as whole seconds when possible, otherwise as a duration string
+/// rounded up to the millisecond, so a short keep-alive never becomes `0`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "KeepAliveValue", into = "KeepAliveValue")]
+pub enum KeepAlive {
+    /// unload after this long idle; zero unloads immediately
+    For(Duration),
+    Forever,
+}
+
+/// the wire forms Ollama accepts: seconds, or a Go duration such as `"5m"`
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum KeepAliveValue {
+    Seconds(i64),
+    Duration(String),
+}
+
+impl TryFrom<KeepAliveValue> for KeepAlive {
+    type Error = String;
+
+    fn try_from(value: KeepAliveValue) -> std::result::Result<Self, Self::Error> {
+        match value {
+            KeepAliveValue::Seconds(secs) if secs < 0 => Ok(KeepAlive::Forever),
+            KeepAliveValue::Seconds(secs) => Ok(KeepAlive::For(Duration::from_secs(secs as u64))),
+            KeepAliveValue::Duration(text) if text.starts_with('-') => Ok(KeepAlive::Forever),
+            KeepAliveValue::Duration(text) => parse_duration(&text)
+                .map(KeepAlive::For)
+                .ok_or_else(|| format!("invalid keep_alive duration: {:?}", text)),
+        }
+    }
+}
+
+impl From<KeepAlive> for KeepAliveValue {
+    fn from(keep_alive: KeepAlive) -> Self {
+        match keep_alive {
+            KeepAlive::For(duration) if duration.subsec_nanos() == 0 => KeepAliveValue::Seconds(duration.as_secs() as i64),
+            KeepAlive::For(duration) => KeepAliveValue::Duration(format!("{}ms", duration.as_nanos().div_ceil(1_000_000))),
+            KeepAlive::Forever => KeepAliveValue::Seconds(-1),
+        }
+    }
+}
+
+/// parse a Go-style duration like `"500ms"`, `"5m"` or `"1h30m"`
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut total = Duration::ZERO;
+    let mut rest = text.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(number_len);
+        let unit_len = tail.find(|c: char| c.is_ascii_digit() || c == '.').unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+
+        let number: f64 = number.parse().ok()?;
+        let unit_nanos: f64 = match unit {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return None,
+        };
+        let nanos = (number * unit_nanos).round();
+        if !(0.0..=u64::MAX as f64).contains(&nanos) {
+            return None;
+        }
+        total = total.checked_add(Duration::from_nanos(nanos as u64))?;
+        rest = tail;
+    }
+    Some(total)
+}
+
+/// Output format requested from the model.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Value", try_from = "Value")]
+pub enum Format {
+    /// any valid JSON
+    Json,
+    /// JSON matching this JSON schema
+    Schema(Value),
+}
+
+impl From<Format> for Value {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Json => Value::String("json".to_string()),
+            Format::Schema(schema) => schema,
+        }
+    }
+}
+
+impl TryFrom<Value> for Format {
+    type Error = String;
+
+    fn try_from(value: Value) -> std::result::Result<Self, Self::Error> {
+        match value {
+            Value::String(s) if s == "json" => Ok(Format::Json),
+            Value::Object(_) => Ok(Format::Schema(value)),
+            other => Err(format!("unsupported format: {}", other)),
+        }
+    }
+}
+
+/// Default `GenerateOptions` per model, keyed by full name (`codellama:7b`)
+/// or by name without the tag (`codellama`).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ModelProfiles {
+    profiles: HashMap<String, GenerateOptions>,
+}
+
+impl ModelProfiles {
+    /// Profiles shipped with llama_pack: low temperature for code models.
+    pub fn builtin() -> Self {
+        let code = GenerateOptions { temperature: Some(0.2), ..Default::default() };
+        let mut profiles = Self::default();
+        for family in ["codellama", "qwen2.5-coder", "deepseek-coder", "deepseek-coder-v2", "starcoder2", "codegemma"] {
+            profiles.insert(family, code.clone());
+        }
+        profiles
+    }
+
+    /// Read profiles from a JSON object mapping model names to options.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::InvalidInput(format!("invalid model profiles in {}: {}", path.display(), e)))
+    }
+
+    pub fn insert(&mut self, model: &str, options: GenerateOptions) {
+        self.profiles.insert(model.to_string(), options);
+    }
+
+    /// Add `other`'s profiles, replacing ones with the same key.
+    pub fn extend(&mut self, other: ModelProfiles) {
+        self.profiles.extend(other.profiles);
+    }
+
+    /// Defaults for `model`; an exact match wins over a match on the untagged name.
+    pub fn for_model(&self, model: &str) -> GenerateOptions {
+        let untagged = model.split(':').next().unwrap_or(model);
+        self.profiles
+            .get(model)
+            .or_else(|| self.profiles.get(untagged))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+
+/// system prompt used when no configured prompt applies
+pub const DEFAULT_SYSTEM_PROMPT: &str = "You are an expert software development assistant. Your job is to help the user understand, write, and debug code across many languages.
+Always clearly separate explanations from code.
+When generating code, use triple backticks with language identifiers (e.g., ```rust).
+Only generate code that is directly related to the user's task and relevant context.";
+
+/// System prompts per model, e.g. a team's house style, loaded from JSON like
+/// `{"default": "...", "models": {"codellama": "..."}}`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct SystemPrompts {
+    #[serde(default = "default_prompt")]
+    default: String,
+    /// keyed by full model name or by name without the tag
+    #[serde(default)]
+    models: HashMap<String, String>,
+}
+
+fn default_prompt() -> String {
+    DEFAULT_SYSTEM_PROMPT.to_string()
+}
+
+impl Default for SystemPrompts {
+    fn default() -> Self {
+        Self { default: default_prompt(), models: HashMap::new() }
+    }
+}
+
+impl SystemPrompts {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::InvalidInput(format!("invalid system prompts in {}: {}", path.display(), e)))
+    }
+
+    /// The prompt for `model`; an exact match wins over a match on the untagged name.
+    pub fn for_model(&self, model: &str) -> &str {
+        let untagged = model.split(':').next().unwrap_or(model);
+        self.models
+            .get(model)
+            .or_else(|| self.models.get(untagged))
+            .unwrap_or(&self.default)
+    }
+}
@@ -0,0 +1,177 @@
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::{future, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+
+/// Stream of chunks from a streaming LLM endpoint.
+pub type ChunkStream<T> = Pin<Box<dyn Stream<Item = Result<T>> + Send>>;
+
+/// One piece of a streamed `/api/generate` response.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GenerationChunk {
+    /// text generated since the previous chunk
+    pub text: String,
+    /// set on the last chunk, which also carries `stats`
+    pub done: bool,
+    /// why generation stopped, e.g. "stop" or "length"
+    pub done_reason: Option<String>,
+    pub stats: Option<GenerationStats>,
+}
+
+/// Token counts and timings Ollama reports on the final chunk.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenerationStats {
+    pub prompt_eval_count: u64,
+    pub eval_count: u64,
+    pub total_duration: Duration,
+    pub load_duration: Duration,
+    pub prompt_eval_duration: Duration,
+    pub eval_duration: Duration,
+}
+
+impl GenerationStats {
+    /// generated tokens per second, if Ollama reported a duration
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let secs = self.eval_duration.as_secs_f64();
+        (secs > 0.0).then(|| self.eval_count as f64 / secs)
+    }
+}
+
+/// wire format of one generate line; durations are nanoseconds
+#[derive(Deserialize)]
+struct RawGenerateChunk {
+    #[serde(default)]
+    response: String,
+    #[serde(default)]
+    done: bool,
+    done_reason: Option<String>,
+    #[serde(flatten)]
+    stats: RawStats,
+}
+
+#[derive(Deserialize, Default)]
+pub(crate) struct RawStats {
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
+    total_duration: Option<u64>,
+    load_duration: Option<u64>,
+    prompt_eval_duration: Option<u64>,
+    eval_duration: Option<u64>,
+}
+
+impl RawStats {
+    pub(crate) fn into_stats(self) -> GenerationStats {
+        GenerationStats {
+            prompt_eval_count: self.prompt_eval_count.unwrap_or(0),
+            eval_count: self.eval_count.unwrap_or(0),
+            total_duration: Duration::from_nanos(self.total_duration.unwrap_or(0)),
+            load_duration: Duration::from_nanos(self.load_duration.unwrap_or(0)),
+            prompt_eval_duration: Duration::from_nanos(self.prompt_eval_duration.unwrap_or(0)),
+            eval_duration: Duration::from_nanos(self.eval_duration.unwrap_or(0)),
+        }
+    }
+}
+
+impl From<RawGenerateChunk> for GenerationChunk {
+    fn from(raw: RawGenerateChunk) -> Self {
+        GenerationChunk {
+            text: raw.response,
+            done: raw.done,
+            done_reason: raw.done_reason,
+            stats: raw.done.then(|| raw.stats.into_stats()),
+        }
+    }
+}
+
+/// Decode a streamed `/api/generate` body.
+pub(crate) fn generation_stream(response: reqwest::Response) -> ChunkStream<GenerationChunk> {
+    Box::pin(ndjson_stream::<RawGenerateChunk>(response).map(|chunk| chunk.map(GenerationChunk::from)))
+}
+
+/// an `{"error": ...}` line sent mid-stream
+#[derive(Deserialize)]
+struct StreamError {
+    error: String,
+}
+
+/// Decode a newline-delimited JSON body into a stream of `T`, ending after the
+/// first line with `"done": true`.
+pub(crate) fn ndjson_stream<T>(response: reqwest::Response) -> ChunkStream<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    Box::pin(line_stream(response).scan(false, |finished, line| {
+        if *finished {
+            return future::ready(None);
+        }
+        let decoded = line.and_then(|line| decode_line::<T>(&line));
+        *finished = decoded.as_ref().map_or(true, |(_, done)| *done);
+        future::ready(Some(decoded.map(|(item, _)| item)))
+    }))
+}
+
+/// Split a response body into its non-blank lines, newline included. A final
+/// line without a trailing newline is still yielded; the stream ends after
+/// the first transport error.
+pub(crate) fn line_stream(response: reqwest::Response) -> ChunkStream<Vec<u8>> {
+    struct State<S> {
+        body: Pin<Box<S>>,
+        /// bytes not yet terminated by a newline
+        pending: Vec<u8>,
+        finished: bool,
+    }
+
+    let state = State {
+        body: Box::pin(response.bytes_stream()),
+        pending: Vec::new(),
+        finished: false,
+    };
+
+    Box::pin(futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if state.finished {
+                return None;
+            }
+
+            if let Some(newline) = state.pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = state.pending.drain(..=newline).collect();
+                if is_blank(&line) {
+                    continue;
+                }
+                return Some((Ok(line), state));
+            }
+
+            match state.body.next().await {
+                Some(Ok(bytes)) => state.pending.extend_from_slice(&bytes),
+                Some(Err(e)) => {
+                    state.finished = true;
+                    return Some((Err(Error::Http(e)), state));
+                }
+                None => {
+                    state.finished = true;
+                    let line = std::mem::take(&mut state.pending);
+                    return (!is_blank(&line)).then_some((Ok(line), state));
+                }
+            }
+        }
+    }))
+}
+
+fn is_blank(line: &[u8]) -> bool {
+    line.iter().all(u8::is_ascii_whitespace)
+}
+
+/// the bool is the line's `done` flag
+fn decode_line<T: DeserializeOwned>(line: &[u8]) -> Result<(T, bool)> {
+    let value: serde_json::Value = serde_json::from_slice(line)?;
+    if let Ok(StreamError { error }) = serde_json::from_value::<StreamError>(value.clone()) {
+        return Err(Error::Api { status: 200, message: error });
+    }
+
+    let done = value.get("done").and_then(|d| d.as_bool()).unwrap_or(false);
+    Ok((serde_json::from_value(value)?, done))
+}
@@ -0,0 +1,94 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// attempts `OllamaClient::query_structured` makes before giving up
+pub const STRUCTURED_ATTEMPTS: usize = 3;
+
+/// Check `value` against the subset of JSON Schema Ollama's `format` uses:
+/// `type`, `enum`, `properties`, `required`, `additionalProperties: false`
+/// and `items`. Returns the first violation, with its JSON path.
+pub fn validate(value: &Value, schema: &Value) -> Result<(), String> {
+    validate_at(value, schema, "$")
+}
+
+fn validate_at(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| has_type(value, t)) {
+            return Err(format!("{}: expected {}, got {}", path, allowed.join(" or "), type_name(value)));
+        }
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            return Err(format!("{}: {} is not one of {}", path, value, Value::Array(options.clone())));
+        }
+    }
+
+    if let Value::Object(fields) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !fields.contains_key(name) {
+                    return Err(format!("{}: missing required field '{}'", path, name));
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, field) in fields {
+            match properties.and_then(|p| p.get(name)) {
+                Some(field_schema) => validate_at(field, field_schema, &format!("{}.{}", path, name))?,
+                None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                    return Err(format!("{}: unexpected field '{}'", path, name));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate_at(item, item_schema, &format!("{}[{}]", path, i))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Parse a model reply as JSON, check it against `schema` and deserialise it.
+pub(crate) fn parse<T: DeserializeOwned>(reply: &str, schema: &Value) -> Result<T, String> {
+    let value: Value = serde_json::from_str(reply.trim()).map_err(|e| format!("reply is not valid JSON: {}", e))?;
+    validate(&value, schema)?;
+    serde_json::from_value(value).map_err(|e| format!("reply does not fit the expected shape: {}", e))
+}
+
+/// follow-up turn asking the model to fix its previous reply
+pub(crate) fn retry_prompt(error: &str) -> String {
+    format!(
+        "Your previous reply was rejected: {}. Reply again with only JSON that matches the schema.",
+        error
+    )
+}
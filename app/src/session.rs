@@ -1,16 +1,23 @@
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::error::Error;
 use std::{env, fs};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+use crate::error::Result;
+use crate::ollama_client::ChatMessage;
+
+/// appended to interrupted responses when they are sent back as history
+pub const INTERRUPTED_MARKER: &str = "\n[interrupted by the user]";
 
 #[derive(Serialize, Deserialize)]
 pub struct PromptLog {
     timestamp: String,
     prompt: String,
     response: String,
+    /// the user stopped the reply part way, so `response` is partial
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    interrupted: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -26,11 +33,16 @@ pub struct SessionManager {
 
 impl SessionManager {
     /// Creates a new session with a unique ID
-    pub fn new_session() -> std::io::Result<Self> {
+    pub fn new_session() -> Result<Self> {
+        Self::new_session_in(&env::current_dir()?)
+    }
+
+    /// Creates a new session stored under `root/.coder_sessions`
+    pub fn new_session_in(root: &Path) -> Result<Self> {
         let id = Uuid::new_v4().to_string();
         let session = Session { id, logs: Vec::new() };
 
-        let mut session_dir = env::current_dir()?;
+        let mut session_dir = root.to_path_buf();
         session_dir.push(".coder_sessions");
         session_dir.push(&session.id);
 
@@ -41,7 +53,7 @@ impl SessionManager {
 
 
     /// Loads an existing session by ID
-    pub fn load_session(_id: &str) -> Result<Self, Box<dyn Error>> {
+    pub fn load_session(_id: &str) -> Result<Self> {
         // TODO: Construct path to session file (~/.coder_sessions/<id>.json)
         // TODO: Check if session file exists
         // TODO: Read and parse JSON file into Session struct
@@ -51,7 +63,7 @@ impl SessionManager {
     }
 
     /// Lists all available session IDs
-    pub fn list_sessions() -> Result<Vec<String>, Box<dyn Error>> {
+    pub fn list_sessions() -> Result<Vec<String>> {
         // TODO: Get session directory path
         // TODO: Read directory contents
         // TODO: Filter for .json files
@@ -62,20 +74,54 @@ impl SessionManager {
     }
 
     /// Saves a prompt and response to the current session
-    pub fn save_log(&mut self, prompt: &str, response: &str) -> Result<(), Box<dyn Error>> {
+    pub fn save_log(&mut self, prompt: &str, response: &str) -> Result<()> {
+        self.push_log(prompt, response, false)
+    }
+
+    /// Saves a prompt and the part of the response received before the user
+    /// interrupted it
+    pub fn save_interrupted(&mut self, prompt: &str, partial_response: &str) -> Result<()> {
+        self.push_log(prompt, partial_response, true)
+    }
+
+    /// Builds the most recent turns as chat messages, oldest first, keeping as
+    /// many whole turns as fit in `token_budget` estimated tokens
+    pub fn history(&self, token_budget: usize) -> Vec<ChatMessage> {
+        let mut used = 0;
+        let mut turns = Vec::new();
+
+        for log in self.session.logs.iter().rev() {
+            let cost = estimate_tokens(&log.prompt) + estimate_tokens(&log.history_response());
+            if used + cost > token_budget {
+                break;
+            }
+            used += cost;
+            turns.push(log);
+        }
+
+        turns
+            .into_iter()
+            .rev()
+            .flat_map(|log| [ChatMessage::user(&log.prompt), ChatMessage::assistant(log.history_response())])
+            .collect()
+    }
+
+    /// Private helper to append a log entry and persist the session
+    fn push_log(&mut self, prompt: &str, response: &str, interrupted: bool) -> Result<()> {
         let log = PromptLog {
             timestamp: Utc::now().to_rfc3339(),
             prompt: prompt.to_string(),
             response: response.to_string(),
+            interrupted,
         };
-        
+
         self.session.logs.push(log);
         self.save_session()?;
         Ok(())
     }
 
     /// Private helper to save session to disk
-    fn save_session(&self) -> Result<(), Box<dyn Error>> {
+    fn save_session(&self) -> Result<()> {
         let session_file = self.session_dir.join("session.json");
         let session_json = serde_json::to_string_pretty(&self.session)?;
         fs::write(session_file, session_json)?;
@@ -83,10 +129,27 @@ impl SessionManager {
     }
 
     /// Private helper to get session directory path
+    #[allow(dead_code)]
     fn get_session_dir() -> PathBuf {
         // TODO: Get user home directory
         // TODO: Append .coder_sessions to path
         
         todo!("Implement get_session_dir")
     }
-}
\ No newline at end of file
+}
+
+impl PromptLog {
+    /// the response as replayed to the model; partial ones say so
+    fn history_response(&self) -> String {
+        if self.interrupted {
+            format!("{}{}", self.response, INTERRUPTED_MARKER)
+        } else {
+            self.response.clone()
+        }
+    }
+}
+
+/// Rough token count for budgeting history; about four characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
@@ -0,0 +1,214 @@
+/// max chars kept in content_preview
+pub const PREVIEW_LEN: usize = 512;
+
+/// keywords that open a definition once visibility/async modifiers are stripped
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "fn ", "struct ", "enum ", "trait ", "impl ", "impl<", "mod ", "type ", "const ", "static ", "macro_rules!",
+    "def ", "class ", "func ", "function ", "interface ", "module ",
+];
+
+/// longest multi-line signature kept in a preview
+const MAX_SIGNATURE_LINES: usize = 12;
+
+/// modifiers that may precede a definition keyword
+const MODIFIERS: &[&str] = &[
+    "pub ", "pub(crate) ", "pub(super) ", "export ", "default ", "async ", "unsafe ", "extern ",
+    "public ", "private ", "protected ", "static ", "abstract ", "final ",
+];
+
+/// Build the `content_preview` for a chunk: the doc comment directly above its
+/// first definition followed by that definition's signature, which may span
+/// several lines, and a Python docstring if there is one. Capped at
+/// `PREVIEW_LEN` chars. Chunks without a recognisable definition fall back to
+/// their leading lines.
+pub fn content_preview(content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+
+    let preview = match lines.iter().position(|line| is_definition(line)) {
+        Some(def_index) => {
+            let mut doc_start = def_index;
+            while doc_start > 0 && is_doc_or_attribute(lines[doc_start - 1]) {
+                doc_start -= 1;
+            }
+
+            let mut preview: Vec<&str> = lines[doc_start..def_index]
+                .iter()
+                .copied()
+                .filter(|line| !line.trim_start().starts_with("#["))
+                .collect();
+            let signature = signature(&lines[def_index..]);
+            let body = &lines[def_index + signature.len()..];
+            let python = ["def ", "class "].iter().any(|k| strip_modifiers(lines[def_index]).starts_with(k));
+            preview.extend(signature);
+            if python {
+                preview.extend(docstring(body));
+            }
+            preview.join("\n")
+        }
+        None => lines
+            .iter()
+            .copied()
+            .filter(|line| !line.trim().is_empty())
+            .take(5)
+            .collect::<Vec<&str>>()
+            .join("\n"),
+    };
+
+    preview.chars().take(PREVIEW_LEN).collect()
+}
+
+fn is_definition(line: &str) -> bool {
+    let rest = strip_modifiers(line);
+    DEFINITION_KEYWORDS.iter().any(|keyword| rest.starts_with(keyword))
+}
+
+/// the line from its definition keyword on
+fn strip_modifiers(line: &str) -> &str {
+    let mut rest = line.trim_start();
+    while let Some(modifier) = MODIFIERS.iter().find(|m| rest.starts_with(**m)) {
+        rest = &rest[modifier.len()..];
+    }
+    rest
+}
+
+fn is_doc_or_attribute(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    ["//", "#", "/*", "*", "--", "\"\"\"", "@"]
+        .iter()
+        .any(|prefix| trimmed.starts_with(prefix))
+}
+
+/// The declaration starting at `lines[0]`, without its body. Continuation
+/// lines are kept while brackets are open or a `where` clause or return type
+/// follows, up to the opening `{`, a Python `:` or a `;`.
+fn signature<'a>(lines: &[&'a str]) -> Vec<&'a str> {
+    let mut signature = Vec::new();
+    let mut depth = 0i32;
+    let mut in_where = false;
+
+    for (i, line) in lines.iter().enumerate().take(MAX_SIGNATURE_LINES) {
+        let line = line.trim_end();
+        if let Some(brace) = line.find('{') {
+            let head = line[..brace].trim_end();
+            if !head.trim().is_empty() {
+                signature.push(head);
+            }
+            break;
+        }
+        signature.push(line);
+
+        for c in line.chars() {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth -= 1,
+                _ => {}
+            }
+        }
+        let trimmed = line.trim();
+        in_where |= trimmed == "where" || trimmed.starts_with("where ") || trimmed.ends_with(" where");
+        if depth > 0 {
+            continue;
+        }
+        if trimmed.ends_with(':') || trimmed.ends_with(';') {
+            break;
+        }
+
+        let next = lines.get(i + 1).map(|next| next.trim_start()).unwrap_or_default();
+        let continues = in_where
+            || trimmed.ends_with("->")
+            || ["where", "->", "{", ")", ":"].iter().any(|start| next.starts_with(start));
+        if !continues {
+            break;
+        }
+    }
+
+    signature
+}
+
+/// The docstring opening a Python body: its first statement, if that is a
+/// string literal.
+fn docstring<'a>(body: &[&'a str]) -> Vec<&'a str> {
+    let Some(start) = body.iter().position(|line| !line.trim().is_empty()) else { return Vec::new() };
+    let first = body[start].trim_start();
+    let literal = first.trim_start_matches(['r', 'R', 'u', 'U']);
+
+    let Some(quote) = ["\"\"\"", "'''", "\"", "'"].into_iter().find(|q| literal.starts_with(q)) else {
+        return Vec::new();
+    };
+    if quote.len() == 1 || literal[quote.len()..].contains(quote) {
+        return vec![body[start].trim_end()];
+    }
+
+    match body[start + 1..].iter().position(|line| line.contains(quote)) {
+        Some(end) => body[start..=start + 1 + end].iter().map(|line| line.trim_end()).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Names defined by the chunk, in order of first appearance.
+pub fn extract_symbols(content: &str) -> Vec<String> {
+    let mut symbols: Vec<String> = Vec::new();
+
+    for line in content.lines() {
+        let Some(name) = defined_name(line) else { continue };
+        if !symbols.contains(&name) {
+            symbols.push(name);
+        }
+    }
+
+    symbols
+}
+
+/// the identifier introduced by a definition line, if any
+fn defined_name(line: &str) -> Option<String> {
+    let mut rest = strip_modifiers(line);
+
+    // impl blocks define no new name
+    let keyword = DEFINITION_KEYWORDS
+        .iter()
+        .filter(|keyword| !keyword.starts_with("impl"))
+        .find(|keyword| rest.starts_with(**keyword))?;
+    rest = rest[keyword.len()..].trim_start();
+
+    let name: String = rest
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Test sources and fixtures, judged by path.
+pub fn is_test_path(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    let mut components: Vec<&str> = path.split('/').collect();
+    let file_name = components.pop().unwrap_or_default();
+    let stem = file_name.split('.').next().unwrap_or_default();
+
+    components.iter().any(|dir| matches!(*dir, "test" | "tests" | "__tests__" | "spec" | "fixtures" | "testdata"))
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with("_tb")
+        || stem.ends_with("_spec")
+        || file_name.contains(".test.")
+        || file_name.contains(".spec.")
+}
+
+/// Generated bindings and code, judged by path conventions and the usual
+/// "generated, do not edit" header.
+pub fn is_generated(path: &str, content: &str) -> bool {
+    let lower_path = path.to_ascii_lowercase();
+    let by_path = lower_path.split('/').any(|part| matches!(part, "generated" | "gen" | "__generated__"))
+        || lower_path.ends_with(".pb.go")
+        || lower_path.ends_with("_pb2.py")
+        || lower_path.ends_with(".g.dart")
+        || lower_path.contains(".generated.");
+
+    by_path || content.lines().take(10).any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.contains("@generated")
+            || line.contains("do not edit")
+            || line.contains("auto-generated")
+            || line.contains("autogenerated")
+            || line.contains("automatically generated")
+    })
+}
@@ -0,0 +1,294 @@
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use crate::error::{Error, Result};
+use crate::embedder::Embedder;
+use crate::embeddings_controller::{
+    build_record, detect_language, hash_content, is_skipped_dir, read_source_file, relative_path,
+    IndexStats, IndexerConfig,
+};
+use crate::lancedb::LanceDbClient;
+
+/// Settings for `watch_directory`.
+#[derive(Clone, Debug)]
+pub struct WatchConfig {
+    /// model and tokenizer used to re-embed changed files
+    pub indexer: IndexerConfig,
+    /// quiet period after the last event before a burst of changes is applied
+    pub debounce: Duration,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            indexer: IndexerConfig::default(),
+            debounce: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Keep `client` in sync with the files under `root` until `shutdown` completes.
+///
+/// Changed files are re-embedded, removed files are deleted, and a file whose
+/// content hash matches a just-removed row is treated as a rename: only its
+/// `path` is updated. Files that become empty or unreadable are deleted too.
+///
+/// Returns the first error that affects the whole store, such as a failed
+/// write or a model that won't load; the caller decides whether to start
+/// watching again. Files that fail on their own are skipped and listed in
+/// each batch's `IndexStats::failed`, see `watch_directory_with`.
+pub async fn watch_directory<F>(client: &LanceDbClient, root: &Path, config: &WatchConfig, shutdown: F) -> Result<()>
+where
+    F: Future<Output = ()>,
+{
+    watch_directory_with(client, root, config, shutdown, |_| {}).await
+}
+
+/// Like `watch_directory`, passing the stats of every applied batch of
+/// changes to `on_batch`.
+pub async fn watch_directory_with<F, B>(
+    client: &LanceDbClient,
+    root: &Path,
+    config: &WatchConfig,
+    shutdown: F,
+    mut on_batch: B,
+) -> Result<()>
+where
+    F: Future<Output = ()>,
+    B: FnMut(&IndexStats),
+{
+    let root = root.canonicalize()?;
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel::<notify::Result<Event>>();
+
+    let mut watcher = recommended_watcher(move |event| {
+        let _ = event_tx.send(event);
+    })?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+
+    let mut sync = StoreSync {
+        client,
+        root: root.clone(),
+        config: config.indexer.clone(),
+        known: client.list_hashes().await?,
+        embedder: None,
+        commit_cleared: false,
+    };
+
+    tokio::pin!(shutdown);
+    loop {
+        let first = tokio::select! {
+            _ = &mut shutdown => return Ok(()),
+            event = event_rx.recv() => match event {
+                Some(event) => event,
+                None => return Ok(()),
+            },
+        };
+
+        // debounce: keep collecting until the tree has been quiet for a while
+        let mut touched = BTreeSet::new();
+        let mut stats = IndexStats::default();
+        collect_paths(first, &root, &mut touched, &mut stats);
+        while let Ok(Some(event)) = tokio::time::timeout(config.debounce, event_rx.recv()).await {
+            collect_paths(event, &root, &mut touched, &mut stats);
+        }
+
+        sync.apply(touched, &mut stats).await?;
+        on_batch(&stats);
+    }
+}
+
+/// record the paths an event touched, ignoring hidden and build directories;
+/// a watcher error is recorded against the paths it names
+fn collect_paths(event: notify::Result<Event>, root: &Path, touched: &mut BTreeSet<PathBuf>, stats: &mut IndexStats) {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            let path = e.paths.first().map(|path| relative_path(root, path)).unwrap_or_default();
+            stats.failed.push((path, Error::Watch(e)));
+            return;
+        }
+    };
+    if matches!(event.kind, EventKind::Access(_)) {
+        return;
+    }
+
+    for path in event.paths {
+        let Ok(relative) = path.strip_prefix(root) else { continue };
+        let ignored = relative.components().any(|c| match c {
+            Component::Normal(name) => is_skipped_dir(&name.to_string_lossy()),
+            _ => false,
+        });
+        if !ignored {
+            touched.insert(path);
+        }
+    }
+}
+
+/// applies batches of file-system changes to the store
+struct StoreSync<'a> {
+    client: &'a LanceDbClient,
+    root: PathBuf,
+    config: IndexerConfig,
+    /// path -> hash of every indexed file, kept in step with the table
+    known: HashMap<String, String>,
+    /// loaded on first use so pure renames and deletes never pay for it
+    embedder: Option<Arc<Mutex<Embedder>>>,
+    /// whether this batch has cleared the table's indexed commit yet
+    commit_cleared: bool,
+}
+
+impl StoreSync<'_> {
+    /// apply one batch, adding what happened to `stats`; per-file embedding
+    /// failures leave the old row in place and are retried on the next change
+    async fn apply(&mut self, touched: BTreeSet<PathBuf>, stats: &mut IndexStats) -> Result<()> {
+        // a git index may have stamped the table since the last batch
+        self.commit_cleared = false;
+        let mut present: BTreeSet<PathBuf> = BTreeSet::new();
+        let mut removed: BTreeSet<String> = BTreeSet::new();
+
+        for path in touched {
+            if path.is_dir() {
+                collect_files(&path, &mut present);
+            } else if path.is_file() {
+                present.insert(path);
+            } else {
+                // gone: either a file or a whole directory
+                let relative = relative_path(&self.root, &path);
+                let prefix = format!("{}/", relative);
+                removed.extend(
+                    self.known.keys()
+                        .filter(|known| **known == relative || known.starts_with(&prefix))
+                        .cloned(),
+                );
+            }
+        }
+
+        // removed hash -> path, so moved files can be matched by content
+        let mut moved_from: HashMap<String, String> = removed
+            .iter()
+            .filter_map(|path| self.known.get(path).map(|hash| (hash.clone(), path.clone())))
+            .collect();
+
+        for path in present {
+            let Some(file) = read_source_file(&self.root, &path) else {
+                // e.g. no longer UTF-8: the old content must not stay searchable
+                let relative = relative_path(&self.root, &path);
+                if self.known.contains_key(&relative) {
+                    removed.insert(relative);
+                }
+                continue;
+            };
+            let hash = hash_content(&file.content);
+
+            if self.known.get(&file.path) == Some(&hash) {
+                continue;
+            }
+
+            if let Some(old_path) = moved_from.remove(&hash) {
+                self.clear_commit().await?;
+                self.client.rename_embedding(&old_path, &file.path).await?;
+                removed.remove(&old_path);
+                self.known.remove(&old_path);
+                self.known.insert(file.path, hash);
+                continue;
+            }
+
+            if file.content.trim().is_empty() {
+                if self.known.contains_key(&file.path) {
+                    removed.insert(file.path);
+                }
+                stats.files_skipped += 1;
+                continue;
+            }
+
+            let embedder = self.embedder().await?;
+            let (embedding, token_count) = match embed(embedder, file.content.clone()).await {
+                Ok(embedded) => embedded,
+                Err(e) => {
+                    stats.failed.push((file.path, e));
+                    continue;
+                }
+            };
+            let record = build_record(file, embedding, token_count);
+            let path = record.path.clone();
+
+            self.clear_commit().await?;
+            self.client.update_embedding(&path, record).await?;
+            self.known.insert(path, hash);
+            stats.files_indexed += 1;
+        }
+
+        let removed: Vec<String> = removed.into_iter().collect();
+        if !removed.is_empty() {
+            self.clear_commit().await?;
+        }
+        self.client.delete_embeddings(&removed).await?;
+        for path in &removed {
+            self.known.remove(path);
+        }
+
+        Ok(())
+    }
+
+    /// once the working tree is written, the table no longer reflects a commit
+    async fn clear_commit(&mut self) -> Result<()> {
+        if !self.commit_cleared {
+            self.client.clear_indexed_commit().await?;
+            self.commit_cleared = true;
+        }
+        Ok(())
+    }
+
+    /// the embedder, loaded on the blocking pool on first use; without it
+    /// no file can be embedded, so a failure here ends the watch
+    async fn embedder(&mut self) -> Result<Arc<Mutex<Embedder>>> {
+        if let Some(embedder) = &self.embedder {
+            return Ok(Arc::clone(embedder));
+        }
+
+        let model_path = self.config.model_path.clone();
+        let tokenizer_path = self.config.tokenizer_path.clone();
+        let embedder = tokio::task::spawn_blocking(move || Embedder::new(&model_path, &tokenizer_path))
+            .await
+            .map_err(|_| Error::WorkerPanicked)??;
+
+        let embedder = Arc::new(Mutex::new(embedder));
+        self.embedder = Some(Arc::clone(&embedder));
+        Ok(embedder)
+    }
+}
+
+/// embedding and token count of `content`, computed on the blocking pool
+/// so any runtime flavour can drive the watcher
+async fn embed(embedder: Arc<Mutex<Embedder>>, content: String) -> Result<(Vec<f32>, usize)> {
+    tokio::task::spawn_blocking(move || {
+        let mut embedder = embedder.lock().map_err(|_| Error::WorkerPanicked)?;
+        embedder.embed_counted(&content)
+    })
+    .await
+    .map_err(|_| Error::WorkerPanicked)?
+}
+
+/// every indexable file below `dir`
+fn collect_files(dir: &Path, files: &mut BTreeSet<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else { continue };
+
+        if file_type.is_dir() {
+            if !is_skipped_dir(&entry.file_name().to_string_lossy()) {
+                collect_files(&path, files);
+            }
+        } else if file_type.is_file() && detect_language(&path).is_some() {
+            files.insert(path);
+        }
+    }
+}
@@ -23,6 +23,20 @@ fn test_embed_single_prompt() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_embed_counted_matches_count_tokens() -> Result<()> {
+    let mut embedder = Embedder::new(
+        "../models/UniXcoder/unixcoder-embedding.onnx",
+        "../models/UniXcoder/tokenizer.json"
+    )?;
+
+    let code = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+    let (embedding, token_count) = embedder.embed_counted(code)?;
+    assert_eq!(embedding, embedder.embed(code)?);
+    assert_eq!(token_count, embedder.count_tokens(code)?);
+    Ok(())
+}
+
 #[test]
 fn test_embed_retrieval_task() -> Result<()> {
     let mut embedder = Embedder::new(
@@ -0,0 +1,134 @@
+use llama_pack::embeddings_controller::{
+    detect_language, find_stale_records, index_directory, IndexerConfig, Staleness,
+};
+use llama_pack::lancedb::LanceDbClient;
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+mod support;
+
+use support::{record_for, MODEL_PATH, TOKENIZER_PATH};
+
+fn model_files_exist() -> bool {
+    Path::new(MODEL_PATH).exists() && Path::new(TOKENIZER_PATH).exists()
+}
+
+// Test helper to lay out a small source tree
+fn create_source_tree(root: &Path) -> Result<()> {
+    fs::create_dir_all(root.join("src/utils"))?;
+    fs::create_dir_all(root.join("target/debug"))?;
+    fs::create_dir_all(root.join(".git"))?;
+
+    fs::write(root.join("src/main.rs"), "fn main() { println!(\"hello\"); }")?;
+    fs::write(root.join("src/utils/mod.rs"), "pub fn add(a: i32, b: i32) -> i32 { a + b }")?;
+    fs::write(root.join("src/script.py"), "def reverse(s):\n    return s[::-1]\n")?;
+    fs::write(root.join("src/empty.rs"), "")?;
+    fs::write(root.join("image.png"), [0u8, 1, 2, 3])?;
+    fs::write(root.join("target/debug/build.rs"), "fn ignored() {}")?;
+    fs::write(root.join(".git/config.toml"), "ignored = true")?;
+    Ok(())
+}
+
+#[test]
+fn test_detect_language() {
+    assert_eq!(detect_language(Path::new("src/main.rs")), Some("rust"));
+    assert_eq!(detect_language(Path::new("app/Main.JAVA")), Some("java"));
+    assert_eq!(detect_language(Path::new("web/index.tsx")), Some("typescript"));
+    assert_eq!(detect_language(Path::new("image.png")), None);
+    assert_eq!(detect_language(Path::new("Makefile")), None);
+}
+
+#[test]
+fn test_model_id_is_model_file_stem() {
+    assert_eq!(IndexerConfig::default().model_id(), "unixcoder-embedding");
+}
+
+#[tokio::test]
+async fn test_find_stale_records() -> Result<()> {
+    let source_dir = TempDir::new()?;
+    fs::create_dir_all(source_dir.path().join("src"))?;
+    fs::write(source_dir.path().join("src/fresh.rs"), "fn fresh() {}")?;
+    fs::write(source_dir.path().join("src/edited.rs"), "fn edited() { 2 }")?;
+
+    let db_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.insert_embeddings(vec![
+        record_for("src/fresh.rs", "fn fresh() {}"),
+        record_for("src/edited.rs", "fn edited() { 1 }"),
+        record_for("src/deleted.rs", "fn deleted() {}"),
+    ]).await?;
+
+    let stale = find_stale_records(&client, source_dir.path()).await?;
+    assert_eq!(stale, vec![
+        ("src/deleted.rs".to_string(), Staleness::Missing),
+        ("src/edited.rs".to_string(), Staleness::Modified),
+    ]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_index_directory_invalid_model_path() -> Result<()> {
+    let source_dir = TempDir::new()?;
+    create_source_tree(source_dir.path())?;
+
+    let db_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+
+    let config = IndexerConfig {
+        model_path: "does/not/exist.onnx".to_string(),
+        tokenizer_path: "does/not/exist.json".to_string(),
+        ..IndexerConfig::default()
+    };
+
+    // A worker failing to load its model should abort the whole pipeline
+    let result = index_directory(&client, source_dir.path(), &config).await;
+    assert!(result.is_err());
+    assert!(client.get_embedding("src/main.rs").await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_index_directory_with_real_embeddings() -> Result<()> {
+    if !model_files_exist() {
+        println!("Skipping test - model files not found");
+        return Ok(());
+    }
+
+    let source_dir = TempDir::new()?;
+    create_source_tree(source_dir.path())?;
+
+    let db_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+
+    let config = IndexerConfig {
+        model_path: MODEL_PATH.to_string(),
+        tokenizer_path: TOKENIZER_PATH.to_string(),
+        embedder_threads: 2,
+        batch_size: 2,
+        ..IndexerConfig::default()
+    };
+
+    let stats = index_directory(&client, source_dir.path(), &config).await?;
+    assert_eq!(stats.files_indexed, 3);
+    assert_eq!(stats.files_skipped, 1);
+    assert!(stats.failed.is_empty());
+
+    let main_record = client.get_embedding("src/main.rs").await?.unwrap();
+    assert_eq!(main_record.language, "rust");
+    assert_eq!(main_record.line_count, 1);
+    assert_eq!(main_record.content.as_deref(), Some("fn main() { println!(\"hello\"); }"));
+    assert_eq!(main_record.content_preview.as_deref(), Some("fn main()"));
+    assert!(client.get_embedding("src/utils/mod.rs").await?.is_some());
+    assert!(client.get_embedding("target/debug/build.rs").await?.is_none());
+
+    // Re-indexing replaces rows instead of duplicating them
+    index_directory(&client, source_dir.path(), &config).await?;
+    let results = client.query_similar(&main_record.embedding, 10).await?;
+    assert_eq!(results.len(), 3);
+
+    Ok(())
+}
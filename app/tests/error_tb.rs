@@ -0,0 +1,70 @@
+use llama_pack::git_index::resolve_commit;
+use llama_pack::lancedb::{BundleManifest, LanceDbClient, Namespace, QueryOptions};
+use llama_pack::Error;
+use anyhow::Result;
+use std::error::Error as _;
+use std::fs;
+use tempfile::TempDir;
+
+// ========== VECTOR STORE ERROR TESTS ==========
+
+#[tokio::test]
+async fn test_dimension_mismatch_is_typed() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(temp_dir.path().to_str().unwrap()).await?;
+
+    let err = client.query_similar(&[0.1; 512], 5).await.unwrap_err();
+    assert!(matches!(err, Error::DimensionMismatch { expected: 768, actual: 512 }));
+    assert_eq!(err.code(), "store.dimension_mismatch");
+    assert!(err.to_string().contains("Invalid embedding dimension"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_missing_embedding_and_table_are_distinguishable() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(temp_dir.path().to_str().unwrap()).await?;
+
+    let err = client.query_similar_to_file("src/nope.rs", 5).await.unwrap_err();
+    assert!(matches!(&err, Error::EmbeddingNotFound(path) if path == "src/nope.rs"));
+    assert_eq!(err.code(), "store.embedding_not_found");
+
+    let missing = [Namespace::new("ghost", "main")];
+    let err = client.query_namespaces(&missing, &[0.1; 768], 5, &QueryOptions::default()).await.unwrap_err();
+    assert!(matches!(&err, Error::TableNotFound(name) if name == "ghost@main"));
+    assert_eq!(err.code(), "store.table_not_found");
+
+    let err = Namespace::parse("no-branch").unwrap_err();
+    assert_eq!(err.code(), "store.invalid_namespace");
+
+    Ok(())
+}
+
+#[test]
+fn test_bundle_errors_chain_their_source() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let err = BundleManifest::read(temp_dir.path()).unwrap_err();
+    assert_eq!(err.code(), "store.incompatible_bundle");
+
+    fs::write(temp_dir.path().join("manifest.json"), "{ not json")?;
+    let err = BundleManifest::read(temp_dir.path()).unwrap_err();
+    assert_eq!(err.code(), "json_error");
+    assert!(err.source().is_some());
+
+    Ok(())
+}
+
+// ========== INDEXING ERROR TESTS ==========
+
+#[test]
+fn test_unknown_revision_is_typed() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+
+    let err = resolve_commit(temp_dir.path(), "main").unwrap_err();
+    assert!(matches!(&err, Error::UnknownRevision(rev) if rev == "main"));
+    assert_eq!(err.code(), "index.unknown_revision");
+
+    Ok(())
+}
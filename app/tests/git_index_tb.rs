@@ -0,0 +1,240 @@
+use llama_pack::embeddings_controller::{hash_content, index_directory, IndexerConfig};
+use llama_pack::git_index::{index_commit, resolve_commit};
+use llama_pack::lancedb::LanceDbClient;
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+mod support;
+
+use support::{no_model_config, picky_tokenizer, record_for, MODEL_PATH};
+
+fn git(repo: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C").arg(repo)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com", "-c", "commit.gpgsign=false"])
+        .args(args)
+        .output()?;
+    assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+fn commit_all(repo: &Path, message: &str) -> Result<String> {
+    git(repo, &["add", "-A"])?;
+    git(repo, &["commit", "-q", "-m", message])?;
+    git(repo, &["rev-parse", "HEAD"])
+}
+
+// ========== INDEXED COMMIT TESTS ==========
+
+#[tokio::test]
+async fn test_indexed_commit_survives_writes_and_reconnect() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+
+    let client = LanceDbClient::connect(db_path).await?;
+    assert_eq!(client.indexed_commit().await?, None);
+
+    client.set_indexed_commit("abc123").await?;
+    client.insert_embeddings(vec![record_for("src/lib.rs", "fn lib() {}")]).await?;
+    client.delete_embedding("src/lib.rs").await?;
+    assert_eq!(client.indexed_commit().await?, Some("abc123".to_string()));
+
+    let reopened = LanceDbClient::connect(db_path).await?;
+    assert_eq!(reopened.indexed_commit().await?, Some("abc123".to_string()));
+
+    Ok(())
+}
+
+// ========== INDEX_COMMIT TESTS ==========
+
+#[tokio::test]
+async fn test_index_commit_applies_renames_and_deletes_from_diff() -> Result<()> {
+    let repo_dir = TempDir::new()?;
+    let db_dir = TempDir::new()?;
+    let repo = repo_dir.path();
+    git(repo, &["init", "-q"])?;
+
+    fs::create_dir(repo.join("src"))?;
+    let moved = "pub fn moved() -> u32 { 42 }\n";
+    fs::write(repo.join("src/old_name.rs"), moved)?;
+    fs::write(repo.join("src/gone.rs"), "pub fn gone() {}\n")?;
+    let first = commit_all(repo, "first")?;
+
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.insert_embeddings(vec![
+        record_for("src/old_name.rs", moved),
+        record_for("src/gone.rs", "pub fn gone() {}\n"),
+    ]).await?;
+    client.set_indexed_commit(&first).await?;
+
+    git(repo, &["mv", "src/old_name.rs", "src/new_name.rs"])?;
+    git(repo, &["rm", "-q", "src/gone.rs"])?;
+    let second = commit_all(repo, "second")?;
+
+    // the working tree no longer matters: index from the object store
+    fs::remove_dir_all(repo.join("src"))?;
+
+    let stats = index_commit(&client, repo, "HEAD", &no_model_config()).await?;
+    assert_eq!(stats.commit, second);
+    assert_eq!(stats.previous_commit, Some(first));
+    assert_eq!(stats.files_renamed, 1);
+    assert_eq!(stats.files_deleted, 1);
+    assert_eq!(stats.indexed.files_indexed, 0);
+
+    let renamed = client.get_embedding("src/new_name.rs").await?.unwrap();
+    assert_eq!(renamed.hash, hash_content(moved));
+    assert!(client.get_embedding("src/old_name.rs").await?.is_none());
+    assert!(client.get_embedding("src/gone.rs").await?.is_none());
+    assert_eq!(client.indexed_commit().await?, Some(second.clone()));
+
+    // indexing the same commit again is a no-op
+    let again = index_commit(&client, repo, &second, &no_model_config()).await?;
+    assert_eq!(again.files_renamed + again.files_deleted + again.indexed.files_indexed, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_index_commit_resolves_tags_and_rejects_unknown_revisions() -> Result<()> {
+    let repo_dir = TempDir::new()?;
+    let db_dir = TempDir::new()?;
+    let repo = repo_dir.path();
+    git(repo, &["init", "-q"])?;
+
+    fs::write(repo.join("README.txt"), "not source\n")?;
+    let tagged = commit_all(repo, "release")?;
+    git(repo, &["tag", "-a", "v1.0", "-m", "v1.0"])?;
+
+    assert_eq!(resolve_commit(repo, "v1.0")?, tagged);
+
+    // nothing indexable at the tag, so the model is never needed
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    let stats = index_commit(&client, repo, "v1.0", &no_model_config()).await?;
+    assert_eq!(stats.commit, tagged);
+    assert_eq!(stats.previous_commit, None);
+    assert_eq!(client.indexed_commit().await?, Some(tagged));
+
+    assert!(index_commit(&client, repo, "no-such-branch", &no_model_config()).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_index_commit_deletes_rows_of_blobs_that_cannot_be_embedded() -> Result<()> {
+    let repo_dir = TempDir::new()?;
+    let db_dir = TempDir::new()?;
+    let repo = repo_dir.path();
+    git(repo, &["init", "-q"])?;
+
+    fs::write(repo.join("emptied.rs"), "pub fn emptied() {}\n")?;
+    fs::write(repo.join("binary.rs"), "pub fn binary() {}\n")?;
+    let first = commit_all(repo, "first")?;
+
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.insert_embeddings(vec![
+        record_for("emptied.rs", "pub fn emptied() {}\n"),
+        record_for("binary.rs", "pub fn binary() {}\n"),
+    ]).await?;
+    client.set_indexed_commit(&first).await?;
+
+    fs::write(repo.join("emptied.rs"), "\n")?;
+    fs::write(repo.join("binary.rs"), [0xff, 0xfe, 0x00, 0x81])?;
+    let second = commit_all(repo, "second")?;
+
+    // both blobs are read through cat-file but never reach the model
+    let stats = index_commit(&client, repo, "HEAD", &no_model_config()).await?;
+    assert_eq!(stats.indexed.files_indexed, 0);
+    assert_eq!(stats.indexed.files_skipped, 2);
+    assert!(client.list_hashes().await?.is_empty());
+    assert_eq!(client.indexed_commit().await?, Some(second));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_index_commit_retries_files_that_failed_to_embed() -> Result<()> {
+    let repo_dir = TempDir::new()?;
+    let db_dir = TempDir::new()?;
+    let repo = repo_dir.path();
+    git(repo, &["init", "-q"])?;
+
+    let config = IndexerConfig {
+        model_path: MODEL_PATH.to_string(),
+        tokenizer_path: picky_tokenizer(db_dir.path())?,
+        ..IndexerConfig::default()
+    };
+
+    fs::write(repo.join("ok.rs"), "pub fn ok() {}\n")?;
+    let first = commit_all(repo, "first")?;
+    let client = LanceDbClient::connect(db_dir.path().join("store").to_str().unwrap()).await?;
+    let stats = index_commit(&client, repo, "HEAD", &config).await?;
+    assert_eq!(stats.indexed.files_indexed, 1);
+    assert_eq!(client.indexed_commit().await?, Some(first.clone()));
+
+    fs::write(repo.join("unembeddable.rs"), "pub fn zzz_unknown() {}\n")?;
+    commit_all(repo, "second")?;
+
+    // the commit stays where it was, so the next run diffs from it again
+    for _ in 0..2 {
+        let stats = index_commit(&client, repo, "HEAD", &config).await?;
+        let failed: Vec<&str> = stats.indexed.failed.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(failed, vec!["unembeddable.rs"]);
+        assert_eq!(stats.previous_commit, Some(first.clone()));
+        assert_eq!(client.indexed_commit().await?, Some(first.clone()));
+    }
+    assert!(client.get_embedding("ok.rs").await?.is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_full_index_commit_keeps_rows_until_new_ones_are_written() -> Result<()> {
+    let repo_dir = TempDir::new()?;
+    let db_dir = TempDir::new()?;
+    let repo = repo_dir.path();
+    git(repo, &["init", "-q"])?;
+
+    fs::write(repo.join("lib.rs"), "pub fn lib() {}\n")?;
+    commit_all(repo, "first")?;
+
+    // rows from a working-tree index, so there is no commit to diff from
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.insert_embeddings(vec![
+        record_for("lib.rs", "pub fn lib() {}\n"),
+        record_for("scratch.rs", "fn scratch() {}"),
+    ]).await?;
+
+    // the model can't load, so the run fails and must not have emptied the store
+    assert!(index_commit(&client, repo, "HEAD", &no_model_config()).await.is_err());
+    assert_eq!(client.list_hashes().await?.len(), 2);
+    assert_eq!(client.indexed_commit().await?, None);
+
+    // with nothing to embed it succeeds, and rows missing from the tree go
+    fs::write(repo.join("lib.rs"), "\n")?;
+    let second = commit_all(repo, "second")?;
+    let stats = index_commit(&client, repo, "HEAD", &no_model_config()).await?;
+    assert_eq!(stats.files_deleted, 1);
+    assert!(client.list_hashes().await?.is_empty());
+    assert_eq!(client.indexed_commit().await?, Some(second));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_working_tree_index_clears_indexed_commit() -> Result<()> {
+    let source_dir = TempDir::new()?;
+    let db_dir = TempDir::new()?;
+
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.set_indexed_commit("abc123").await?;
+
+    // nothing to embed, so the missing model is never loaded
+    let stats = index_directory(&client, source_dir.path(), &no_model_config()).await?;
+    assert_eq!(stats.files_indexed, 0);
+    assert_eq!(client.indexed_commit().await?, None);
+
+    Ok(())
+}
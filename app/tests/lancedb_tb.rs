@@ -1,6 +1,6 @@
-use llama_pack::lancedb::{LanceDbClient, EmbeddingRecord};
+use llama_pack::lancedb::{LanceDbClient, BundleManifest, EmbeddingRecord, Mmr, Namespace, QueryOptions, RecencyBoost};
+use std::time::Duration;
 use anyhow::Result;
-use std::fs;
 use tempfile::TempDir;
 
 // Test helper to create sample embedding records
@@ -15,6 +15,12 @@ fn create_test_record(path: &str, embedding_dim: usize) -> EmbeddingRecord {
         line_count: 42,
         imported_by: vec!["main.rs".to_string(), "lib.rs".to_string()],
         content_preview: Some("fn main() { println!(\"hello\"); }".to_string()),
+        content: Some("fn main() { println!(\"hello\"); }".to_string()),
+        byte_size: 32,
+        token_count: 12,
+        symbols: vec!["main".to_string()],
+        is_test: false,
+        is_generated: false,
     }
 }
 
@@ -124,7 +130,7 @@ async fn test_various_languages() -> Result<()> {
     let client = LanceDbClient::connect(db_path).await?;
     
     let mut records = vec![];
-    let languages = vec!["rust", "python", "javascript", "go", "java"];
+    let languages = ["rust", "python", "javascript", "go", "java"];
     
     for (i, lang) in languages.iter().enumerate() {
         let mut record = create_test_record(&format!("src/file_{}.{}", i, lang), 768);
@@ -187,6 +193,12 @@ async fn test_insert_with_real_embeddings() -> Result<()> {
         line_count: 1,
         imported_by: vec![],
         content_preview: Some(code.to_string()),
+        content: Some(code.to_string()),
+        byte_size: code.len() as i64,
+        token_count: 0,
+        symbols: vec![],
+        is_test: false,
+        is_generated: false,
     };
     
     client.insert_embeddings(vec![record]).await?;
@@ -223,6 +235,12 @@ async fn test_get_existing_embedding() -> Result<()> {
     assert_eq!(retrieved_record.line_count, original_record.line_count);
     assert_eq!(retrieved_record.imported_by, original_record.imported_by);
     assert_eq!(retrieved_record.content_preview, original_record.content_preview);
+    assert_eq!(retrieved_record.content, original_record.content);
+    assert_eq!(retrieved_record.byte_size, original_record.byte_size);
+    assert_eq!(retrieved_record.token_count, original_record.token_count);
+    assert_eq!(retrieved_record.symbols, original_record.symbols);
+    assert_eq!(retrieved_record.is_test, original_record.is_test);
+    assert_eq!(retrieved_record.is_generated, original_record.is_generated);
     
     Ok(())
 }
@@ -346,7 +364,7 @@ async fn test_get_embedding_different_languages() -> Result<()> {
     let client = LanceDbClient::connect(db_path).await?;
     
     // Insert records with different languages
-    let languages = vec!["rust", "python", "javascript", "go", "java"];
+    let languages = ["rust", "python", "javascript", "go", "java"];
     let mut records = vec![];
     
     for (i, lang) in languages.iter().enumerate() {
@@ -731,6 +749,12 @@ async fn test_query_similar_integration_with_real_embeddings() -> Result<()> {
             line_count: 1,
             imported_by: vec![],
             content_preview: Some(code.to_string()),
+            content: Some(code.to_string()),
+            byte_size: code.len() as i64,
+            token_count: 0,
+            symbols: vec![],
+            is_test: false,
+            is_generated: false,
         };
         records.push(record);
     }
@@ -750,4 +774,786 @@ async fn test_query_similar_integration_with_real_embeddings() -> Result<()> {
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+// ========== DELETE_EMBEDDINGS TESTS ==========
+
+#[tokio::test]
+async fn test_delete_multiple_embeddings() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    
+    let records = vec![
+        create_test_record("src/main.rs", 768),
+        create_test_record("src/lib.rs", 768),
+        create_test_record("src/file's.rs", 768),
+    ];
+    client.insert_embeddings(records).await?;
+    
+    // Delete two of the three, including a path that needs escaping
+    client.delete_embeddings(&["src/main.rs".to_string(), "src/file's.rs".to_string()]).await?;
+    
+    assert!(client.get_embedding("src/main.rs").await?.is_none());
+    assert!(client.get_embedding("src/file's.rs").await?.is_none());
+    assert!(client.get_embedding("src/lib.rs").await?.is_some());
+    
+    // Empty slice is a no-op
+    client.delete_embeddings(&[]).await?;
+    assert!(client.get_embedding("src/lib.rs").await?.is_some());
+    
+    Ok(())
+}
+
+// ========== RENAME / LIST_HASHES TESTS ==========
+
+#[tokio::test]
+async fn test_rename_embedding_keeps_embedding() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    let mut record = create_test_record("src/old.rs", 768);
+    record.embedding = vec![0.7; 768];
+    client.insert_embeddings(vec![record.clone()]).await?;
+    
+    client.rename_embedding("src/old.rs", "src/new's.rs").await?;
+    
+    assert!(client.get_embedding("src/old.rs").await?.is_none());
+    let renamed = client.get_embedding("src/new's.rs").await?.unwrap();
+    assert_eq!(renamed.hash, record.hash);
+    assert_eq!(renamed.embedding, record.embedding);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_hashes() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    assert!(client.list_hashes().await?.is_empty());
+    
+    client.insert_embeddings(vec![
+        create_test_record("src/main.rs", 768),
+        create_test_record("src/lib.rs", 768),
+    ]).await?;
+    
+    let hashes = client.list_hashes().await?;
+    assert_eq!(hashes.len(), 2);
+    assert_eq!(hashes.get("src/main.rs"), Some(&"hash_src_main.rs".to_string()));
+    assert_eq!(hashes.get("src/lib.rs"), Some(&"hash_src_lib.rs".to_string()));
+    
+    Ok(())
+}
+
+// ========== LAST_ACCESSED / RECENCY TESTS ==========
+
+fn now_micros() -> i64 {
+    chrono::Utc::now().timestamp_micros()
+}
+
+#[tokio::test]
+async fn test_touch_embeddings_bumps_last_accessed() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    client.insert_embeddings(vec![
+        create_test_record("src/main.rs", 768),
+        create_test_record("src/lib.rs", 768),
+    ]).await?;
+    
+    let before = now_micros();
+    client.touch_embeddings(&["src/main.rs".to_string()]).await?;
+    
+    let touched = client.get_embedding("src/main.rs").await?.unwrap();
+    let untouched = client.get_embedding("src/lib.rs").await?.unwrap();
+    assert!(touched.last_accessed >= before);
+    assert_eq!(untouched.last_accessed, 1640995200000);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upsert_keeps_last_accessed() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    client.insert_embeddings(vec![create_test_record("src/main.rs", 768)]).await?;
+    
+    // re-indexed rows start unaccessed; the stored time must survive
+    let mut reindexed = create_test_record("src/main.rs", 768);
+    reindexed.hash = "reindexed".to_string();
+    reindexed.last_accessed = 0;
+    let mut added = create_test_record("src/new.rs", 768);
+    added.last_accessed = 0;
+    let version = client.version().await?;
+    client.upsert_embeddings(vec![reindexed.clone(), added]).await?;
+    // replaced and inserted in a single commit
+    assert_eq!(client.version().await?, version + 1);
+    
+    let stored = client.get_embedding("src/main.rs").await?.unwrap();
+    assert_eq!(stored.hash, "reindexed");
+    assert_eq!(stored.last_accessed, 1640995200000);
+    assert_eq!(client.get_embedding("src/new.rs").await?.unwrap().last_accessed, 0);
+    
+    client.update_embedding("src/main.rs", reindexed).await?;
+    assert_eq!(client.get_embedding("src/main.rs").await?.unwrap().last_accessed, 1640995200000);
+    assert_eq!(client.list_hashes().await?.len(), 2);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_similar_is_read_only() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    let mut near = create_test_record("src/near.rs", 768);
+    near.embedding = vec![0.5; 768];
+    let mut far = create_test_record("src/far.rs", 768);
+    far.embedding = vec![-0.5; 768];
+    client.insert_embeddings(vec![near, far]).await?;
+    
+    // lookups must not commit versions; only explicit touches do
+    let version = client.version().await?;
+    let options = QueryOptions { recency: Some(RecencyBoost::default()), ..QueryOptions::default() };
+    let results = client.query_similar_with(&[0.5; 768], 1, &options).await?;
+    assert_eq!(results[0].path, "src/near.rs");
+    assert_eq!(client.version().await?, version);
+    assert_eq!(client.get_embedding("src/near.rs").await?.unwrap().last_accessed, 1640995200000);
+    
+    let before = now_micros();
+    client.touch_embeddings(&[results[0].path.clone()]).await?;
+    assert!(client.get_embedding("src/near.rs").await?.unwrap().last_accessed >= before);
+    assert_eq!(client.get_embedding("src/far.rs").await?.unwrap().last_accessed, 1640995200000);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_similar_with_recency_boost() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    
+    // Slightly closer match, untouched since 2022
+    let mut stale = create_test_record("src/stale.rs", 768);
+    stale.embedding = vec![0.50; 768];
+    // Slightly further match, edited just now
+    let mut fresh = create_test_record("src/fresh.rs", 768);
+    fresh.embedding = vec![0.51; 768];
+    fresh.last_modified = now_micros();
+    client.insert_embeddings(vec![stale, fresh]).await?;
+    
+    let query = vec![0.50; 768];
+    
+    // Plain similarity prefers the closer, stale file
+    let plain = client.query_similar(&query, 2).await?;
+    assert_eq!(plain[0].path, "src/stale.rs");
+    
+    // Recency blend lifts the file being worked on
+    let options = QueryOptions {
+        recency: Some(RecencyBoost { weight: 0.5, half_life: Duration::from_secs(3600) }),
+        ..QueryOptions::default()
+    };
+    let boosted = client.query_similar_with(&query, 2, &options).await?;
+    assert_eq!(boosted.len(), 2);
+    assert_eq!(boosted[0].path, "src/fresh.rs");
+    
+    Ok(())
+}
+
+/// a mean-pooled-looking embedding: not normalised, norm around 15
+fn pooled_embedding(frequency: f32) -> Vec<f32> {
+    (0..768).map(|i| (i as f32 * frequency).sin() * 0.8).collect()
+}
+
+#[tokio::test]
+async fn test_recency_boost_keeps_close_match_on_realistic_vectors() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    let query = pooled_embedding(1.0);
+    
+    // Close to the query, but with an L2 distance of several units
+    let mut close = create_test_record("src/close.rs", 768);
+    close.embedding = query.iter().zip(pooled_embedding(5.3)).map(|(q, noise)| q + 0.3 * noise).collect();
+    // Unrelated, edited just now
+    let mut fresh = create_test_record("src/unrelated.rs", 768);
+    fresh.embedding = pooled_embedding(3.1);
+    fresh.last_modified = now_micros();
+    client.insert_embeddings(vec![close, fresh]).await?;
+    
+    let options = QueryOptions { recency: Some(RecencyBoost::default()), ..QueryOptions::default() };
+    let results = client.query_similar_with(&query, 2, &options).await?;
+    assert_eq!(results[0].path, "src/close.rs");
+    
+    Ok(())
+}
+
+// ========== CONTENT / MIGRATION TESTS ==========
+
+#[tokio::test]
+async fn test_full_content_round_trip() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    
+    let body = "/// Adds numbers.\npub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n".repeat(200);
+    let mut with_content = create_test_record("src/add.rs", 768);
+    with_content.content = Some(body.clone());
+    let mut without_content = create_test_record("src/none.rs", 768);
+    without_content.content = None;
+    client.insert_embeddings(vec![with_content, without_content]).await?;
+    
+    assert_eq!(client.get_embedding("src/add.rs").await?.unwrap().content, Some(body));
+    assert!(client.get_embedding("src/none.rs").await?.unwrap().content.is_none());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_connect_migrates_table_without_content_column() -> Result<()> {
+    use arrow_array::{
+        ArrayRef, FixedSizeListArray, Float32Array, Int16Array, ListArray, RecordBatch,
+        RecordBatchIterator, StringArray, TimestampMicrosecondArray,
+    };
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use std::sync::Arc;
+    
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    // Table as written before the content column existed
+    let item = Arc::new(Field::new("item", DataType::Float32, true));
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("path", DataType::Utf8, false),
+        Field::new("hash", DataType::Utf8, false),
+        Field::new("embedding", DataType::FixedSizeList(item.clone(), 768), false),
+        Field::new("language", DataType::Utf8, false),
+        Field::new("last_modified", DataType::Timestamp(TimeUnit::Microsecond, None), false),
+        Field::new("last_accessed", DataType::Timestamp(TimeUnit::Microsecond, None), false),
+        Field::new("line_count", DataType::Int16, false),
+        Field::new("imported_by", DataType::List(Arc::new(Field::new("item", DataType::Utf8, false))), true),
+        Field::new("content_preview", DataType::Utf8, true),
+    ]));
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(vec!["src/legacy.rs"])),
+        Arc::new(StringArray::from(vec!["legacy_hash"])),
+        Arc::new(FixedSizeListArray::new(item, 768, Arc::new(Float32Array::from(vec![0.2; 768])), None)),
+        Arc::new(StringArray::from(vec!["rust"])),
+        Arc::new(TimestampMicrosecondArray::from(vec![1640995200000])),
+        Arc::new(TimestampMicrosecondArray::from(vec![1640995200000])),
+        Arc::new(Int16Array::from(vec![7])),
+        Arc::new(ListArray::new_null(Arc::new(Field::new("item", DataType::Utf8, false)), 1)),
+        Arc::new(StringArray::from(vec![Some("fn legacy()")])),
+    ];
+    let batch = RecordBatch::try_new(schema.clone(), columns)?;
+    let db = lancedb::connect(db_path).execute().await?;
+    db.create_table("embeddings", Box::new(RecordBatchIterator::new(vec![Ok(batch)], schema)))
+        .execute()
+        .await?;
+    
+    // Connecting adds the new column; old rows read back with no content
+    let client = LanceDbClient::connect(db_path).await?;
+    let legacy = client.get_embedding("src/legacy.rs").await?.unwrap();
+    assert_eq!(legacy.hash, "legacy_hash");
+    assert_eq!(legacy.line_count, 7);
+    assert!(legacy.content.is_none());
+    assert_eq!(legacy.byte_size, 0);
+    assert!(legacy.symbols.is_empty());
+    assert!(!legacy.is_test);
+    
+    // line_count was widened, so huge files fit after migration
+    let mut huge = create_test_record("src/huge.sql", 768);
+    huge.line_count = 250_000;
+    client.insert_embeddings(vec![huge]).await?;
+    let huge = client.get_embedding("src/huge.sql").await?.unwrap();
+    assert_eq!(huge.line_count, 250_000);
+    assert!(huge.content.is_some());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_table_with_reordered_columns_reads_and_writes() -> Result<()> {
+    use arrow_array::RecordBatchIterator;
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use std::sync::Arc;
+    
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    // Same columns as an old table, in another order
+    let item = Arc::new(Field::new("item", DataType::Float32, true));
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("content_preview", DataType::Utf8, true),
+        Field::new("line_count", DataType::Int32, false),
+        Field::new("hash", DataType::Utf8, false),
+        Field::new("imported_by", DataType::List(Arc::new(Field::new("item", DataType::Utf8, false))), true),
+        Field::new("last_accessed", DataType::Timestamp(TimeUnit::Microsecond, None), false),
+        Field::new("embedding", DataType::FixedSizeList(item, 768), false),
+        Field::new("language", DataType::Utf8, false),
+        Field::new("last_modified", DataType::Timestamp(TimeUnit::Microsecond, None), false),
+        Field::new("path", DataType::Utf8, false),
+    ]));
+    let db = lancedb::connect(db_path).execute().await?;
+    db.create_table("embeddings", Box::new(RecordBatchIterator::new(std::iter::empty(), schema)))
+        .execute()
+        .await?;
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    client.insert_embeddings(vec![create_test_record("src/main.rs", 768)]).await?;
+    
+    let record = client.get_embedding("src/main.rs").await?.unwrap();
+    assert_eq!(record.hash, "hash_src_main.rs");
+    assert_eq!(record.line_count, 42);
+    assert_eq!(record.imported_by, vec!["main.rs", "lib.rs"]);
+    assert_eq!(record.symbols, vec!["main"]);
+    assert_eq!(client.query_similar(&[0.1; 768], 1).await?[0].path, "src/main.rs");
+    
+    Ok(())
+}
+
+// ========== METADATA TESTS ==========
+
+#[tokio::test]
+async fn test_large_line_count_and_metadata_round_trip() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    let mut record = create_test_record("gen/bindings.rs", 768);
+    record.line_count = 40_000; // past the old Int16 limit
+    record.byte_size = 5_000_000_000;
+    record.token_count = 1_200_000;
+    record.symbols = vec!["Client".to_string(), "connect".to_string()];
+    record.is_generated = true;
+    client.insert_embeddings(vec![record.clone()]).await?;
+    
+    let retrieved = client.get_embedding("gen/bindings.rs").await?.unwrap();
+    assert_eq!(retrieved.line_count, 40_000);
+    assert_eq!(retrieved.byte_size, 5_000_000_000);
+    assert_eq!(retrieved.token_count, 1_200_000);
+    assert_eq!(retrieved.symbols, record.symbols);
+    assert!(retrieved.is_generated);
+    assert!(!retrieved.is_test);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_similar_excludes_tests_and_generated() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    let source = create_test_record("src/client.rs", 768);
+    let mut test = create_test_record("tests/client_tb.rs", 768);
+    test.is_test = true;
+    let mut generated = create_test_record("src/gen/client.pb.rs", 768);
+    generated.is_generated = true;
+    client.insert_embeddings(vec![source, test, generated]).await?;
+    
+    let query = vec![0.1; 768];
+    assert_eq!(client.query_similar(&query, 10).await?.len(), 3);
+    
+    let options = QueryOptions { exclude_tests: true, ..QueryOptions::default() };
+    let results = client.query_similar_with(&query, 10, &options).await?;
+    assert_eq!(results.len(), 2);
+    assert!(!results.iter().any(|r| r.is_test));
+    
+    let options = QueryOptions { exclude_tests: true, exclude_generated: true, ..QueryOptions::default() };
+    let results = client.query_similar_with(&query, 10, &options).await?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "src/client.rs");
+    
+    Ok(())
+}
+
+// ========== NAMESPACE TESTS ==========
+
+#[test]
+fn test_namespace_table_name_round_trip() -> Result<()> {
+    let namespace = Namespace::parse("org/billing_service@feature/retry-2")?;
+    assert_eq!(namespace.repo, "org/billing_service");
+    assert_eq!(namespace.branch, "feature/retry-2");
+    
+    let table_name = namespace.table_name();
+    assert!(table_name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')));
+    assert_eq!(Namespace::from_table_name(&table_name), Some(namespace));
+    assert_eq!(Namespace::from_table_name("embeddings"), None);
+    
+    assert!(Namespace::parse("billing").is_err());
+    assert!(Namespace::parse("billing@").is_err());
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_namespaces_do_not_collide() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let main = LanceDbClient::connect_namespace(db_path, &Namespace::new("billing", "main")).await?;
+    let feature = LanceDbClient::connect_namespace(db_path, &Namespace::new("billing", "feature")).await?;
+    let default = LanceDbClient::connect(db_path).await?;
+    
+    let mut on_main = create_test_record("src/lib.rs", 768);
+    on_main.hash = "main_hash".to_string();
+    let mut on_feature = create_test_record("src/lib.rs", 768);
+    on_feature.hash = "feature_hash".to_string();
+    main.insert_embeddings(vec![on_main]).await?;
+    feature.insert_embeddings(vec![on_feature]).await?;
+    
+    assert_eq!(main.get_embedding("src/lib.rs").await?.unwrap().hash, "main_hash");
+    assert_eq!(feature.get_embedding("src/lib.rs").await?.unwrap().hash, "feature_hash");
+    assert!(default.get_embedding("src/lib.rs").await?.is_none());
+    
+    feature.delete_embedding("src/lib.rs").await?;
+    assert!(main.get_embedding("src/lib.rs").await?.is_some());
+    
+    assert_eq!(main.namespace(), Some(&Namespace::new("billing", "main")));
+    assert_eq!(default.namespace(), None);
+    assert_eq!(
+        default.list_namespaces().await?,
+        vec![Namespace::new("billing", "feature"), Namespace::new("billing", "main")]
+    );
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_namespaces_merges_by_distance() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let billing = Namespace::new("billing", "main");
+    let payments = Namespace::new("payments", "main");
+    let billing_client = LanceDbClient::connect_namespace(db_path, &billing).await?;
+    let payments_client = LanceDbClient::connect_namespace(db_path, &payments).await?;
+    
+    let mut retry = create_test_record("src/retry.rs", 768);
+    retry.embedding = vec![0.5; 768];
+    let mut far = create_test_record("src/far.rs", 768);
+    far.embedding = vec![-0.5; 768];
+    billing_client.insert_embeddings(vec![retry, far]).await?;
+    
+    let mut backoff = create_test_record("src/backoff.rs", 768);
+    backoff.embedding = vec![0.4; 768];
+    payments_client.insert_embeddings(vec![backoff]).await?;
+    
+    let query = vec![0.5; 768];
+    let namespaces = billing_client.list_namespaces().await?;
+    let results = billing_client.query_namespaces(&namespaces, &query, 2, &QueryOptions::default()).await?;
+    
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].namespace, billing);
+    assert_eq!(results[0].record.path, "src/retry.rs");
+    assert_eq!(results[1].namespace, payments);
+    assert_eq!(results[1].record.path, "src/backoff.rs");
+    assert!(results[0].distance <= results[1].distance);
+    
+    // unknown namespaces are an error rather than silently empty
+    let missing = [Namespace::new("unknown", "main")];
+    assert!(billing_client.query_namespaces(&missing, &query, 2, &QueryOptions::default()).await.is_err());
+    
+    Ok(())
+}
+
+// ========== EXPORT / IMPORT TESTS ==========
+
+#[tokio::test]
+async fn test_export_import_round_trip() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let ci_path = temp_dir.path().join("ci");
+    let laptop_path = temp_dir.path().join("laptop");
+    let bundle = temp_dir.path().join("bundle");
+    
+    let ci = LanceDbClient::connect(ci_path.to_str().unwrap()).await?;
+    let mut big = create_test_record("src/big.rs", 768);
+    big.line_count = 50_000;
+    big.symbols = vec!["a".to_string(), "b".to_string()];
+    ci.insert_embeddings(vec![create_test_record("src/main.rs", 768), big]).await?;
+    ci.set_indexed_commit("0123abcd").await?;
+    
+    let exported = ci.export(&bundle, "unixcoder-embedding").await?;
+    assert_eq!(exported.row_count, 2);
+    assert_eq!(exported.commit, Some("0123abcd".to_string()));
+    assert_eq!(BundleManifest::read(&bundle)?, exported);
+    
+    let laptop = LanceDbClient::connect(laptop_path.to_str().unwrap()).await?;
+    laptop.insert_embeddings(vec![create_test_record("src/stale.rs", 768)]).await?;
+    let imported = laptop.import(&bundle, "unixcoder-embedding").await?;
+    assert_eq!(imported.row_count, 2);
+    
+    assert!(laptop.get_embedding("src/stale.rs").await?.is_none());
+    let retrieved = laptop.get_embedding("src/big.rs").await?.unwrap();
+    assert_eq!(retrieved.line_count, 50_000);
+    assert_eq!(retrieved.symbols, vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(laptop.list_hashes().await?.len(), 2);
+    assert_eq!(laptop.indexed_commit().await?, Some("0123abcd".to_string()));
+    
+    // still writable after the overwrite
+    laptop.insert_embeddings(vec![create_test_record("src/new.rs", 768)]).await?;
+    assert_eq!(laptop.list_hashes().await?.len(), 3);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_import_rejects_incompatible_bundles() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let bundle = temp_dir.path().join("bundle");
+    
+    let source = LanceDbClient::connect(temp_dir.path().join("source").to_str().unwrap()).await?;
+    source.insert_embeddings(vec![create_test_record("src/main.rs", 768)]).await?;
+    let manifest = source.export(&bundle, "unixcoder-embedding").await?;
+    
+    let target = LanceDbClient::connect(temp_dir.path().join("target").to_str().unwrap()).await?;
+    target.insert_embeddings(vec![create_test_record("src/keep.rs", 768)]).await?;
+    
+    let err = target.import(&bundle, "other-model").await.unwrap_err();
+    assert!(err.to_string().contains("other-model"));
+    
+    BundleManifest { dimension: 384, ..manifest.clone() }.write(&bundle)?;
+    assert!(target.import(&bundle, "unixcoder-embedding").await.is_err());
+    
+    BundleManifest { schema_version: manifest.schema_version + 1, ..manifest.clone() }.write(&bundle)?;
+    assert!(target.import(&bundle, "unixcoder-embedding").await.is_err());
+    
+    BundleManifest { row_count: 5, ..manifest.clone() }.write(&bundle)?;
+    assert!(target.import(&bundle, "unixcoder-embedding").await.is_err());
+    
+    // nothing was replaced by the failed imports
+    assert!(target.get_embedding("src/keep.rs").await?.is_some());
+    assert!(target.get_embedding("src/main.rs").await?.is_none());
+    
+    Ok(())
+}
+
+// ========== MAINTENANCE TESTS ==========
+
+#[tokio::test]
+async fn test_stats_reports_rows_fragments_and_size() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    let empty = client.stats().await?;
+    assert_eq!(empty.row_count, 0);
+    assert!(empty.indices.is_empty());
+    
+    client.insert_embeddings(vec![create_test_record("src/a.rs", 768)]).await?;
+    client.insert_embeddings(vec![create_test_record("src/b.rs", 768)]).await?;
+    
+    let stats = client.stats().await?;
+    assert_eq!(stats.row_count, 2);
+    assert_eq!(stats.fragment_count, 2);
+    assert!(stats.version > empty.version);
+    assert!(stats.version_count >= 3);
+    assert!(stats.data_bytes > 0);
+    assert!(stats.disk_bytes.unwrap() > 0);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_optimize_compacts_and_prunes_versions() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    for i in 0..5 {
+        client.insert_embeddings(vec![create_test_record(&format!("src/file_{}.rs", i), 768)]).await?;
+    }
+    client.delete_embedding("src/file_0.rs").await?;
+    let before = client.stats().await?;
+    
+    // recent versions survive a generous cutoff
+    let kept = client.optimize(Duration::from_secs(60 * 60 * 24 * 7)).await?;
+    assert!(kept.fragments_removed > 0);
+    assert_eq!(kept.versions_removed, 0);
+    
+    let report = client.optimize(Duration::ZERO).await?;
+    assert!(report.versions_removed > 0);
+    
+    let after = client.stats().await?;
+    assert_eq!(after.row_count, 4);
+    assert_eq!(after.fragment_count, 1);
+    assert!(after.version_count < before.version_count);
+    assert!(after.disk_bytes.unwrap() < before.disk_bytes.unwrap());
+    assert!(client.get_embedding("src/file_4.rs").await?.is_some());
+    
+    Ok(())
+}
+
+// ========== VERSION TESTS ==========
+
+#[tokio::test]
+async fn test_checkout_version_reads_old_contents() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    client.insert_embeddings(vec![create_test_record("src/before.rs", 768)]).await?;
+    let before_refactor = client.version().await?;
+    
+    client.delete_embedding("src/before.rs").await?;
+    client.insert_embeddings(vec![create_test_record("src/after.rs", 768)]).await?;
+    
+    let versions = client.list_versions().await?;
+    assert!(versions.iter().any(|v| v.version == before_refactor));
+    assert!(versions.windows(2).all(|w| w[0].version < w[1].version));
+    
+    let old = client.checkout_version(before_refactor).await?;
+    assert_eq!(old.version().await?, before_refactor);
+    let query = vec![0.1; 768];
+    let old_results = old.query_similar(&query, 10).await?;
+    assert_eq!(old_results.len(), 1);
+    assert_eq!(old_results[0].path, "src/before.rs");
+    
+    // old versions are read-only; the original client still sees the latest
+    assert!(old.insert_embeddings(vec![create_test_record("src/x.rs", 768)]).await.is_err());
+    assert_eq!(client.query_similar(&query, 10).await?[0].path, "src/after.rs");
+    
+    old.checkout_latest().await?;
+    assert_eq!(old.version().await?, client.version().await?);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_restore_version_rolls_back_bad_reindex() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    client.insert_embeddings(vec![
+        create_test_record("src/a.rs", 768),
+        create_test_record("src/b.rs", 768),
+    ]).await?;
+    let good = client.version().await?;
+    
+    // a botched re-index wipes the table
+    client.delete_embeddings(&["src/a.rs".to_string(), "src/b.rs".to_string()]).await?;
+    assert!(client.list_hashes().await?.is_empty());
+    
+    client.restore_version(good).await?;
+    assert!(client.version().await? > good);
+    assert_eq!(client.list_hashes().await?.len(), 2);
+    
+    // writable again after the rollback
+    client.insert_embeddings(vec![create_test_record("src/c.rs", 768)]).await?;
+    assert_eq!(client.list_hashes().await?.len(), 3);
+    
+    // a failed rollback leaves the client on the latest version and writable
+    assert!(client.restore_version(9_999).await.is_err());
+    client.insert_embeddings(vec![create_test_record("src/d.rs", 768)]).await?;
+    assert_eq!(client.list_hashes().await?.len(), 4);
+    
+    Ok(())
+}
+
+// ========== DIVERSITY TESTS ==========
+
+fn unit_embedding(x: f32, y: f32) -> Vec<f32> {
+    let mut embedding = vec![0.0; 768];
+    embedding[0] = x;
+    embedding[1] = y;
+    embedding
+}
+
+#[tokio::test]
+async fn test_mmr_promotes_distinct_results() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    let mut records = Vec::new();
+    for i in 0..3 {
+        let mut duplicate = create_test_record(&format!("proto/gen_{}.rs", i), 768);
+        duplicate.embedding = unit_embedding(1.0, 0.1);
+        records.push(duplicate);
+    }
+    let mut distinct = create_test_record("src/client.rs", 768);
+    distinct.embedding = unit_embedding(0.7, -0.7);
+    records.push(distinct);
+    client.insert_embeddings(records).await?;
+    
+    let query = unit_embedding(1.0, 0.0);
+    let plain = client.query_similar(&query, 2).await?;
+    assert!(plain.iter().all(|r| r.path.starts_with("proto/")));
+    
+    let options = QueryOptions { mmr: Some(Mmr { lambda: 0.3 }), ..QueryOptions::default() };
+    let diverse = client.query_similar_with(&query, 2, &options).await?;
+    assert_eq!(diverse.len(), 2);
+    assert!(diverse[0].path.starts_with("proto/"));
+    assert_eq!(diverse[1].path, "src/client.rs");
+    
+    // lambda 1.0 is plain relevance
+    let options = QueryOptions { mmr: Some(Mmr { lambda: 1.0 }), ..QueryOptions::default() };
+    let relevant = client.query_similar_with(&query, 2, &options).await?;
+    assert!(relevant.iter().all(|r| r.path.starts_with("proto/")));
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mmr_full_lambda_keeps_relevance_order_on_realistic_vectors() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    let query = pooled_embedding(1.0);
+    let noisy = |amount: f32, frequency: f32| -> Vec<f32> {
+        query.iter().zip(pooled_embedding(frequency)).map(|(q, noise)| q + amount * noise).collect()
+    };
+    
+    let mut best = create_test_record("src/best.rs", 768);
+    best.embedding = noisy(0.3, 5.3);
+    // near-duplicate of the best match, slightly less relevant
+    let mut duplicate = create_test_record("src/duplicate.rs", 768);
+    duplicate.embedding = best.embedding.iter().zip(pooled_embedding(7.7)).map(|(b, noise)| b + 0.15 * noise).collect();
+    let mut other = create_test_record("src/other.rs", 768);
+    other.embedding = noisy(2.0, 3.1);
+    client.insert_embeddings(vec![other, duplicate, best]).await?;
+    
+    let options = QueryOptions { mmr: Some(Mmr { lambda: 1.0 }), ..QueryOptions::default() };
+    let results = client.query_similar_with(&query, 3, &options).await?;
+    let paths: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+    assert_eq!(paths, vec!["src/best.rs", "src/duplicate.rs", "src/other.rs"]);
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_per_directory_caps_hits() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let db_path = temp_dir.path().to_str().unwrap();
+    
+    let client = LanceDbClient::connect(db_path).await?;
+    let mut records = Vec::new();
+    for (i, path) in ["proto/a.rs", "proto/b.rs", "proto/c.rs", "src/d.rs", "main.rs"].iter().enumerate() {
+        let mut record = create_test_record(path, 768);
+        record.embedding = unit_embedding(1.0, i as f32 * 0.1);
+        records.push(record);
+    }
+    client.insert_embeddings(records).await?;
+    
+    let query = unit_embedding(1.0, 0.0);
+    let options = QueryOptions { max_per_directory: Some(1), ..QueryOptions::default() };
+    let results = client.query_similar_with(&query, 3, &options).await?;
+    let paths: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+    assert_eq!(paths, vec!["proto/a.rs", "src/d.rs", "main.rs"]);
+    
+    Ok(())
+}
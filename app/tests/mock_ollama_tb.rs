@@ -0,0 +1,221 @@
+use futures::StreamExt;
+use llama_pack::backend::LlmBackend;
+use llama_pack::ollama_client::{CancellationToken, ChatMessage, GenerateOptions, OllamaClient};
+use llama_pack::Error;
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+mod support;
+
+use support::mock_ollama::embedding;
+use support::{MockOllama, Reply};
+
+// ========== DEFAULT BEHAVIOUR TESTS ==========
+
+#[tokio::test]
+async fn test_mock_echoes_prompts_for_installed_models() -> Result<()> {
+    let mock = MockOllama::start()?.with_models(&["codellama:7b", "llama3:8b"]);
+    let client = OllamaClient::with_base_url(mock.url());
+
+    let names: Vec<String> = client.list_models().await?.into_iter().map(|model| model.name).collect();
+    assert_eq!(names, vec!["codellama:7b", "llama3:8b"]);
+    assert_eq!(client.query_model("codellama:7b", "hello").await?, "You said: hello");
+
+    let history = [ChatMessage::user("first"), ChatMessage::assistant("ok"), ChatMessage::user("second")];
+    assert_eq!(client.chat("llama3:8b", &history).await?, ChatMessage::assistant("You said: second"));
+    assert_eq!(mock.requests("/api/chat")[0]["messages"].as_array().map(Vec::len), Some(3));
+
+    let err = client.query_model("ghost", "hi").await.unwrap_err();
+    assert!(matches!(&err, Error::ModelNotFound(model) if model == "ghost"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_embeds_and_pulls() -> Result<()> {
+    let mock = MockOllama::start()?.with_models(&["nomic-embed-text:latest"]).with_embedding_dim(8);
+    let client = OllamaClient::with_base_url(mock.url());
+
+    let inputs = vec!["fn main() {}".to_string(), "struct Foo;".to_string()];
+    let vectors = client.embed("nomic-embed-text", &inputs).await?;
+    assert_eq!(vectors, vec![embedding("fn main() {}", 8), embedding("struct Foo;", 8)]);
+    assert_eq!(client.embed("nomic-embed-text", &inputs[..1]).await?[0], vectors[0]);
+
+    let mut statuses = Vec::new();
+    client.pull_with("qwen2.5-coder", |progress| statuses.push(progress.status.clone())).await?;
+    assert_eq!(statuses.first().map(String::as_str), Some("pulling manifest"));
+    assert_eq!(statuses.last().map(String::as_str), Some("success"));
+    assert!(mock.models().contains(&"qwen2.5-coder:latest".to_string()));
+    assert_eq!(client.query_model("qwen2.5-coder", "hi").await?, "You said: hi");
+
+    Ok(())
+}
+
+// ========== SCRIPTED STREAM TESTS ==========
+
+#[tokio::test]
+async fn test_scripted_stream_arrives_chunk_by_chunk() -> Result<()> {
+    let mock = MockOllama::start()?.with_chunk_delay(Duration::from_millis(20));
+    mock.enqueue("/api/generate", Reply::text(&["fn ", "main", "() {}"]));
+    let client = OllamaClient::with_base_url(mock.url());
+
+    let mut stream = client.generate_stream("llama3:8b", "write main", &GenerateOptions::default()).await?;
+    let mut chunks = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        chunks.push(chunk?);
+    }
+    let text: String = chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+    assert_eq!(text, "fn main() {}");
+    assert_eq!(chunks.len(), 4);
+    assert_eq!(chunks[3].stats.as_ref().map(|stats| stats.eval_count), Some(3));
+
+    // the queue is used up, so the default echo comes back
+    assert_eq!(client.query_model("llama3:8b", "again").await?, "You said: again");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_backend_trait_runs_against_mock() -> Result<()> {
+    let mock = MockOllama::start()?;
+    mock.enqueue("/api/chat", Reply::text(&["Use ", "a trait."]));
+    let backend: Box<dyn LlmBackend> = Box::new(OllamaClient::with_base_url(mock.url()));
+
+    let mut pieces = Vec::new();
+    let messages = [ChatMessage::user("How do I abstract over backends?")];
+    let reply = backend
+        .reply("llama3:8b", &messages, &GenerateOptions::default(), &mut |chunk| {
+            pieces.push(chunk.message.content.clone())
+        })
+        .await?;
+    assert_eq!(reply.content, "Use a trait.");
+    assert_eq!(pieces, vec!["Use ", "a trait.", ""]);
+
+    Ok(())
+}
+
+// ========== ERROR INJECTION TESTS ==========
+
+#[tokio::test]
+async fn test_injected_failures_surface_as_typed_errors() -> Result<()> {
+    let mock = MockOllama::start()?;
+    mock.enqueue("/api/generate", Reply::Status(500, "CUDA out of memory".to_string()))
+        .enqueue("/api/generate", Reply::ErrorAfter(vec!["partial".to_string()], "runner crashed".to_string()))
+        .enqueue("/api/generate", Reply::DropAfter(vec!["cut".to_string()]));
+    let client = OllamaClient::with_base_url(mock.url());
+    let options = GenerateOptions::default();
+
+    let err = client.query_model("llama3:8b", "hi").await.unwrap_err();
+    assert!(matches!(&err, Error::Api { status: 500, message } if message == "CUDA out of memory"));
+
+    let mut stream = client.generate_stream("llama3:8b", "hi", &options).await?;
+    assert_eq!(stream.next().await.transpose()?.map(|chunk| chunk.text), Some("partial".to_string()));
+    let err = stream.next().await.transpose().unwrap_err();
+    assert!(matches!(&err, Error::Api { message, .. } if message == "runner crashed"));
+    assert!(stream.next().await.is_none());
+
+    let mut stream = client.generate_stream("llama3:8b", "hi", &options).await?;
+    assert_eq!(stream.next().await.transpose()?.map(|chunk| chunk.text), Some("cut".to_string()));
+    let err = stream.next().await.transpose().unwrap_err();
+    assert_eq!(err.code(), "ollama.http_error");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_latency_trips_read_timeout() -> Result<()> {
+    let mock = MockOllama::start()?.with_latency(Duration::from_millis(500));
+    let impatient = OllamaClient::builder()
+        .base_url(mock.url())
+        .read_timeout(Duration::from_millis(100))
+        .build()?;
+
+    let started = Instant::now();
+    let err = impatient.list_models().await.unwrap_err();
+    assert_eq!(err.code(), "ollama.http_error");
+    assert!(started.elapsed() < Duration::from_millis(500));
+
+    let patient = OllamaClient::with_base_url(mock.url());
+    assert_eq!(patient.list_models().await?.len(), 1);
+
+    Ok(())
+}
+
+// ========== CANCELLATION TESTS ==========
+
+fn cancel_after(token: &CancellationToken, delay: Duration) {
+    let token = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        token.cancel();
+    });
+}
+
+#[tokio::test]
+async fn test_cancel_stops_stream_and_closes_connection() -> Result<()> {
+    let mock = MockOllama::start()?.with_chunk_delay(Duration::from_millis(50));
+    let pieces: Vec<String> = (0..40).map(|i| format!("{} ", i)).collect();
+    mock.enqueue("/api/generate", Reply::Text(pieces));
+    let client = OllamaClient::with_base_url(mock.url());
+
+    let token = CancellationToken::new();
+    let options = GenerateOptions { cancel: Some(token.clone()), ..Default::default() };
+    let started = Instant::now();
+    let mut stream = client.generate_stream("llama3:8b", "count", &options).await?;
+    cancel_after(&token, Duration::from_millis(300));
+
+    let mut received = 0;
+    let err = loop {
+        match stream.next().await {
+            Some(Ok(_)) => received += 1,
+            Some(Err(e)) => break e,
+            None => panic!("stream ended without being cancelled"),
+        }
+    };
+    assert!(matches!(err, Error::Cancelled));
+    assert_eq!(err.code(), "ollama.cancelled");
+    assert!(received > 0 && received < 40, "received {}", received);
+    assert!(stream.next().await.is_none());
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    // the server notices the hang-up on its next write
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while mock.disconnects() == 0 && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(mock.disconnects(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancel_before_first_byte_and_partial_replies() -> Result<()> {
+    let slow = MockOllama::start()?.with_latency(Duration::from_secs(2));
+    let client = OllamaClient::with_base_url(slow.url());
+    let token = CancellationToken::new();
+    let options = GenerateOptions { cancel: Some(token.clone()), ..Default::default() };
+    cancel_after(&token, Duration::from_millis(50));
+
+    let started = Instant::now();
+    let err = client.chat_stream("llama3:8b", &[ChatMessage::user("hi")], &options).await.err().unwrap();
+    assert!(matches!(err, Error::Cancelled));
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    // a backend reply cancelled mid-stream fails, but the chunks that arrived were seen
+    let mock = MockOllama::start()?.with_chunk_delay(Duration::from_millis(50));
+    mock.enqueue("/api/chat", Reply::text(&["partial ", "answer ", "never ", "finished"]));
+    let backend: Box<dyn LlmBackend> = Box::new(OllamaClient::with_base_url(mock.url()));
+    let token = CancellationToken::new();
+    let options = GenerateOptions { cancel: Some(token.clone()), ..Default::default() };
+    cancel_after(&token, Duration::from_millis(130));
+
+    let mut partial = String::new();
+    let messages = [ChatMessage::user("hi")];
+    let result = backend.reply("llama3:8b", &messages, &options, &mut |chunk| partial.push_str(&chunk.message.content)).await;
+    assert!(matches!(result, Err(Error::Cancelled)));
+    assert!(partial.starts_with("partial "), "{:?}", partial);
+    assert!(!partial.contains("finished"));
+    assert!(token.is_cancelled());
+
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use llama_pack::ollama_client::prompts::DEFAULT_SYSTEM_PROMPT;
+use llama_pack::ollama_client::{ChatMessage, Modelfile, Role, SystemPrompts};
+use anyhow::Result;
+use serde_json::json;
+use std::fs;
+use tempfile::TempDir;
+
+// ========== PARSE TESTS ==========
+
+#[test]
+fn test_parse_all_instructions() -> Result<()> {
+    let text = r#"
+# team coding model
+FROM codellama:7b
+adapter ./lora.gguf
+PARAMETER temperature 0.2
+PARAMETER stop "<|end|>"
+PARAMETER stop "User:"
+TEMPLATE """{{ .System }}
+{{ .Prompt }}"""
+SYSTEM You are terse.
+LICENSE """MIT
+see LICENSE"""
+MESSAGE user "Is Rust safe?"
+MESSAGE assistant """Mostly."""
+"#;
+    let modelfile: Modelfile = text.parse()?;
+
+    assert_eq!(modelfile.from, "codellama:7b");
+    assert_eq!(modelfile.adapters, vec!["./lora.gguf"]);
+    assert_eq!(
+        modelfile.parameters,
+        vec![
+            ("temperature".to_string(), "0.2".to_string()),
+            ("stop".to_string(), "<|end|>".to_string()),
+            ("stop".to_string(), "User:".to_string()),
+        ]
+    );
+    assert_eq!(modelfile.template.as_deref(), Some("{{ .System }}\n{{ .Prompt }}"));
+    assert_eq!(modelfile.system.as_deref(), Some("You are terse."));
+    assert_eq!(modelfile.license, vec!["MIT\nsee LICENSE"]);
+    assert_eq!(
+        modelfile.messages,
+        vec![ChatMessage::user("Is Rust safe?"), ChatMessage::assistant("Mostly.")]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_errors() {
+    for (text, expected) in [
+        ("SYSTEM hi", "missing FROM"),
+        ("FROM x\nSYSTEM \"\"\"never closed", "unterminated"),
+        ("FROM x\nMESSAGE robot hi", "unknown MESSAGE role 'robot'"),
+        ("FROM x\nQUANTIZE q4", "unknown instruction 'QUANTIZE'"),
+    ] {
+        let err = Modelfile::parse(text).unwrap_err();
+        assert_eq!(err.code(), "invalid_input");
+        assert!(err.to_string().contains(expected), "{}: {}", text, err);
+    }
+}
+
+// ========== BUILD TESTS ==========
+
+#[test]
+fn test_builder_round_trips_through_text() -> Result<()> {
+    let modelfile = Modelfile::from("qwen2.5-coder:7b")
+        .system("Answer in \"plain\" English.\nCite files.")
+        .template("{{ .Prompt }}")
+        .parameter("num_ctx", 8192)
+        .parameter("stop", "</s>")
+        .adapter("adapters/team lora.gguf")
+        .license("Apache-2.0")
+        .message(Role::User, "hello")
+        .message(Role::Assistant, "Hi! What are we building?");
+
+    let text = modelfile.render()?;
+    assert!(text.starts_with("FROM qwen2.5-coder:7b\n"));
+    assert!(text.contains("ADAPTER \"adapters/team lora.gguf\"\n"));
+    assert!(text.contains("SYSTEM \"\"\"Answer in \"plain\" English.\nCite files.\"\"\"\n"));
+    assert_eq!(Modelfile::parse(&text)?, modelfile);
+
+    Ok(())
+}
+
+#[test]
+fn test_render_quotes_or_rejects_awkward_values() -> Result<()> {
+    // single-line quotes are escaped, so even a trailing quote round-trips
+    let modelfile = Modelfile::from("llama3")
+        .system("Say \"done\"")
+        .message(Role::User, "a \\\" b")
+        .parameter("stop", "\"");
+    let text = modelfile.render()?;
+    assert!(text.contains("SYSTEM \"Say \\\"done\\\"\"\n"));
+    assert_eq!(Modelfile::parse(&text)?, modelfile);
+
+    // nothing inside """ can be escaped
+    for value in ["Use \"\"\"docstrings\"\"\"\nalways.", "First line.\nThen say \"bye\""] {
+        let err = Modelfile::from("llama3").template(value).render().unwrap_err();
+        assert_eq!(err.code(), "invalid_input");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_to_create_model_types_parameters() -> Result<()> {
+    let modelfile = Modelfile::parse("FROM llama3\nPARAMETER temperature 0.7\nPARAMETER stop a\nPARAMETER stop b\nPARAMETER mirostat_tau 5\nSYSTEM hi")?;
+    let request = modelfile.to_create_model("team-llama");
+
+    assert_eq!(request.model, "team-llama");
+    assert_eq!(request.from.as_deref(), Some("llama3"));
+    assert_eq!(
+        serde_json::to_value(&request.parameters)?,
+        json!({ "temperature": 0.7, "stop": ["a", "b"], "mirostat_tau": 5 })
+    );
+    assert!(serde_json::to_value(&request)?.get("adapters").is_none());
+
+    Ok(())
+}
+
+// ========== SYSTEM PROMPT TESTS ==========
+
+#[test]
+fn test_system_prompts_from_config() -> Result<()> {
+    assert_eq!(SystemPrompts::default().for_model("anything"), DEFAULT_SYSTEM_PROMPT);
+
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("prompts.json");
+    fs::write(&path, r#"{ "models": { "codellama": "Follow the team style guide.", "codellama:13b": "Big model." } }"#)?;
+    let prompts = SystemPrompts::load(&path)?;
+
+    assert_eq!(prompts.for_model("codellama:7b"), "Follow the team style guide.");
+    assert_eq!(prompts.for_model("codellama:13b"), "Big model.");
+    assert_eq!(prompts.for_model("llama3"), DEFAULT_SYSTEM_PROMPT);
+
+    fs::write(&path, r#"{ "default": 3 }"#)?;
+    assert_eq!(SystemPrompts::load(&path).unwrap_err().code(), "invalid_input");
+
+    Ok(())
+}
@@ -0,0 +1,991 @@
+use futures::StreamExt;
+use llama_pack::agent::{Agent, ReadFileTool, SearchCodeTool, Tool};
+use llama_pack::backend::{BackendConfig, LlmBackend, OpenAiClient};
+use llama_pack::lancedb::{EmbeddingRecord, LanceDbClient};
+use llama_pack::ollama_client::{
+    blocking, ChatMessage, CreateModel, Format, GenerateOptions, GenerationChunk, KeepAlive, ModelProfiles,
+    Backoff, DaemonConfig, DaemonManager, DaemonMode, DaemonStatus, Modelfile, OllamaClient, OllamaConfig,
+    PullProgress, Role,
+};
+use llama_pack::ollama_client::builder::parse_host;
+use llama_pack::ollama_client::structured::validate;
+use llama_pack::Error;
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Write};
+use std::fs;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod support;
+
+use support::{closed_port_url, serve, serve_recording};
+
+// ========== ASYNC CLIENT TESTS ==========
+
+#[tokio::test]
+async fn test_query_model_joins_streamed_chunks() -> Result<()> {
+    let stream = [
+        r#"{"model":"m","response":"Hello","done":false}"#,
+        r#"{"model":"m","response":", world","done":false}"#,
+        r#"{"model":"m","response":"","done":true}"#,
+    ]
+    .join("\n") + "\n";
+    let url = serve(vec![("/api/generate", 200, stream)])?;
+
+    let client = OllamaClient::with_base_url(&url);
+    assert_eq!(client.query_model("m", "hi").await?, "Hello, world");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_validate_daemon_and_errors() -> Result<()> {
+    let url = serve(vec![
+        ("/api/tags", 200, r#"{"models":[]}"#.to_string()),
+        ("/api/generate", 404, r#"{"error":"model 'ghost' not found"}"#.to_string()),
+    ])?;
+    let client = OllamaClient::with_base_url(&url);
+    assert!(client.validate_daemon().await?);
+
+    let err = client.query_model("ghost", "hi").await.unwrap_err();
+    assert!(matches!(&err, Error::ModelNotFound(model) if model == "ghost"));
+
+    let offline = OllamaClient::with_base_url(&closed_port_url()?);
+    let err = offline.validate_daemon().await.unwrap_err();
+    assert_eq!(err.code(), "ollama.daemon_not_running");
+
+    Ok(())
+}
+
+// ========== STREAMING TESTS ==========
+
+#[tokio::test]
+async fn test_generate_stream_yields_typed_chunks() -> Result<()> {
+    let stream = [
+        r#"{"response":"fn","done":false}"#,
+        "",
+        r#"{"response":" main","done":false}"#,
+        r#"{"response":"","done":true,"done_reason":"stop","prompt_eval_count":7,"eval_count":2,"eval_duration":500000000,"total_duration":900000000}"#,
+    ]
+    .join("\n");
+    let url = serve(vec![("/api/generate", 200, stream)])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let chunks: Vec<GenerationChunk> = client
+        .generate_stream("m", "write main", &GenerateOptions::default())
+        .await?
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[0].text, "fn");
+    assert!(chunks[0].stats.is_none());
+    let last = chunks.last().unwrap();
+    assert!(last.done);
+    assert_eq!(last.done_reason.as_deref(), Some("stop"));
+    let stats = last.stats.as_ref().unwrap();
+    assert_eq!(stats.prompt_eval_count, 7);
+    assert_eq!(stats.eval_count, 2);
+    assert_eq!(stats.tokens_per_second(), Some(4.0));
+
+    let mut seen = Vec::new();
+    let full = client.query_model_with("m", "write main", |chunk| seen.push(chunk.text.clone())).await?;
+    assert_eq!(full, "fn main");
+    assert_eq!(seen, vec!["fn", " main", ""]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_generate_stream_surfaces_mid_stream_errors() -> Result<()> {
+    let stream = [r#"{"response":"partial","done":false}"#, r#"{"error":"model crashed"}"#].join("\n") + "\n";
+    let url = serve(vec![("/api/generate", 200, stream)])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let err = client.query_model("m", "hi").await.unwrap_err();
+    assert!(matches!(&err, Error::Api { message, .. } if message == "model crashed"));
+
+    Ok(())
+}
+
+// ========== CHAT TESTS ==========
+
+#[tokio::test]
+async fn test_chat_sends_history_and_joins_reply() -> Result<()> {
+    let stream = [
+        r#"{"message":{"role":"assistant","content":"It returns"},"done":false}"#,
+        r#"{"message":{"role":"assistant","content":" 42."},"done":false}"#,
+        r#"{"message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","eval_count":3}"#,
+    ]
+    .join("\n") + "\n";
+    let (url, requests) = serve_recording(vec![("/api/chat", 200, stream)])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let messages = vec![
+        ChatMessage::system("be brief"),
+        ChatMessage::user("what does answer() do?"),
+        ChatMessage::assistant("Which file?"),
+        ChatMessage::tool("read_file", "fn answer() -> u32 { 42 }"),
+        ChatMessage::user("src/lib.rs"),
+    ];
+    let mut last = None;
+    let reply = client.chat_with("m", &messages, |chunk| last = Some(chunk.clone())).await?;
+    assert_eq!(reply, ChatMessage::assistant("It returns 42."));
+    let last = last.unwrap();
+    assert!(last.done);
+    assert_eq!(last.stats.unwrap().eval_count, 3);
+
+    let requests = requests.lock().unwrap();
+    let sent = &requests[0]["messages"];
+    let roles: Vec<&str> = sent.as_array().unwrap().iter().map(|m| m["role"].as_str().unwrap()).collect();
+    assert_eq!(roles, vec!["system", "user", "assistant", "tool", "user"]);
+    assert_eq!(sent[3]["tool_name"], "read_file");
+    assert!(sent[0].get("tool_name").is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_chat_unknown_model() -> Result<()> {
+    let url = serve(vec![("/api/chat", 404, r#"{"error":"model 'ghost' not found"}"#.to_string())])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let err = client.chat("ghost", &[ChatMessage::user("hi")]).await.unwrap_err();
+    assert!(matches!(&err, Error::ModelNotFound(model) if model == "ghost"));
+    assert_eq!(ChatMessage::user("hi").role, Role::User);
+
+    Ok(())
+}
+
+// ========== OPTIONS TESTS ==========
+
+#[tokio::test]
+async fn test_options_merge_over_model_profile() -> Result<()> {
+    let stream = r#"{"response":"ok","done":true}"#.to_string() + "\n";
+    let (url, requests) = serve_recording(vec![("/api/generate", 200, stream)])?;
+
+    let mut profiles = ModelProfiles::default();
+    profiles.insert("coder", GenerateOptions { temperature: Some(0.2), seed: Some(7), ..Default::default() });
+    profiles.insert("coder:13b", GenerateOptions { num_ctx: Some(16384), ..Default::default() });
+    let client = OllamaClient::with_base_url(&url).with_profiles(profiles);
+
+    // profile lookup falls back to the untagged name
+    client.query_model("coder:7b", "hi").await?;
+
+    let overrides = GenerateOptions {
+        seed: Some(42),
+        stop: vec!["\n\n".to_string()],
+        keep_alive: Some(KeepAlive::Forever),
+        format: Some(Format::Json),
+        ..Default::default()
+    };
+    client.generate_stream("coder:7b", "hi", &overrides).await?.collect::<Vec<_>>().await;
+    client.query_model("coder:13b", "hi").await?;
+    client.query_model("other", "hi").await?;
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0]["options"], serde_json::json!({ "temperature": 0.2, "seed": 7 }));
+    assert!(requests[0].get("keep_alive").is_none());
+
+    assert_eq!(requests[1]["options"]["seed"], 42);
+    assert_eq!(requests[1]["options"]["temperature"], 0.2);
+    assert_eq!(requests[1]["options"]["stop"], serde_json::json!(["\n\n"]));
+    assert_eq!(requests[1]["keep_alive"], -1);
+    assert_eq!(requests[1]["format"], "json");
+    assert!(requests[1]["options"].get("format").is_none());
+
+    assert_eq!(requests[2]["options"], serde_json::json!({ "num_ctx": 16384 }));
+    assert!(requests[3].get("options").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_keep_alive_wire_format() -> Result<()> {
+    use std::time::Duration;
+
+    assert_eq!(serde_json::to_value(KeepAlive::For(Duration::from_secs(300)))?, 300);
+    assert_eq!(serde_json::to_value(KeepAlive::Forever)?, -1);
+    // sub-second values must not truncate to 0, which unloads the model
+    assert_eq!(serde_json::to_value(KeepAlive::For(Duration::from_millis(500)))?, "500ms");
+    assert_eq!(serde_json::to_value(KeepAlive::For(Duration::from_micros(1500)))?, "2ms");
+
+    assert_eq!(serde_json::from_str::<KeepAlive>(r#""1h30m""#)?, KeepAlive::For(Duration::from_secs(5400)));
+    assert_eq!(serde_json::from_str::<KeepAlive>(r#""500ms""#)?, KeepAlive::For(Duration::from_millis(500)));
+    assert_eq!(serde_json::from_str::<KeepAlive>(r#""-1m""#)?, KeepAlive::Forever);
+    assert!(serde_json::from_str::<KeepAlive>(r#""soon""#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_model_profiles_load_from_json() -> Result<()> {
+    let temp_dir = tempfile::TempDir::new()?;
+    let path = temp_dir.path().join("profiles.json");
+    std::fs::write(
+        &path,
+        r#"{ "llama3": { "temperature": 0.7, "keep_alive": 300, "format": { "type": "object" } } }"#,
+    )?;
+
+    let profiles = ModelProfiles::load(&path)?;
+    let options = profiles.for_model("llama3:8b");
+    assert_eq!(options.temperature, Some(0.7));
+    assert_eq!(options.keep_alive, Some(KeepAlive::For(std::time::Duration::from_secs(300))));
+    assert_eq!(options.format, Some(Format::Schema(serde_json::json!({ "type": "object" }))));
+    assert_eq!(profiles.for_model("mistral"), GenerateOptions::default());
+
+    assert_eq!(ModelProfiles::builtin().for_model("codellama:7b").temperature, Some(0.2));
+
+    std::fs::write(&path, r#"{ "llama3": { "format": "yaml" } }"#)?;
+    assert_eq!(ModelProfiles::load(&path).unwrap_err().code(), "invalid_input");
+
+    Ok(())
+}
+
+// ========== STRUCTURED OUTPUT TESTS ==========
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct CommitMessage {
+    subject: String,
+    body: Option<String>,
+}
+
+fn commit_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "subject": { "type": "string" },
+            "body": { "type": ["string", "null"] }
+        },
+        "required": ["subject"],
+        "additionalProperties": false
+    })
+}
+
+fn chat_reply(content: &str) -> String {
+    json!({ "message": { "role": "assistant", "content": content }, "done": true }).to_string() + "\n"
+}
+
+#[tokio::test]
+async fn test_query_structured_retries_with_validation_error() -> Result<()> {
+    let (url, requests) = serve_recording(vec![
+        ("/api/chat", 200, chat_reply("Sure! Here it is")),
+        ("/api/chat", 200, chat_reply(r#"{"subject": 3}"#)),
+        ("/api/chat", 200, chat_reply(r#"{"subject": "Fix typo", "body": null}"#)),
+    ])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let message: CommitMessage = client.query_structured("m", "describe the diff", &commit_schema()).await?;
+    assert_eq!(message, CommitMessage { subject: "Fix typo".to_string(), body: None });
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0]["format"], commit_schema());
+    let retry = requests[2]["messages"].as_array().unwrap();
+    assert_eq!(retry.len(), 5);
+    assert_eq!(retry[3]["content"], r#"{"subject": 3}"#);
+    assert!(retry[4]["content"].as_str().unwrap().contains("$.subject: expected string, got number"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_structured_gives_up_after_attempts() -> Result<()> {
+    let url = serve(vec![("/api/chat", 200, chat_reply(r#"{"subject": "x", "extra": 1}"#))])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let err = client.query_structured::<CommitMessage>("m", "hi", &commit_schema()).await.unwrap_err();
+    assert!(matches!(&err, Error::InvalidResponse { attempts: 3, message } if message.contains("unexpected field 'extra'")));
+    assert_eq!(err.code(), "ollama.invalid_response");
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_reports_json_path() {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "edits": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": { "path": { "type": "string" }, "kind": { "enum": ["add", "delete"] } },
+                    "required": ["path"]
+                }
+            }
+        }
+    });
+
+    assert!(validate(&json!({ "edits": [{ "path": "a.rs", "kind": "add" }] }), &schema).is_ok());
+    assert_eq!(
+        validate(&json!({ "edits": [{ "path": "a.rs" }, {}] }), &schema).unwrap_err(),
+        "$.edits[1]: missing required field 'path'"
+    );
+    assert!(validate(&json!({ "edits": [{ "path": "a.rs", "kind": "move" }] }), &schema)
+        .unwrap_err()
+        .starts_with("$.edits[0].kind:"));
+    assert_eq!(validate(&json!([1]), &schema).unwrap_err(), "$: expected object, got array");
+}
+
+// ========== AGENT TESTS ==========
+
+fn tool_call_reply(name: &str, arguments: serde_json::Value) -> String {
+    json!({
+        "message": {
+            "role": "assistant",
+            "content": "",
+            "tool_calls": [{ "function": { "name": name, "arguments": arguments } }]
+        },
+        "done": true
+    })
+    .to_string()
+        + "\n"
+}
+
+#[tokio::test]
+async fn test_agent_runs_tool_calls_until_answer() -> Result<()> {
+    let repo = TempDir::new()?;
+    fs::write(repo.path().join("lib.rs"), "pub fn answer() -> u32 {\n    42\n}\n")?;
+
+    let (url, requests) = serve_recording(vec![
+        ("/api/chat", 200, tool_call_reply("read_file", json!({ "path": "lib.rs" }))),
+        ("/api/chat", 200, tool_call_reply("read_file", json!({ "file": "lib.rs" }))),
+        ("/api/chat", 200, chat_reply("answer() returns 42.")),
+    ])?;
+    let client = OllamaClient::with_base_url(&url);
+    let agent = Agent::new(&client, "m").with_tool(ReadFileTool::new(repo.path()));
+
+    let mut messages = vec![ChatMessage::user("what does answer() return?")];
+    let answer = agent.run(&mut messages).await?;
+    assert_eq!(answer.content, "answer() returns 42.");
+
+    let roles: Vec<Role> = messages.iter().map(|m| m.role).collect();
+    assert_eq!(roles, vec![Role::User, Role::Assistant, Role::Tool, Role::Assistant, Role::Tool, Role::Assistant]);
+    assert_eq!(messages[2].tool_name.as_deref(), Some("read_file"));
+    assert!(messages[2].content.contains("42"));
+    assert!(messages[4].content.starts_with("error: invalid arguments: $: missing required field 'path'"));
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0]["tools"][0]["type"], "function");
+    assert_eq!(requests[0]["tools"][0]["function"]["name"], "read_file");
+    assert_eq!(requests[1]["messages"][1]["tool_calls"][0]["function"]["name"], "read_file");
+    assert_eq!(requests[1]["messages"][2]["role"], "tool");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_agent_stops_at_step_limit() -> Result<()> {
+    let url = serve(vec![("/api/chat", 200, tool_call_reply("launch_rockets", json!({})))])?;
+    let client = OllamaClient::with_base_url(&url);
+    let agent = Agent::new(&client, "m").with_max_steps(2);
+
+    let mut messages = vec![ChatMessage::user("go")];
+    let err = agent.run(&mut messages).await.unwrap_err();
+    assert!(matches!(err, Error::StepLimit(2)));
+    assert_eq!(err.code(), "agent.step_limit");
+    assert_eq!(messages[2].content, "error: unknown tool 'launch_rockets'");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_file_tool_ranges_and_sandbox() -> Result<()> {
+    let repo = TempDir::new()?;
+    fs::write(repo.path().join("notes.txt"), "one\ntwo\nthree\nfour\n")?;
+    let tool = ReadFileTool::new(repo.path());
+    assert_eq!(tool.invoke(json!({ "path": "notes.txt", "start_line": 2, "end_line": 3 })).await?, "two\nthree\n");
+
+    let short = ReadFileTool::new(repo.path()).with_max_bytes(8);
+    assert_eq!(short.invoke(json!({ "path": "notes.txt" })).await?, "one\ntwo\n\n[truncated]");
+
+    let outside = repo.path().parent().unwrap().join(format!("outside-{}.txt", std::process::id()));
+    fs::write(&outside, "secret")?;
+    let escape = format!("../{}", outside.file_name().unwrap().to_str().unwrap());
+    let err = tool.invoke(json!({ "path": escape })).await.unwrap_err();
+    fs::remove_file(&outside)?;
+    assert_eq!(err.code(), "invalid_input");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_search_code_tool_formats_hits() -> Result<()> {
+    let db = TempDir::new()?;
+    let store = Arc::new(LanceDbClient::connect(db.path().to_str().unwrap()).await?);
+    store
+        .insert_embeddings(vec![EmbeddingRecord {
+            path: "src/parser.rs".to_string(),
+            hash: "h".to_string(),
+            embedding: vec![0.1; 768],
+            language: "rust".to_string(),
+            last_modified: 0,
+            last_accessed: 0,
+            line_count: 120,
+            imported_by: Vec::new(),
+            content_preview: Some("pub fn parse(input: &str) -> Ast".to_string()),
+            content: None,
+            byte_size: 0,
+            token_count: 0,
+            symbols: vec!["parse".to_string()],
+            is_test: false,
+            is_generated: false,
+        }])
+        .await?;
+
+    let tool = SearchCodeTool::with_embed_fn(Arc::clone(&store), |_| Ok(vec![0.1; 768]));
+    assert_eq!(tool.spec().name, "search_code");
+    let output = tool.invoke(json!({ "query": "parser entry point" })).await?;
+    assert_eq!(output, "src/parser.rs (rust, 120 lines)\npub fn parse(input: &str) -> Ast");
+
+    // results went into the prompt, so they count as accessed
+    assert!(store.get_embedding("src/parser.rs").await?.unwrap().last_accessed > 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_file_tool_marks_file_accessed() -> Result<()> {
+    let repo = TempDir::new()?;
+    fs::create_dir_all(repo.path().join("src"))?;
+    fs::write(repo.path().join("src/lib.rs"), "pub fn lib() {}\n")?;
+
+    let db = TempDir::new()?;
+    let store = Arc::new(LanceDbClient::connect(db.path().to_str().unwrap()).await?);
+    store
+        .insert_embeddings(vec![EmbeddingRecord {
+            path: "src/lib.rs".to_string(),
+            hash: "h".to_string(),
+            embedding: vec![0.1; 768],
+            language: "rust".to_string(),
+            last_modified: 0,
+            last_accessed: 0,
+            line_count: 1,
+            imported_by: Vec::new(),
+            content_preview: None,
+            content: None,
+            byte_size: 0,
+            token_count: 0,
+            symbols: Vec::new(),
+            is_test: false,
+            is_generated: false,
+        }])
+        .await?;
+
+    let tool = ReadFileTool::new(repo.path()).with_store(Arc::clone(&store));
+    assert_eq!(tool.invoke(json!({ "path": "src/lib.rs" })).await?, "pub fn lib() {}\n");
+    assert!(store.get_embedding("src/lib.rs").await?.unwrap().last_accessed > 0);
+
+    Ok(())
+}
+
+// ========== MODEL MANAGEMENT TESTS ==========
+
+#[tokio::test]
+async fn test_pull_streams_progress() -> Result<()> {
+    let stream = [
+        r#"{"status":"pulling manifest"}"#,
+        r#"{"status":"pulling 6a0746a1ec1a","digest":"sha256:6a07","total":1000,"completed":250}"#,
+        r#"{"status":"pulling 6a0746a1ec1a","digest":"sha256:6a07","total":1000,"completed":1000}"#,
+        r#"{"status":"success"}"#,
+    ]
+    .join("\n") + "\n";
+    let (url, requests) = serve_recording(vec![("/api/pull", 200, stream)])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let mut updates: Vec<PullProgress> = Vec::new();
+    client.pull_with("codellama:7b", |progress| updates.push(progress.clone())).await?;
+    assert_eq!(updates.len(), 4);
+    assert_eq!(updates[1].completed, Some(250));
+    assert_eq!(updates[1].total, Some(1000));
+    assert!(updates[3].is_success());
+    assert_eq!(requests.lock().unwrap()[0]["model"], "codellama:7b");
+
+    let url = serve(vec![("/api/pull", 500, r#"{"error":"pull model manifest: file does not exist"}"#.to_string())])?;
+    let err = OllamaClient::with_base_url(&url).pull("nope").await.unwrap_err();
+    assert!(matches!(&err, Error::Api { status: 500, message } if message.contains("file does not exist")));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_show_copy_delete() -> Result<()> {
+    let show = json!({
+        "modelfile": "FROM codellama:7b",
+        "parameters": "temperature 0.2",
+        "template": "{{ .Prompt }}",
+        "details": { "family": "llama", "parameter_size": "7B", "quantization_level": "Q4_0" },
+        "model_info": { "llama.context_length": 16384 },
+        "capabilities": ["completion"]
+    });
+    let (url, requests) = serve_recording(vec![
+        ("/api/create", 200, r#"{"status":"success"}"#.to_string()),
+        ("/api/show", 200, show.to_string()),
+        ("/api/copy", 200, String::new()),
+        ("/api/delete", 404, r#"{"error":"model 'ghost' not found"}"#.to_string()),
+    ])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let mut parameters = std::collections::BTreeMap::new();
+    parameters.insert("temperature".to_string(), json!(0.2));
+    client
+        .create_model(&CreateModel {
+            model: "coder".to_string(),
+            from: Some("codellama:7b".to_string()),
+            system: Some("be brief".to_string()),
+            parameters,
+            ..Default::default()
+        })
+        .await?;
+
+    let info = client.show_model("coder").await?;
+    assert_eq!(info.details.parameter_size, "7B");
+    assert_eq!(info.model_info["llama.context_length"], 16384);
+    assert_eq!(info.capabilities, vec!["completion"]);
+
+    client.copy_model("coder", "coder-backup").await?;
+    let err = client.delete_model("ghost").await.unwrap_err();
+    assert!(matches!(&err, Error::ModelNotFound(model) if model == "ghost"));
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests[0],
+        json!({
+            "model": "coder",
+            "from": "codellama:7b",
+            "system": "be brief",
+            "parameters": { "temperature": 0.2 },
+            "stream": false
+        })
+    );
+    assert_eq!(requests[2], json!({ "source": "coder", "destination": "coder-backup" }));
+    assert_eq!(requests[3], json!({ "model": "ghost" }));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_create_from_modelfile_uploads_adapters() -> Result<()> {
+    use sha2::{Digest, Sha256};
+
+    let dir = TempDir::new()?;
+    let adapter = dir.path().join("team.gguf");
+    fs::write(&adapter, b"lora weights")?;
+    let digest = format!("sha256:{:x}", Sha256::digest(b"lora weights"));
+    let blob_path: &'static str = Box::leak(format!("/api/blobs/{}", digest).into_boxed_str());
+
+    let (url, requests) = serve_recording(vec![
+        (blob_path, 404, String::new()),
+        (blob_path, 201, String::new()),
+        ("/api/create", 200, r#"{"status":"success"}"#.to_string()),
+    ])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let modelfile = Modelfile::from("llama3").adapter(adapter.to_str().unwrap()).system("be brief");
+    client.create_from_modelfile("team-llama", &modelfile).await?;
+
+    let requests = requests.lock().unwrap();
+    let create = requests.last().unwrap();
+    assert_eq!(create["model"], "team-llama");
+    assert_eq!(create["adapters"], json!({ "team.gguf": digest }));
+    assert_eq!(create["system"], "be brief");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_and_running_models() -> Result<()> {
+    let tags = json!({ "models": [
+        { "name": "codellama:7b", "size": 3825819519u64, "digest": "8fdf", "modified_at": "2024-05-01T10:00:00.5-07:00",
+          "details": { "family": "llama", "parameter_size": "7B" } }
+    ] });
+    let ps = json!({ "models": [
+        { "name": "codellama:7b", "size": 5137025024u64, "size_vram": 5137025024u64, "digest": "8fdf",
+          "expires_at": "2024-06-04T14:38:31.83753-07:00" }
+    ] });
+    let url = serve(vec![("/api/tags", 200, tags.to_string()), ("/api/ps", 200, ps.to_string())])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let models = client.list_models().await?;
+    assert_eq!(models[0].name, "codellama:7b");
+    assert_eq!(models[0].details.parameter_size, "7B");
+    assert!(models[0].modified_at.is_some());
+
+    let running = client.running_models().await?;
+    assert_eq!(running[0].size_vram, 5137025024);
+    assert!(running[0].expires_at.is_some());
+
+    Ok(())
+}
+
+// ========== BUILDER TESTS ==========
+
+// Accept one connection, answer `/api/tags` and hand back the raw request head.
+fn capture_request() -> Result<(String, thread::JoinHandle<String>)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut head = String::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            head.push_str(&line);
+        }
+        let body = r#"{"models":[]}"#;
+        write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body).unwrap();
+        head
+    });
+    Ok((url, handle))
+}
+
+#[test]
+fn test_parse_host_fills_in_scheme_and_port() {
+    assert_eq!(parse_host("gpu-box"), "http://gpu-box:11434");
+    assert_eq!(parse_host("gpu-box:8080"), "http://gpu-box:8080");
+    assert_eq!(parse_host(":8080"), "http://127.0.0.1:8080");
+    assert_eq!(parse_host(""), "http://127.0.0.1:11434");
+    assert_eq!(parse_host("http://10.0.0.2/"), "http://10.0.0.2:11434");
+    assert_eq!(parse_host("https://llm.internal/ollama"), "https://llm.internal/ollama");
+    assert_eq!(parse_host("[::1]"), "http://[::1]:11434");
+    assert_eq!(parse_host("[::1]:9000"), "http://[::1]:9000");
+}
+
+#[tokio::test]
+async fn test_builder_sends_auth_and_custom_headers() -> Result<()> {
+    let (url, request) = capture_request()?;
+    let config = OllamaConfig {
+        host: Some("http://ignored.invalid".to_string()),
+        bearer_token: Some("from-config".to_string()),
+        headers: [("X-Team".to_string(), "search".to_string())].into(),
+        ..Default::default()
+    };
+
+    let client = OllamaClient::builder()
+        .ignore_env()
+        .config(config)
+        .base_url(&url)
+        .bearer_token("s3cret")
+        .header("X-Request-Source", "llama_pack")
+        .connect_timeout(Duration::from_secs(2))
+        .build()?;
+    assert_eq!(client.base_url(), url);
+    assert!(client.validate_daemon().await?);
+
+    let head = request.join().unwrap().to_ascii_lowercase();
+    assert!(head.contains("authorization: bearer s3cret"));
+    assert!(head.contains("x-team: search"));
+    assert!(head.contains("x-request-source: llama_pack"));
+
+    let result = OllamaClient::builder().header("bad header", "x").build();
+    assert!(matches!(result, Err(Error::InvalidInput(_))));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_builder_config_file_and_host_fallback() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.path().join("ollama.json");
+    fs::write(&path, r#"{ "host": "gpu-box", "read_timeout_secs": 600 }"#)?;
+
+    let config = OllamaConfig::load(&path)?;
+    assert_eq!(config.read_timeout_secs, Some(600));
+    let client = OllamaClient::builder().ignore_env().config(config).build()?;
+    assert_eq!(client.base_url(), "http://gpu-box:11434");
+
+    let client = OllamaClient::builder().ignore_env().build()?;
+    assert_eq!(client.base_url(), "http://127.0.0.1:11434");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wait_for_daemon_backs_off_then_gives_up() -> Result<()> {
+    let backoff = Backoff { initial: Duration::from_millis(10), max_delay: Duration::from_millis(25), max_attempts: 4 };
+    let delays: Vec<Duration> = backoff.delays().collect();
+    assert_eq!(delays, vec![Duration::from_millis(10), Duration::from_millis(20), Duration::from_millis(25)]);
+
+    let client = OllamaClient::builder().base_url(&closed_port_url()?).backoff(backoff).build()?;
+    let started = std::time::Instant::now();
+    let err = client.wait_for_daemon().await.unwrap_err();
+    assert_eq!(err.code(), "ollama.daemon_not_running");
+    assert!(started.elapsed() >= Duration::from_millis(55));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_read_timeout_cuts_off_stalled_daemon() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    thread::spawn(move || {
+        let (_stream, _) = listener.accept().unwrap();
+        thread::sleep(Duration::from_secs(5));
+    });
+
+    let client = OllamaClient::builder().base_url(&url).read_timeout(Duration::from_millis(200)).build()?;
+    let err = client.query_model("m", "hi").await.unwrap_err();
+    assert!(matches!(&err, Error::Http(e) if e.is_timeout()));
+
+    Ok(())
+}
+
+// ========== DAEMON TESTS ==========
+
+fn daemon_config(dir: &TempDir, url: &str, mode: DaemonMode) -> DaemonConfig {
+    DaemonConfig {
+        log_file: dir.path().join("ollama.log"),
+        lock_file: dir.path().join("ollama.lock"),
+        ready_timeout: Duration::from_millis(500),
+        ..DaemonConfig::new(url, mode)
+    }
+}
+
+#[cfg(unix)]
+fn fake_ollama(dir: &TempDir, script: &str) -> Result<std::path::PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.path().join("ollama");
+    fs::write(&path, format!("#!/bin/sh\n{}\n", script))?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    Ok(path)
+}
+
+#[tokio::test]
+async fn test_daemon_reuses_running_daemon_and_detects_conflicts() -> Result<()> {
+    let dir = TempDir::new()?;
+    let url = serve(vec![("/api/tags", 200, r#"{"models":[]}"#.to_string())])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let mut daemon = DaemonManager::new(daemon_config(&dir, &url, DaemonMode::Shared));
+    assert_eq!(daemon.ensure_running(&client).await?, DaemonStatus::AlreadyRunning);
+    drop(daemon);
+    assert!(!dir.path().join("ollama.lock").exists());
+
+    let other = serve(vec![("/api/tags", 404, "not ollama".to_string())])?;
+    let mut daemon = DaemonManager::new(daemon_config(&dir, &other, DaemonMode::Owned));
+    let err = daemon.ensure_running(&OllamaClient::with_base_url(&other)).await.unwrap_err();
+    assert_eq!(err.code(), "ollama.port_conflict");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_daemon_start_failures_surface_logs() -> Result<()> {
+    let dir = TempDir::new()?;
+    let url = closed_port_url()?;
+    let client = OllamaClient::with_base_url(&url);
+
+    let mut config = daemon_config(&dir, &url, DaemonMode::Owned);
+    config.binary = dir.path().join("missing-ollama");
+    let err = DaemonManager::new(config).ensure_running(&client).await.unwrap_err();
+    assert_eq!(err.code(), "ollama.daemon_start_failed");
+
+    let mut config = daemon_config(&dir, &url, DaemonMode::Owned);
+    config.binary = fake_ollama(&dir, "echo \"listen tcp: bind: address already in use\" >&2; exit 1")?;
+    let err = DaemonManager::new(config).ensure_running(&client).await.unwrap_err();
+    assert!(err.to_string().contains("address already in use"), "{}", err);
+    let log = fs::read_to_string(dir.path().join("ollama.log"))?;
+    assert!(log.contains("llama_pack started ollama serve"));
+
+    let mut config = daemon_config(&dir, &url, DaemonMode::Owned);
+    config.binary = fake_ollama(&dir, "echo \"serving on $OLLAMA_HOST\"; exec sleep 30")?;
+    let started = std::time::Instant::now();
+    let err = DaemonManager::new(config).ensure_running(&client).await.unwrap_err();
+    assert!(err.to_string().contains("did not become ready"), "{}", err);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    let port = url.rsplit(':').next().unwrap();
+    assert!(fs::read_to_string(dir.path().join("ollama.log"))?.contains(&format!("serving on 127.0.0.1:{}", port)));
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_shared_daemon_stops_with_last_client() -> Result<()> {
+    let dir = TempDir::new()?;
+    let url = serve(vec![("/api/tags", 200, r#"{"models":[]}"#.to_string())])?;
+    let client = OllamaClient::with_base_url(&url);
+
+    // a "daemon" started by an earlier llama_pack process that has since exited
+    let mut shared_daemon = std::process::Command::new("sleep").arg("30").spawn()?;
+    let lock_file = dir.path().join("ollama.lock");
+    fs::write(&lock_file, json!({ "daemon_pid": shared_daemon.id(), "clients": [999_999_999u32] }).to_string())?;
+
+    let mut daemon = DaemonManager::new(daemon_config(&dir, &url, DaemonMode::Shared));
+    assert_eq!(daemon.ensure_running(&client).await?, DaemonStatus::Joined);
+    let lock: serde_json::Value = serde_json::from_str(&fs::read_to_string(&lock_file)?)?;
+    assert_eq!(lock["clients"], json!([std::process::id()]));
+
+    daemon.shutdown().await?;
+    assert!(!lock_file.exists());
+    assert!(shared_daemon.wait()?.code().is_none()); // killed by a signal
+
+    Ok(())
+}
+
+// ========== BLOCKING FACADE TESTS ==========
+
+#[test]
+fn test_blocking_facade_outside_runtime() -> Result<()> {
+    let stream = r#"{"response":"ok","done":true}"#.to_string() + "\n";
+    let url = serve(vec![
+        ("/api/tags", 200, r#"{"models":[]}"#.to_string()),
+        ("/api/generate", 200, stream),
+    ])?;
+
+    let client = blocking::OllamaClient::with_base_url(&url)?;
+    assert!(client.validate_daemon()?);
+    assert_eq!(client.query_model("m", "hi")?, "ok");
+
+    let mut chunks = 0;
+    assert_eq!(client.query_model_with("m", "hi", |_| chunks += 1)?, "ok");
+    assert_eq!(chunks, 1);
+
+    Ok(())
+}
+
+// ========== BACKEND TESTS ==========
+
+fn sse(events: &[&str]) -> String {
+    events.iter().map(|event| format!("data: {}\n\n", event)).collect()
+}
+
+#[tokio::test]
+async fn test_openai_chat_streams_server_sent_events() -> Result<()> {
+    let body = sse(&[
+        r#"{"choices":[{"delta":{"role":"assistant"},"finish_reason":null}]}"#,
+        r#"{"choices":[{"delta":{"content":"Hello"},"finish_reason":null}]}"#,
+        r#"{"choices":[{"delta":{"content":", world"},"finish_reason":"stop"}]}"#,
+        r#"{"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":3}}"#,
+        "[DONE]",
+    ]);
+    let (url, requests) = serve_recording(vec![("/v1/chat/completions", 200, body)])?;
+    let backend: Box<dyn LlmBackend> = Box::new(OpenAiClient::new(&format!("{}/v1/", url)));
+
+    let options = GenerateOptions { num_predict: Some(64), format: Some(Format::Json), ..Default::default() };
+    let messages = [ChatMessage::system("Be brief."), ChatMessage::user("hi")];
+    let mut chunks = Vec::new();
+    let reply = backend.reply("codellama:7b", &messages, &options, &mut |chunk| chunks.push(chunk.clone())).await?;
+
+    assert_eq!(reply, ChatMessage::assistant("Hello, world"));
+    assert_eq!(chunks[2].done_reason.as_deref(), Some("stop"));
+    assert_eq!(chunks.last().and_then(|chunk| chunk.stats.as_ref()).map(|stats| stats.eval_count), Some(3));
+
+    let request = requests.lock().unwrap()[0].clone();
+    assert_eq!(request["stream"], json!(true));
+    assert_eq!(request["messages"], json!([{ "role": "system", "content": "Be brief." }, { "role": "user", "content": "hi" }]));
+    assert_eq!(request["max_tokens"], json!(64));
+    assert_eq!(request["response_format"], json!({ "type": "json_object" }));
+    // the built-in codellama profile applies here too
+    assert_eq!(request["temperature"], json!(0.2));
+    assert!(request.get("options").is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_openai_embeddings_models_and_errors() -> Result<()> {
+    let url = serve(vec![
+        ("/v1/models", 200, r#"{"object":"list","data":[{"id":"qwen2.5-coder"},{"id":"nomic-embed"}]}"#.to_string()),
+        ("/v1/embeddings", 200, r#"{"data":[{"index":1,"embedding":[0.5]},{"index":0,"embedding":[0.25]}]}"#.to_string()),
+        ("/v1/embeddings", 500, r#"{"error":{"message":"out of memory","type":"server_error"}}"#.to_string()),
+    ])?;
+    let client = OpenAiClient::new(&url);
+
+    assert_eq!(client.list_models().await?, vec!["qwen2.5-coder", "nomic-embed"]);
+    let inputs = vec!["a".to_string(), "b".to_string()];
+    assert_eq!(client.embed("nomic-embed", &inputs).await?, vec![vec![0.25], vec![0.5]]);
+
+    let err = client.embed("nomic-embed", &inputs).await.unwrap_err();
+    assert!(matches!(&err, Error::Api { status: 500, message } if message == "out of memory"));
+    let err = client.chat_stream("ghost", &[ChatMessage::user("hi")], &GenerateOptions::default()).await.err().unwrap();
+    assert!(matches!(&err, Error::ModelNotFound(model) if model == "ghost"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_openai_agent_sends_tool_calls_and_results() -> Result<()> {
+    let repo = TempDir::new()?;
+    fs::write(repo.path().join("lib.rs"), "pub fn answer() -> u32 {\n    42\n}\n")?;
+
+    let tool_call = json!({
+        "choices": [{
+            "message": {
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": { "name": "read_file", "arguments": "{\"path\":\"lib.rs\"}" }
+                }]
+            },
+            "finish_reason": "tool_calls"
+        }]
+    });
+    let answer = json!({ "choices": [{ "message": { "role": "assistant", "content": "It returns 42." } }] });
+    let (url, requests) = serve_recording(vec![
+        ("/v1/chat/completions", 200, tool_call.to_string()),
+        ("/v1/chat/completions", 200, answer.to_string()),
+    ])?;
+    let client = OpenAiClient::new(&url);
+    let agent = Agent::new(&client, "m").with_tool(ReadFileTool::new(repo.path()));
+
+    let mut messages = vec![ChatMessage::user("what does answer() return?")];
+    assert_eq!(agent.run(&mut messages).await?.content, "It returns 42.");
+    assert_eq!(messages[1].tool_calls[0].function.arguments, json!({ "path": "lib.rs" }));
+    assert!(messages[2].content.contains("42"));
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0]["stream"], json!(false));
+    assert_eq!(requests[0]["tools"][0]["function"]["name"], "read_file");
+    assert_eq!(
+        requests[1]["messages"][1]["tool_calls"],
+        json!([{ "id": "call_1", "type": "function", "function": { "name": "read_file", "arguments": "{\"path\":\"lib.rs\"}" } }])
+    );
+    assert_eq!(requests[1]["messages"][2]["role"], "tool");
+    assert_eq!(requests[1]["messages"][2]["tool_call_id"], "call_1");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_backend_config_selects_client() -> Result<()> {
+    let url = serve(vec![
+        ("/api/tags", 200, r#"{"models":[{"name":"llama3:8b"}]}"#.to_string()),
+        ("/api/embed", 200, r#"{"model":"m","embeddings":[[0.1,0.2]]}"#.to_string()),
+        ("/v1/models", 200, r#"{"data":[{"id":"served"}]}"#.to_string()),
+    ])?;
+    let temp_dir = TempDir::new()?;
+    let path = temp_dir.path().join("backend.json");
+
+    fs::write(&path, json!({ "kind": "openai", "base_url": url }).to_string())?;
+    let backend = BackendConfig::load(&path)?.connect()?;
+    assert_eq!(backend.name(), "openai");
+    assert_eq!(backend.list_models().await?, vec!["served"]);
+
+    fs::write(&path, json!({ "kind": "ollama", "host": url }).to_string())?;
+    let backend = BackendConfig::load(&path)?.connect()?;
+    assert_eq!(backend.name(), "ollama");
+    assert_eq!(backend.list_models().await?, vec!["llama3:8b"]);
+    assert_eq!(backend.embed("m", &["x".to_string()]).await?, vec![vec![0.1, 0.2]]);
+
+    fs::write(&path, r#"{ "kind": "bard" }"#)?;
+    assert_eq!(BackendConfig::load(&path).unwrap_err().code(), "invalid_input");
+
+    Ok(())
+}
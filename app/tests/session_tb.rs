@@ -0,0 +1,75 @@
+use llama_pack::ollama_client::{ChatMessage, Role};
+use llama_pack::session::{estimate_tokens, SessionManager, INTERRUPTED_MARKER};
+use anyhow::Result;
+use std::fs;
+use tempfile::TempDir;
+
+// ========== HISTORY TESTS ==========
+
+#[test]
+fn test_history_is_chronological_user_assistant_pairs() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut session = SessionManager::new_session_in(temp_dir.path())?;
+    assert!(session.history(1000).is_empty());
+
+    session.save_log("first question", "first answer")?;
+    session.save_log("second question", "second answer")?;
+
+    let history = session.history(1000);
+    assert_eq!(
+        history,
+        vec![
+            ChatMessage::user("first question"),
+            ChatMessage::assistant("first answer"),
+            ChatMessage::user("second question"),
+            ChatMessage::assistant("second answer"),
+        ]
+    );
+    assert!(temp_dir.path().join(".coder_sessions").is_dir());
+
+    Ok(())
+}
+
+#[test]
+fn test_history_keeps_newest_turns_within_budget() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut session = SessionManager::new_session_in(temp_dir.path())?;
+
+    // 40 + 40 chars = 20 estimated tokens per turn
+    let old = "o".repeat(40);
+    let new = "n".repeat(40);
+    session.save_log(&old, &old)?;
+    session.save_log(&new, &new)?;
+    assert_eq!(estimate_tokens(&new), 10);
+
+    let history = session.history(30);
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0], ChatMessage::user(&new));
+    assert_eq!(history[1].role, Role::Assistant);
+
+    assert_eq!(session.history(40).len(), 4);
+    assert!(session.history(19).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_interrupted_replies_are_marked() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let mut session = SessionManager::new_session_in(temp_dir.path())?;
+
+    session.save_log("complete", "full answer")?;
+    session.save_interrupted("explain lifetimes", "Lifetimes are")?;
+
+    let history = session.history(1000);
+    assert_eq!(history[1], ChatMessage::assistant("full answer"));
+    assert_eq!(history[3], ChatMessage::assistant(format!("Lifetimes are{}", INTERRUPTED_MARKER)));
+
+    let session_dir = fs::read_dir(temp_dir.path().join(".coder_sessions"))?.next().unwrap()?.path();
+    let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(session_dir.join("session.json"))?)?;
+    assert!(saved["logs"][0].get("interrupted").is_none());
+    assert_eq!(saved["logs"][1]["interrupted"], true);
+    assert_eq!(saved["logs"][1]["response"], "Lifetimes are");
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use llama_pack::source_analysis::{content_preview, extract_symbols, is_generated, is_test_path, PREVIEW_LEN};
+
+#[test]
+fn test_preview_rust_doc_and_signature() {
+    let code = "use std::fmt;\n\n/// Adds two numbers.\n/// Never overflows.\n#[inline]\npub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+    assert_eq!(
+        content_preview(code),
+        "/// Adds two numbers.\n/// Never overflows.\npub fn add(a: i32, b: i32) -> i32"
+    );
+}
+
+#[test]
+fn test_preview_python_comment_and_def() {
+    let code = "import os\n\n# Reverse a string.\ndef reverse(s):\n    return s[::-1]\n";
+    assert_eq!(content_preview(code), "# Reverse a string.\ndef reverse(s):");
+}
+
+#[test]
+fn test_preview_keeps_multi_line_signatures() {
+    let code = "/// Open a session.\npub async fn open(\n    path: &Path,\n    options: &Options,\n) -> Result<Session>\nwhere\n    Options: Clone,\n{\n    todo!()\n}\n";
+    assert_eq!(
+        content_preview(code),
+        "/// Open a session.\npub async fn open(\n    path: &Path,\n    options: &Options,\n) -> Result<Session>\nwhere\n    Options: Clone,"
+    );
+
+    let code = "export function render(\n  props: Props,\n  ctx: Context\n): Node {\n  return null;\n}\n";
+    assert_eq!(content_preview(code), "export function render(\n  props: Props,\n  ctx: Context\n): Node");
+
+    let code = "def load(\n    path,\n    strict=False,\n):\n    pass\n";
+    assert_eq!(content_preview(code), "def load(\n    path,\n    strict=False,\n):");
+}
+
+#[test]
+fn test_preview_python_docstrings() {
+    let code = "def reverse(s):\n    \"\"\"Reverse a string.\"\"\"\n    return s[::-1]\n";
+    assert_eq!(content_preview(code), "def reverse(s):\n    \"\"\"Reverse a string.\"\"\"");
+
+    let code = "class Cache:\n\n    \'\'\'LRU cache.\n\n    Not thread safe.\n    \'\'\'\n    def get(self, key):\n        pass\n";
+    assert_eq!(content_preview(code), "class Cache:\n    \'\'\'LRU cache.\n\n    Not thread safe.\n    \'\'\'");
+
+    // a string that isn't the first statement is not a docstring
+    let code = "def greet():\n    name = \"world\"\n    return name\n";
+    assert_eq!(content_preview(code), "def greet():");
+}
+
+#[test]
+fn test_preview_skips_imports() {
+    let code = "pub use crate::thing;\nimport x from 'y';\nexport async function load(url) {\n  return fetch(url);\n}\n";
+    assert_eq!(content_preview(code), "export async function load(url)");
+}
+
+#[test]
+fn test_preview_without_definition_uses_leading_lines() {
+    let code = "\n\nSELECT *\nFROM users\nWHERE id = 1;\n";
+    assert_eq!(content_preview(code), "SELECT *\nFROM users\nWHERE id = 1;");
+}
+
+#[test]
+fn test_preview_is_capped() {
+    let long_doc = format!("/// {}\nfn long() {{}}\n", "x".repeat(PREVIEW_LEN * 2));
+    assert_eq!(content_preview(&long_doc).chars().count(), PREVIEW_LEN);
+}
+
+#[test]
+fn test_extract_symbols() {
+    let code = "pub struct Client;\nimpl Client {\n    pub async fn connect() {}\n    fn connect() {}\n}\nenum Mode { A }\n";
+    assert_eq!(extract_symbols(code), vec!["Client", "connect", "Mode"]);
+
+    let python = "class Parser:\n    def parse(self):\n        pass\n";
+    assert_eq!(extract_symbols(python), vec!["Parser", "parse"]);
+
+    assert!(extract_symbols("let x = 1;").is_empty());
+}
+
+#[test]
+fn test_is_test_path() {
+    assert!(is_test_path("app/tests/lancedb_tb.rs"));
+    assert!(is_test_path("pkg/server_test.go"));
+    assert!(is_test_path("src/test_parser.py"));
+    assert!(is_test_path("web/button.spec.tsx"));
+    assert!(is_test_path("fixtures/sample.json.rs"));
+    assert!(!is_test_path("src/testing_utils.rs"));
+    assert!(!is_test_path("src/main.rs"));
+}
+
+#[test]
+fn test_is_generated() {
+    assert!(is_generated("api/service.pb.go", "package api"));
+    assert!(is_generated("proto/msg_pb2.py", ""));
+    assert!(is_generated("src/gen/bindings.rs", "pub fn f() {}"));
+    assert!(is_generated("src/bindings.rs", "// @generated by protoc\npub fn f() {}"));
+    assert!(is_generated("src/schema.ts", "/* Code automatically generated. DO NOT EDIT. */"));
+    assert!(!is_generated("src/main.rs", "fn main() {}"));
+}
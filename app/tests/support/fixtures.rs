@@ -0,0 +1,63 @@
+//! Embedding records and indexer configs shared by the indexing tests.
+
+use anyhow::Result;
+use llama_pack::embeddings_controller::{hash_content, IndexerConfig};
+use llama_pack::lancedb::EmbeddingRecord;
+use std::fs;
+use std::path::Path;
+
+pub const MODEL_PATH: &str = "../models/UniXcoder/unixcoder-embedding.onnx";
+pub const TOKENIZER_PATH: &str = "../models/UniXcoder/tokenizer.json";
+
+// Config whose model can never load, so any re-embedding attempt fails loudly
+pub fn no_model_config() -> IndexerConfig {
+    IndexerConfig {
+        model_path: "does/not/exist.onnx".to_string(),
+        tokenizer_path: "does/not/exist.json".to_string(),
+        ..IndexerConfig::default()
+    }
+}
+
+// A stored row for `path` as if `content` had been embedded
+pub fn record_for(path: &str, content: &str) -> EmbeddingRecord {
+    EmbeddingRecord {
+        path: path.to_string(),
+        hash: hash_content(content),
+        embedding: vec![0.3; 768],
+        language: "rust".to_string(),
+        last_modified: 1640995200000,
+        last_accessed: 1640995200000,
+        line_count: 1,
+        imported_by: vec![],
+        content_preview: Some(content.to_string()),
+        content: Some(content.to_string()),
+        byte_size: content.len() as i64,
+        token_count: 0,
+        symbols: vec![],
+        is_test: false,
+        is_generated: false,
+    }
+}
+
+// Tokenizer that only knows a few words and has no unknown token, so
+// encoding any other word fails: files using one can't be embedded.
+// Written into `dir`; returns its path.
+pub fn picky_tokenizer(dir: &Path) -> Result<String> {
+    let words = ["<", "encoder", "-", "only", ">", "pub", "fn", "ok", "()", "{}"];
+    let vocab: serde_json::Map<String, serde_json::Value> =
+        words.iter().enumerate().map(|(id, word)| (word.to_string(), (id + 10).into())).collect();
+    let tokenizer = serde_json::json!({
+        "version": "1.0",
+        "truncation": null,
+        "padding": null,
+        "added_tokens": [],
+        "normalizer": null,
+        "pre_tokenizer": { "type": "Whitespace" },
+        "post_processor": null,
+        "decoder": null,
+        "model": { "type": "WordLevel", "vocab": vocab, "unk_token": "<unk>" }
+    });
+    let path = dir.join("picky-tokenizer.json");
+    fs::write(&path, tokenizer.to_string())?;
+    Ok(path.to_string_lossy().into_owned())
+}
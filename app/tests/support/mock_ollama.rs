@@ -0,0 +1,281 @@
+//! In-process stand-in for an Ollama daemon, so client tests run without a
+//! GPU or model downloads.
+//!
+//! Serves `/api/tags`, `/api/generate`, `/api/chat`, `/api/embed` and
+//! `/api/pull`. By default generate and chat echo the prompt, embed returns
+//! deterministic vectors and pull installs the model. Queue a `Reply` to
+//! script a stream or inject a failure for the next request to a path.
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::read_request;
+
+/// What the mock sends back for one request.
+#[derive(Clone, Debug)]
+pub enum Reply {
+    /// stream these pieces of text as generate or chat chunks, then a done line
+    Text(Vec<String>),
+    /// fail with this status and an Ollama-style `{"error": ...}` body
+    Status(u16, String),
+    /// stream the text, then an `{"error": ...}` line instead of the done line
+    ErrorAfter(Vec<String>, String),
+    /// stream the text, then close the connection mid-body
+    DropAfter(Vec<String>),
+    /// stream these NDJSON lines as they are
+    Lines(Vec<Value>),
+}
+
+impl Reply {
+    pub fn text(pieces: &[&str]) -> Self {
+        Reply::Text(pieces.iter().map(|piece| piece.to_string()).collect())
+    }
+}
+
+struct State {
+    models: Vec<String>,
+    scripts: HashMap<String, VecDeque<Reply>>,
+    requests: Vec<(String, Value)>,
+    latency: Duration,
+    chunk_delay: Duration,
+    embedding_dim: usize,
+    /// streams the client hung up on before they finished
+    disconnects: usize,
+}
+
+pub struct MockOllama {
+    url: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockOllama {
+    /// Start serving on a free local port with `llama3:8b` installed.
+    pub fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(State {
+            models: vec!["llama3:8b".to_string()],
+            scripts: HashMap::new(),
+            requests: Vec::new(),
+            latency: Duration::ZERO,
+            chunk_delay: Duration::ZERO,
+            embedding_dim: 4,
+            disconnects: 0,
+        }));
+
+        let shared = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = shared.clone();
+                thread::spawn(move || {
+                    let _ = handle(stream, &state);
+                });
+            }
+        });
+
+        Ok(MockOllama { url, state })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Replace the installed models.
+    pub fn with_models(self, models: &[&str]) -> Self {
+        self.state.lock().unwrap().models = models.iter().map(|model| model.to_string()).collect();
+        self
+    }
+
+    /// Wait this long before answering each request.
+    pub fn with_latency(self, latency: Duration) -> Self {
+        self.state.lock().unwrap().latency = latency;
+        self
+    }
+
+    /// Wait this long before each streamed line.
+    pub fn with_chunk_delay(self, delay: Duration) -> Self {
+        self.state.lock().unwrap().chunk_delay = delay;
+        self
+    }
+
+    pub fn with_embedding_dim(self, dim: usize) -> Self {
+        self.state.lock().unwrap().embedding_dim = dim;
+        self
+    }
+
+    /// Answer a later request to `path` with `reply`. Queued replies are used
+    /// in order; once they run out the default behaviour resumes.
+    pub fn enqueue(&self, path: &str, reply: Reply) -> &Self {
+        self.state.lock().unwrap().scripts.entry(path.to_string()).or_default().push_back(reply);
+        self
+    }
+
+    /// JSON bodies of every request to `path` so far, in order.
+    pub fn requests(&self, path: &str) -> Vec<Value> {
+        let state = self.state.lock().unwrap();
+        state.requests.iter().filter(|(p, _)| p == path).map(|(_, body)| body.clone()).collect()
+    }
+
+    pub fn models(&self) -> Vec<String> {
+        self.state.lock().unwrap().models.clone()
+    }
+
+    /// How many streams the client closed before the last line was sent.
+    pub fn disconnects(&self) -> usize {
+        self.state.lock().unwrap().disconnects
+    }
+}
+
+fn handle(mut stream: TcpStream, state: &Mutex<State>) -> Result<()> {
+    let (_, path, body) = read_request(&stream)?;
+    let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+    let (reply, latency, chunk_delay) = {
+        let mut state = state.lock().unwrap();
+        state.requests.push((path.clone(), body.clone()));
+        let scripted = state.scripts.get_mut(&path).and_then(VecDeque::pop_front);
+        let reply = match scripted {
+            Some(reply) => Some(reply),
+            None => default_reply(&mut state, &path, &body),
+        };
+        (reply, state.latency, state.chunk_delay)
+    };
+    thread::sleep(latency);
+
+    let model = body["model"].as_str().unwrap_or_default();
+    let sent = match reply {
+        None => write_json(&mut stream, 404, &json!({ "error": "not found" })),
+        Some(Reply::Status(status, message)) => write_json(&mut stream, status, &json!({ "error": message })),
+        Some(Reply::Lines(lines)) => write_stream(&mut stream, &lines, true, chunk_delay),
+        Some(Reply::Text(pieces)) => {
+            let mut lines = text_lines(&path, model, &pieces);
+            lines.push(done_line(&path, model, pieces.len()));
+            write_stream(&mut stream, &lines, true, chunk_delay)
+        }
+        Some(Reply::ErrorAfter(pieces, message)) => {
+            let mut lines = text_lines(&path, model, &pieces);
+            lines.push(json!({ "error": message }));
+            write_stream(&mut stream, &lines, true, chunk_delay)
+        }
+        Some(Reply::DropAfter(pieces)) => {
+            write_stream(&mut stream, &text_lines(&path, model, &pieces), false, chunk_delay)
+        }
+    };
+    if sent.is_err() {
+        state.lock().unwrap().disconnects += 1;
+    }
+    sent
+}
+
+/// what a real daemon would say; `None` for paths it doesn't serve
+fn default_reply(state: &mut State, path: &str, body: &Value) -> Option<Reply> {
+    let model = body["model"].as_str().unwrap_or_default();
+    let installed = state.models.iter().any(|m| m == model || *m == format!("{}:latest", model));
+    let missing = || Reply::Status(404, format!("model '{}' not found, try pulling it first", model));
+
+    match path {
+        "/api/tags" => {
+            let models: Vec<Value> = state.models.iter().map(|name| json!({ "name": name, "model": name })).collect();
+            Some(Reply::Lines(vec![json!({ "models": models })]))
+        }
+        "/api/generate" | "/api/chat" | "/api/embed" if !installed => Some(missing()),
+        "/api/generate" => Some(Reply::text(&["You said: ", body["prompt"].as_str().unwrap_or_default()])),
+        "/api/chat" => {
+            let last = body["messages"].as_array().and_then(|messages| messages.last());
+            let content = last.and_then(|message| message["content"].as_str()).unwrap_or_default();
+            Some(Reply::text(&["You said: ", content]))
+        }
+        "/api/embed" => {
+            let inputs: Vec<&str> = match &body["input"] {
+                Value::String(input) => vec![input.as_str()],
+                Value::Array(inputs) => inputs.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            let embeddings: Vec<Vec<f32>> = inputs.iter().map(|input| embedding(input, state.embedding_dim)).collect();
+            Some(Reply::Lines(vec![json!({ "model": model, "embeddings": embeddings })]))
+        }
+        "/api/pull" => {
+            let name = if model.contains(':') { model.to_string() } else { format!("{}:latest", model) };
+            if !state.models.contains(&name) {
+                state.models.push(name);
+            }
+            Some(Reply::Lines(vec![
+                json!({ "status": "pulling manifest" }),
+                json!({ "status": "pulling sha256:abc", "digest": "sha256:abc", "total": 100, "completed": 50 }),
+                json!({ "status": "pulling sha256:abc", "digest": "sha256:abc", "total": 100, "completed": 100 }),
+                json!({ "status": "success" }),
+            ]))
+        }
+        _ => None,
+    }
+}
+
+/// the same text always gets the same vector
+pub fn embedding(text: &str, dim: usize) -> Vec<f32> {
+    let seed = text.bytes().fold(7u32, |hash, byte| hash.wrapping_mul(31).wrapping_add(byte as u32));
+    (0..dim as u32).map(|i| (seed.wrapping_add(i * 97) % 1000) as f32 / 1000.0).collect()
+}
+
+fn text_lines(path: &str, model: &str, pieces: &[String]) -> Vec<Value> {
+    pieces
+        .iter()
+        .map(|piece| match path {
+            "/api/chat" => json!({ "model": model, "message": { "role": "assistant", "content": piece }, "done": false }),
+            _ => json!({ "model": model, "response": piece, "done": false }),
+        })
+        .collect()
+}
+
+fn done_line(path: &str, model: &str, eval_count: usize) -> Value {
+    let mut line = json!({
+        "model": model,
+        "done": true,
+        "done_reason": "stop",
+        "prompt_eval_count": 1,
+        "eval_count": eval_count,
+        "eval_duration": eval_count as u64 * 1_000_000,
+        "total_duration": eval_count as u64 * 1_000_000,
+    });
+    match path {
+        "/api/chat" => line["message"] = json!({ "role": "assistant", "content": "" }),
+        _ => line["response"] = json!(""),
+    }
+    line
+}
+
+fn write_json(stream: &mut TcpStream, status: u16, body: &Value) -> Result<()> {
+    let body = body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// send `lines` as NDJSON in chunked encoding; without `finish` the body is
+/// cut off before its terminating chunk
+fn write_stream(stream: &mut TcpStream, lines: &[Value], finish: bool, delay: Duration) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n"
+    )?;
+    for line in lines {
+        thread::sleep(delay);
+        let line = format!("{}\n", line);
+        write!(stream, "{:x}\r\n{}\r\n", line.len(), line)?;
+        stream.flush()?;
+    }
+    if finish {
+        write!(stream, "0\r\n\r\n")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! HTTP test doubles and fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+pub mod fixtures;
+pub mod mock_ollama;
+
+#[allow(unused_imports)]
+pub use fixtures::{no_model_config, picky_tokenizer, record_for, MODEL_PATH, TOKENIZER_PATH};
+#[allow(unused_imports)]
+pub use mock_ollama::{MockOllama, Reply};
+
+use anyhow::Result;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub type Requests = Arc<Mutex<Vec<serde_json::Value>>>;
+
+// Minimal HTTP server answering each request with `(status, body)` from `routes`,
+// matched by path; a path listed several times answers with each entry in turn,
+// then keeps repeating the last. Returns the base url.
+pub fn serve(routes: Vec<(&'static str, u16, String)>) -> Result<String> {
+    Ok(serve_recording(routes)?.0)
+}
+
+// Like `serve`, also collecting the JSON body of every request.
+pub fn serve_recording(routes: Vec<(&'static str, u16, String)>) -> Result<(String, Requests)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    let requests = Requests::default();
+
+    let recorded = requests.clone();
+    let mut routes = routes;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = respond(stream, &mut routes, &recorded);
+        }
+    });
+
+    Ok((url, requests))
+}
+
+fn respond(mut stream: TcpStream, routes: &mut Vec<(&'static str, u16, String)>, requests: &Requests) -> Result<()> {
+    let (_, path, body) = read_request(&stream)?;
+    if let Ok(json) = serde_json::from_slice(&body) {
+        requests.lock().unwrap().push(json);
+    }
+
+    let matching: Vec<usize> = (0..routes.len()).filter(|&i| routes[i].0 == path).collect();
+    let (status, body) = match matching.as_slice() {
+        [] => (404, String::new()),
+        [only] => (routes[*only].1, routes[*only].2.clone()),
+        [first, ..] => {
+            let (_, status, body) = routes.remove(*first);
+            (status, body)
+        }
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+// Read one request off `stream`: method, path and body.
+pub fn read_request(stream: &TcpStream) -> Result<(String, String, Vec<u8>)> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("GET").to_string();
+    let path = parts.next().unwrap_or("/").to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        if header.trim().is_empty() {
+            break;
+        }
+        if let Some(value) = header.to_ascii_lowercase().strip_prefix("content-length:") {
+            content_length = value.trim().parse()?;
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok((method, path, body))
+}
+
+pub fn closed_port_url() -> Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}", listener.local_addr()?);
+    drop(listener);
+    Ok(url)
+}
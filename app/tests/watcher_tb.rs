@@ -0,0 +1,307 @@
+use llama_pack::embeddings_controller::{hash_content, IndexerConfig};
+use llama_pack::lancedb::LanceDbClient;
+use llama_pack::watcher::{watch_directory, watch_directory_with, WatchConfig};
+use anyhow::Result;
+use std::fs;
+use std::sync::Mutex;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::sync::oneshot;
+
+mod support;
+
+use support::{no_model_config, picky_tokenizer, record_for, MODEL_PATH};
+
+// Watcher config with a short debounce around `indexer`
+fn watch_config(indexer: IndexerConfig) -> WatchConfig {
+    WatchConfig { indexer, debounce: Duration::from_millis(200) }
+}
+
+// Poll until `check` holds or the deadline passes
+async fn wait_for<F, Fut>(mut check: F) -> Result<bool>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<bool>>,
+{
+    for _ in 0..50 {
+        if check().await? {
+            return Ok(true);
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Ok(false)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watch_rename_updates_path_without_reembedding() -> Result<()> {
+    let source_dir = TempDir::new()?;
+    let content = "fn moved() {}";
+    fs::write(source_dir.path().join("old.rs"), content)?;
+
+    let db_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.insert_embeddings(vec![record_for("old.rs", content)]).await?;
+
+    let config = watch_config(no_model_config());
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+    let watch = watch_directory(&client, source_dir.path(), &config, async {
+        let _ = stop_rx.await;
+    });
+
+    let actions = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        fs::rename(source_dir.path().join("old.rs"), source_dir.path().join("new.rs"))?;
+
+        let renamed = wait_for(|| async { Ok(client.get_embedding("new.rs").await?.is_some()) }).await?;
+        let _ = stop_tx.send(());
+        Ok::<bool, anyhow::Error>(renamed)
+    };
+
+    let (watch_result, renamed) = tokio::join!(watch, actions);
+    watch_result?;
+    assert!(renamed?);
+
+    assert!(client.get_embedding("old.rs").await?.is_none());
+    let record = client.get_embedding("new.rs").await?.unwrap();
+    assert_eq!(record.embedding, vec![0.3; 768]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watch_rename_over_existing_file_leaves_one_row() -> Result<()> {
+    let source_dir = TempDir::new()?;
+    let content = "fn saved() {}";
+    fs::write(source_dir.path().join("tmp.rs"), content)?;
+    fs::write(source_dir.path().join("lib.rs"), "fn original() {}")?;
+
+    let db_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.insert_embeddings(vec![
+        record_for("tmp.rs", content),
+        record_for("lib.rs", "fn original() {}"),
+    ]).await?;
+
+    let config = watch_config(no_model_config());
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+    let watch = watch_directory(&client, source_dir.path(), &config, async {
+        let _ = stop_rx.await;
+    });
+
+    let actions = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        // save-via-rename: the new content replaces an already indexed file
+        fs::rename(source_dir.path().join("tmp.rs"), source_dir.path().join("lib.rs"))?;
+
+        let saved = wait_for(|| async {
+            Ok(client.list_hashes().await?.get("lib.rs") == Some(&hash_content(content)))
+        }).await?;
+        let _ = stop_tx.send(());
+        Ok::<bool, anyhow::Error>(saved)
+    };
+
+    let (watch_result, saved) = tokio::join!(watch, actions);
+    watch_result?;
+    assert!(saved?);
+
+    assert!(client.get_embedding("tmp.rs").await?.is_none());
+    let results = client.query_similar(&[0.3; 768], 10).await?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "lib.rs");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watch_removed_files_are_deleted() -> Result<()> {
+    let source_dir = TempDir::new()?;
+    fs::create_dir_all(source_dir.path().join("src/nested"))?;
+    fs::write(source_dir.path().join("src/main.rs"), "fn main() {}")?;
+    fs::write(source_dir.path().join("src/nested/a.rs"), "fn a() {}")?;
+    fs::write(source_dir.path().join("src/nested/b.rs"), "fn b() {}")?;
+
+    let db_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.insert_embeddings(vec![
+        record_for("src/main.rs", "fn main() {}"),
+        record_for("src/nested/a.rs", "fn a() {}"),
+        record_for("src/nested/b.rs", "fn b() {}"),
+    ]).await?;
+
+    let config = watch_config(no_model_config());
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+    let watch = watch_directory(&client, source_dir.path(), &config, async {
+        let _ = stop_rx.await;
+    });
+
+    let actions = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        fs::remove_file(source_dir.path().join("src/main.rs"))?;
+        fs::remove_dir_all(source_dir.path().join("src/nested"))?;
+
+        let emptied = wait_for(|| async { Ok(client.list_hashes().await?.is_empty()) }).await?;
+        let _ = stop_tx.send(());
+        Ok::<bool, anyhow::Error>(emptied)
+    };
+
+    let (watch_result, emptied) = tokio::join!(watch, actions);
+    watch_result?;
+    assert!(emptied?);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watch_ignores_unchanged_and_hidden_files() -> Result<()> {
+    let source_dir = TempDir::new()?;
+    let content = "fn same() {}";
+    fs::write(source_dir.path().join("same.rs"), content)?;
+    fs::create_dir_all(source_dir.path().join(".vector_store"))?;
+
+    let db_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.insert_embeddings(vec![record_for("same.rs", content)]).await?;
+
+    let config = watch_config(no_model_config());
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+    let watch = watch_directory(&client, source_dir.path(), &config, async {
+        let _ = stop_rx.await;
+    });
+
+    let actions = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        // rewriting identical content and touching hidden dirs must not need the model
+        fs::write(source_dir.path().join("same.rs"), content)?;
+        fs::write(source_dir.path().join(".vector_store/data.rs"), "fn hidden() {}")?;
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        let _ = stop_tx.send(());
+        Ok::<(), anyhow::Error>(())
+    };
+
+    let (watch_result, actions_result) = tokio::join!(watch, actions);
+    watch_result?;
+    actions_result?;
+
+    let hashes = client.list_hashes().await?;
+    assert_eq!(hashes.len(), 1);
+    assert_eq!(hashes.get("same.rs"), Some(&hash_content(content)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_watch_deletes_files_that_become_empty_or_unreadable() -> Result<()> {
+    let source_dir = TempDir::new()?;
+    fs::write(source_dir.path().join("empty.rs"), "fn empty() {}")?;
+    fs::write(source_dir.path().join("binary.rs"), "fn binary() {}")?;
+
+    let db_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.insert_embeddings(vec![
+        record_for("empty.rs", "fn empty() {}"),
+        record_for("binary.rs", "fn binary() {}"),
+    ]).await?;
+
+    let config = watch_config(no_model_config());
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+    let watch = watch_directory(&client, source_dir.path(), &config, async {
+        let _ = stop_rx.await;
+    });
+
+    let actions = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        fs::write(source_dir.path().join("empty.rs"), "  \n")?;
+        fs::write(source_dir.path().join("binary.rs"), [0xff, 0xfe, 0x00, 0x81])?;
+
+        let emptied = wait_for(|| async { Ok(client.list_hashes().await?.is_empty()) }).await?;
+        let _ = stop_tx.send(());
+        Ok::<bool, anyhow::Error>(emptied)
+    };
+
+    let (watch_result, emptied) = tokio::join!(watch, actions);
+    watch_result?;
+    assert!(emptied?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_watch_returns_model_load_failures_on_current_thread_runtime() -> Result<()> {
+    let source_dir = TempDir::new()?;
+    fs::write(source_dir.path().join("lib.rs"), "fn old() {}")?;
+
+    let db_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(db_dir.path().to_str().unwrap()).await?;
+    client.insert_embeddings(vec![record_for("lib.rs", "fn old() {}")]).await?;
+
+    let config = watch_config(no_model_config());
+    let watch = tokio::time::timeout(
+        Duration::from_secs(5),
+        watch_directory(&client, source_dir.path(), &config, std::future::pending()),
+    );
+
+    let actions = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        fs::write(source_dir.path().join("lib.rs"), "fn new() {}")
+    };
+
+    let (watch_result, actions_result) = tokio::join!(watch, actions);
+    actions_result?;
+    let err = watch_result?.unwrap_err();
+    assert_eq!(err.code(), "embedder.tokenizer_error");
+
+    // the failed change leaves the old row in place
+    assert_eq!(client.list_hashes().await?.get("lib.rs"), Some(&hash_content("fn old() {}")));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_watch_records_file_failures_and_keeps_watching() -> Result<()> {
+    let source_dir = TempDir::new()?;
+    let db_dir = TempDir::new()?;
+    let client = LanceDbClient::connect(db_dir.path().join("store").to_str().unwrap()).await?;
+
+    let config = watch_config(IndexerConfig {
+        model_path: MODEL_PATH.to_string(),
+        tokenizer_path: picky_tokenizer(db_dir.path())?,
+        ..IndexerConfig::default()
+    });
+    let failed = Mutex::new(Vec::new());
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+    let watch = watch_directory_with(
+        &client,
+        source_dir.path(),
+        &config,
+        async {
+            let _ = stop_rx.await;
+        },
+        |stats| failed.lock().unwrap().extend(stats.failed.iter().map(|(path, _)| path.clone())),
+    );
+
+    let actions = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        fs::write(source_dir.path().join("unembeddable.rs"), "pub fn zzz_unknown() {}")?;
+        let recorded = wait_for(|| async { Ok(!failed.lock().unwrap().is_empty()) }).await?;
+
+        // the watch is still running and picks up the next change
+        fs::write(source_dir.path().join("ok.rs"), "pub fn ok() {}")?;
+        let indexed = wait_for(|| async { Ok(client.get_embedding("ok.rs").await?.is_some()) }).await?;
+        let _ = stop_tx.send(());
+        Ok::<bool, anyhow::Error>(recorded && indexed)
+    };
+
+    let (watch_result, watched) = tokio::join!(watch, actions);
+    watch_result?;
+    assert!(watched?);
+    assert!(failed.lock().unwrap().iter().all(|path| path == "unembeddable.rs"));
+    assert!(client.get_embedding("unembeddable.rs").await?.is_none());
+
+    Ok(())
+}